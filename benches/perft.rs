@@ -0,0 +1,304 @@
+// Nodes per second for the two ways of walking the move tree:
+// copying the State for every move and legality check, as before,
+// and making/unmaking in place on one State.
+// Run with `cargo bench --bench perft`.
+// Also checks each position against its reference node count where it has one
+// and against its colour flipped twin, and the variant positions against known counts.
+use std::time::Instant;
+
+use chess::{
+    consts,
+    effects::static_attack_provider::StaticAttackProvider,
+    engine::{move_gen, perft},
+    gamestate::{boardstate, move_lens::History, transform, variant},
+    traits::lens::MoveLens,
+};
+
+// Standard and Chess960 positions with their reference node counts. The killer
+// position has nine white pawns so no other engine will count it, it is only
+// checked against the copy walk and its flipped twin
+const POSITIONS: [(&str, &str, u32, Option<u64>); 18] = [
+    ("start", consts::START_POSITION, 5, Some(4865609)),
+    ("tricky", consts::TRICKY_POSITION, 4, Some(4085603)),
+    ("killer", consts::KILLER_POSITION, 4, None),
+    ("cmk", consts::CMK_POSITION, 4, Some(1679340)),
+    (
+        "cpw-3",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        5,
+        Some(674624),
+    ),
+    (
+        "cpw-4",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        4,
+        Some(422333),
+    ),
+    (
+        "cpw-4-mirrored",
+        "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+        4,
+        Some(422333),
+    ),
+    (
+        "cpw-5",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        4,
+        Some(2103487),
+    ),
+    (
+        "cpw-6",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        4,
+        Some(3894594),
+    ),
+    // Positions from the Chess960 perft suite, in Shredder-FEN
+    (
+        "960-1",
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        4,
+        Some(326672),
+    ),
+    (
+        "960-2",
+        "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+        4,
+        Some(667366),
+    ),
+    (
+        "960-3",
+        "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+        4,
+        Some(273318),
+    ),
+    (
+        "960-4",
+        "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9",
+        4,
+        Some(382958),
+    ),
+    (
+        "960-5",
+        "1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9",
+        4,
+        Some(1171749),
+    ),
+    (
+        "960-6",
+        "qnbnr1kr/ppp1b1pp/4p3/3p1p2/8/2NPP3/PPP1BPPP/QNB1R1KR w HEhe - 1 9",
+        4,
+        Some(824055),
+    ),
+    // The first again in X-FEN, the rights name the outermost rooks
+    (
+        "960-1-xfen",
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9",
+        4,
+        Some(326672),
+    ),
+    // Castling from the first move, the king takes its rook on either side
+    (
+        "960-castle-both",
+        "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1",
+        4,
+        Some(366277),
+    ),
+    // The king on b1 castles long by moving right, onto c1
+    (
+        "960-castle-b1",
+        "rk5r/pppppppp/8/8/8/8/PPPPPPPP/RK5R w HAha - 0 1",
+        4,
+        Some(317199),
+    ),
+];
+
+// Variant positions with their expected node counts
+const VARIANT_POSITIONS: [(&str, variant::Variant, &str, u32, u64); 16] = [
+    (
+        "atomic-start",
+        variant::Variant::Atomic,
+        consts::START_POSITION,
+        4,
+        197326,
+    ),
+    (
+        "programfox-1",
+        variant::Variant::Atomic,
+        "rn2kb1r/1pp1p2p/p2q1pp1/3P4/2P3b1/4PN2/PP3PPP/R2QKB1R b KQkq - 0 1",
+        4,
+        1434825,
+    ),
+    (
+        "programfox-2",
+        variant::Variant::Atomic,
+        "rn1qkb1r/p5pp/2p5/3p4/N3P3/5P2/PPP4P/R1BQK3 w Qkq - 0 1",
+        4,
+        714499,
+    ),
+    (
+        "atomic960-castle-1",
+        variant::Variant::Atomic,
+        "8/8/8/8/8/8/2k5/rR4KR w KQ - 0 1",
+        4,
+        61401,
+    ),
+    (
+        "atomic960-castle-2",
+        variant::Variant::Atomic,
+        "r3k1rR/5K2/8/8/8/8/8/8 b kq - 0 1",
+        4,
+        98729,
+    ),
+    (
+        "atomic960-castle-3",
+        variant::Variant::Atomic,
+        "Rr2k1rR/3K4/3p4/8/8/8/7P/8 w kq - 0 1",
+        4,
+        241478,
+    ),
+    (
+        "antichess-start",
+        variant::Variant::Antichess,
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1",
+        4,
+        153299,
+    ),
+    (
+        "a-pawn-vs-b-pawn",
+        variant::Variant::Antichess,
+        "8/1p6/8/8/8/8/P7/8 w - - 0 1",
+        6,
+        0,
+    ),
+    (
+        "a-pawn-vs-c-pawn",
+        variant::Variant::Antichess,
+        "8/2p5/8/8/8/8/P7/8 w - - 0 1",
+        12,
+        2557,
+    ),
+    (
+        "horde-start",
+        variant::Variant::Horde,
+        consts::HORDE_POSITION,
+        4,
+        23310,
+    ),
+    (
+        "horde-en-passant",
+        variant::Variant::Horde,
+        "k7/5p2/4p2P/3p2P1/2p2P2/1p2P2P/p2P2P1/2P2P2 w - - 0 1",
+        4,
+        33781,
+    ),
+    (
+        "crazyhouse-start",
+        variant::Variant::Crazyhouse,
+        consts::START_POSITION,
+        5,
+        4888832,
+    ),
+    (
+        "full-pockets",
+        variant::Variant::Crazyhouse,
+        "2k5/8/8/8/8/8/8/4K3[QRBNPqrbnp] w - - 0 1",
+        2,
+        75353,
+    ),
+    (
+        "promoted-queen",
+        variant::Variant::Crazyhouse,
+        "r1bq1r1k/ppppNppp/8/4Q~3/8/8/PPPP1PPP/R1B1K2R[NBnb] b KQ - 0 1",
+        3,
+        725873,
+    ),
+    (
+        "racingkings-start",
+        variant::Variant::RacingKings,
+        consts::RACING_KINGS_POSITION,
+        4,
+        296242,
+    ),
+    (
+        "occupied-goal",
+        variant::Variant::RacingKings,
+        "4brn1/2K2k2/8/8/8/8/8/8 w - - 0 1",
+        6,
+        265932,
+    ),
+];
+
+// The walk from before in-place make/unmake: every legality check copies the
+// State to make the move on, and the tree is walked by copying through the lens.
+// Only follows standard chess rules
+fn perft_copy(state: boardstate::State, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    move_gen::generate_moves(&state, StaticAttackProvider)
+        .filter(|mv| {
+            let mut next = state;
+            History.make_move(&mut next, *mv);
+            move_gen::leaves_king_safe(&next, state.side_to_move, StaticAttackProvider)
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|mv| perft_copy(History.apply_move(state, mv).0, depth - 1))
+        .sum()
+}
+
+fn nps(nodes: u64, start: Instant) -> f64 {
+    nodes as f64 / start.elapsed().as_secs_f64()
+}
+
+fn main() {
+    println!(
+        "{:<16} {:>5} {:>12} {:>14} {:>14}",
+        "position", "depth", "nodes", "copy nps", "in-place nps"
+    );
+
+    for (name, fen, depth, expected) in POSITIONS {
+        let mut state = boardstate::try_from_fen(fen).expect("Test positions are valid FEN");
+
+        let start = Instant::now();
+        let copy_nodes = perft_copy(state, depth);
+        let copy_nps = nps(copy_nodes, start);
+
+        let start = Instant::now();
+        let nodes = perft::perft(&mut state, depth, StaticAttackProvider);
+        let in_place_nps = nps(nodes, start);
+
+        if let Some(expected) = expected {
+            assert_eq!(nodes, expected, "{} perft {} is off", name, depth);
+        }
+        assert_eq!(copy_nodes, nodes, "Both walks must visit the same tree");
+        assert_eq!(
+            perft::perft(&mut transform::flip_colours(&state), depth, StaticAttackProvider),
+            nodes,
+            "Swapping colours must not change the move tree"
+        );
+        println!(
+            "{:<16} {:>5} {:>12} {:>14.0} {:>14.0}",
+            name, depth, nodes, copy_nps, in_place_nps
+        );
+    }
+
+    println!();
+    println!("{:<20} {:<10} {:>5} {:>12}", "variant position", "variant", "depth", "nodes");
+
+    for (name, rules, fen, depth, expected) in VARIANT_POSITIONS {
+        let mut state =
+            boardstate::try_from_fen_variant(fen, rules).expect("Test positions are valid FEN");
+        let nodes = perft::perft(&mut state, depth, StaticAttackProvider);
+
+        assert_eq!(nodes, expected, "{} perft {} is off", name, depth);
+        println!(
+            "{:<20} {:<10} {:>5} {:>12}",
+            name,
+            rules.uci_name(),
+            depth,
+            nodes
+        );
+    }
+}
@@ -1,6 +1,7 @@
-use crate::board::position;
-use itertools::Itertools;
+use crate::board::{colour, position};
+use crate::consts;
 use std::fmt;
+use std::iter::FusedIterator;
 use std::num::Wrapping;
 use std::ops::{
     BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Mul, MulAssign, Not, ShrAssign
@@ -163,21 +164,126 @@ impl Not for Bitboard {
     }
 }
 
-// === LSB iteration ===
-impl Iterator for Bitboard {
+// === Iteration ===
+// Iterates set squares from the least significant bit upwards,
+// without consuming the bitboard it came from
+#[derive(Debug, Clone, Copy)]
+pub struct Iter(u64);
+
+impl Iterator for Iter {
     type Item = position::Position;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.is_empty() {
-            None
+        if self.0 == 0 {
+            return None;
+        }
+        let lsb = self.0.trailing_zeros();
+        self.0 &= self.0 - 1;
+        position::Position::try_from(lsb as u8).ok()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.0.count_ones() as usize;
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for Iter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.0 == 0 {
+            return None;
+        }
+        let msb = 63 - self.0.leading_zeros();
+        self.0 ^= 1u64 << msb;
+        position::Position::try_from(msb as u8).ok()
+    }
+}
+
+impl ExactSizeIterator for Iter {}
+
+impl FusedIterator for Iter {}
+
+impl IntoIterator for Bitboard {
+    type Item = position::Position;
+    type IntoIter = Iter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter(self.0)
+    }
+}
+
+impl IntoIterator for &Bitboard {
+    type Item = position::Position;
+    type IntoIter = Iter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter(self.0)
+    }
+}
+
+impl FromIterator<position::Position> for Bitboard {
+    fn from_iter<I: IntoIterator<Item = position::Position>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(Bitboard::new(), |bb, pos| bb.set_bit(pos))
+    }
+}
+
+impl Extend<position::Position> for Bitboard {
+    fn extend<I: IntoIterator<Item = position::Position>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|pos| self.mutate_set_bit(pos));
+    }
+}
+
+// === Subset enumeration ===
+// Walks every subset of a mask using the Carry-Rippler trick,
+// starting at the empty set and ending with the mask itself
+#[derive(Debug, Clone, Copy)]
+pub struct Subsets {
+    mask: u64,
+    subset: u64,
+    done: bool,
+}
+
+impl Iterator for Subsets {
+    type Item = Bitboard;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let current = self.subset;
+        self.subset = self.subset.wrapping_sub(self.mask) & self.mask;
+        self.done = self.subset == 0;
+        Some(Bitboard(current))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            (0, Some(0))
         } else {
-            let lsb = self.get_ls1b()?;
-            self.mutate_pop_bit(lsb);
-            Some(lsb)
+            // Subsets are visited in increasing numerical order,
+            // so the bits of the mask select the index of the current subset
+            let visited = pext(self.subset, self.mask) as u128;
+            let total = 1u128 << self.mask.count_ones();
+            let remaining = (total - visited).min(usize::MAX as u128) as usize;
+            (remaining, Some(remaining))
         }
     }
 }
 
+// Software parallel bit extract of `value` under `mask`
+fn pext(value: u64, mask: u64) -> u64 {
+    Iter(mask)
+        .enumerate()
+        .fold(0u64, |acc, (i, pos)| {
+            if value & (1u64 << pos as u64) != 0 {
+                acc | (1u64 << i)
+            } else {
+                acc
+            }
+        })
+}
+
 // === Display trait ===
 impl fmt::Display for Bitboard {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -270,14 +376,7 @@ impl Bitboard {
 
     // How many bits are occupied in a bitboard
     pub fn count_bits(&self) -> usize {
-        let mut bb = Bitboard::from(self.0);
-
-        (0..64)
-            .take_while_inclusive(|_| {
-                bb.0 &= bb.0 - 1;
-                bb.0 > 0
-            })
-            .count()
+        self.popcount() as usize
     }
 
     // Get the index of the least significant bitboard
@@ -309,4 +408,274 @@ impl Bitboard {
     pub fn is_empty(&self) -> bool {
         self.0 == 0
     }
+
+    // Iterate the occupied positions without consuming the bitboard
+    pub fn iter(&self) -> Iter {
+        Iter(self.0)
+    }
+
+    // Every subset of the set bits, used to enumerate blocker
+    // configurations when building the magic attack tables
+    pub fn subsets(&self) -> Subsets {
+        Subsets {
+            mask: self.0,
+            subset: 0,
+            done: false,
+        }
+    }
+
+    // === Set algebra ===
+    pub const fn is_subset(&self, other: Bitboard) -> bool {
+        self.0 & !other.0 == 0
+    }
+
+    pub const fn is_superset(&self, other: Bitboard) -> bool {
+        other.0 & !self.0 == 0
+    }
+
+    pub const fn intersects(&self, other: Bitboard) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub const fn is_disjoint(&self, other: Bitboard) -> bool {
+        !self.intersects(other)
+    }
+}
+
+// === Directions ===
+// The eight compass directions, north being towards the 8th rank.
+// As A8 is bit 0, moving north shifts towards the least significant bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    North,
+    South,
+    East,
+    West,
+    NorthEast,
+    NorthWest,
+    SouthEast,
+    SouthWest,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+        Direction::NorthEast,
+        Direction::NorthWest,
+        Direction::SouthEast,
+        Direction::SouthWest,
+    ];
+
+    // The change in square index for a single step
+    pub const fn offset(&self) -> i8 {
+        match self {
+            Direction::North => -8,
+            Direction::South => 8,
+            Direction::East => 1,
+            Direction::West => -1,
+            Direction::NorthEast => -7,
+            Direction::NorthWest => -9,
+            Direction::SouthEast => 9,
+            Direction::SouthWest => 7,
+        }
+    }
+
+    // Mask of squares a step can legally land on,
+    // stops pieces wrapping around the board edge
+    pub const fn wrap_mask(&self) -> u64 {
+        match self {
+            Direction::North | Direction::South => u64::MAX,
+            Direction::East | Direction::NorthEast | Direction::SouthEast => consts::NOT_A_FILE,
+            Direction::West | Direction::NorthWest | Direction::SouthWest => consts::NOT_H_FILE,
+        }
+    }
+
+    pub const fn opp(&self) -> Self {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+            Direction::NorthEast => Direction::SouthWest,
+            Direction::NorthWest => Direction::SouthEast,
+            Direction::SouthEast => Direction::NorthWest,
+            Direction::SouthWest => Direction::NorthEast,
+        }
+    }
+}
+
+// Shift a raw u64 by a signed square offset
+const fn shift_raw(bb: u64, offset: i8) -> u64 {
+    if offset >= 0 {
+        bb << offset
+    } else {
+        bb >> -offset
+    }
+}
+
+// === Geometric operations ===
+impl Bitboard {
+    // Shift every set bit one step in a direction,
+    // bits pushed off the board are dropped
+    pub const fn shift(self, dir: Direction) -> Self {
+        Bitboard(shift_raw(self.0, dir.offset()) & dir.wrap_mask())
+    }
+
+    pub const fn north(self) -> Self {
+        self.shift(Direction::North)
+    }
+
+    pub const fn south(self) -> Self {
+        self.shift(Direction::South)
+    }
+
+    pub const fn east(self) -> Self {
+        self.shift(Direction::East)
+    }
+
+    pub const fn west(self) -> Self {
+        self.shift(Direction::West)
+    }
+
+    pub const fn ne(self) -> Self {
+        self.shift(Direction::NorthEast)
+    }
+
+    pub const fn nw(self) -> Self {
+        self.shift(Direction::NorthWest)
+    }
+
+    pub const fn se(self) -> Self {
+        self.shift(Direction::SouthEast)
+    }
+
+    pub const fn sw(self) -> Self {
+        self.shift(Direction::SouthWest)
+    }
+
+    // Kogge-Stone occluded fill
+    // Floods each set bit in a direction until it hits a non-empty square.
+    // The generator bits are included, the blocker squares are not.
+    pub const fn occluded_fill(self, dir: Direction, empty: Bitboard) -> Self {
+        let offset = dir.offset();
+        let mut gen = self.0;
+        let mut pro = empty.0 & dir.wrap_mask();
+
+        gen |= pro & shift_raw(gen, offset);
+        pro &= shift_raw(pro, offset);
+        gen |= pro & shift_raw(gen, offset * 2);
+        pro &= shift_raw(pro, offset * 2);
+        gen |= pro & shift_raw(gen, offset * 4);
+
+        Bitboard(gen)
+    }
+
+    // Sliding attacks in a single direction, includes the first blocker
+    pub const fn sliding_attacks(self, dir: Direction, empty: Bitboard) -> Self {
+        self.occluded_fill(dir, empty).shift(dir)
+    }
+
+    // Unoccluded fills, include the generator bits
+    pub const fn fill(self, dir: Direction) -> Self {
+        self.occluded_fill(dir, Bitboard(u64::MAX))
+    }
+
+    pub const fn north_fill(self) -> Self {
+        self.fill(Direction::North)
+    }
+
+    pub const fn south_fill(self) -> Self {
+        self.fill(Direction::South)
+    }
+
+    // Every file containing a set bit
+    pub const fn file_fill(self) -> Self {
+        Bitboard(self.north_fill().0 | self.south_fill().0)
+    }
+
+    // Every rank containing a set bit
+    pub const fn rank_fill(self) -> Self {
+        Bitboard(self.fill(Direction::East).0 | self.fill(Direction::West).0)
+    }
+
+    // === Spans ===
+    // Fills that exclude the generator bits
+    pub const fn north_span(self) -> Self {
+        self.north().north_fill()
+    }
+
+    pub const fn south_span(self) -> Self {
+        self.south().south_fill()
+    }
+
+    // Squares in front of each bit from the point of view of a side
+    pub const fn front_span(self, side: colour::Colour<()>) -> Self {
+        match side {
+            colour::Colour::White(()) => self.north_span(),
+            colour::Colour::Black(()) => self.south_span(),
+        }
+    }
+
+    // Squares behind each bit from the point of view of a side
+    pub const fn rear_span(self, side: colour::Colour<()>) -> Self {
+        match side {
+            colour::Colour::White(()) => self.south_span(),
+            colour::Colour::Black(()) => self.north_span(),
+        }
+    }
+
+    // Every square a pawn could attack as it advances up the board
+    pub const fn attack_span(self, side: colour::Colour<()>) -> Self {
+        let front = self.front_span(side);
+        Bitboard(front.east().0 | front.west().0)
+    }
+
+    // Front span plus attack span, a pawn with no enemy pawns here is passed
+    pub const fn passed_span(self, side: colour::Colour<()>) -> Self {
+        Bitboard(self.front_span(side).0 | self.attack_span(side).0)
+    }
+
+    // === Bit counting ===
+    pub const fn popcount(&self) -> u32 {
+        self.0.count_ones()
+    }
+
+    // Get the index of the most significant bit
+    pub fn msb(&self) -> Option<position::Position> {
+        if self.0 == 0 {
+            None
+        } else {
+            position::Position::from_u32(63 - self.0.leading_zeros())
+        }
+    }
+
+    pub const fn more_than_one(&self) -> bool {
+        self.0 & self.0.wrapping_sub(1) != 0
+    }
+
+    // === Transforms ===
+    // Ranks are bytes, so swapping bytes swaps rank 8 with rank 1 and so on
+    pub const fn flip_vertical(&self) -> Bitboard {
+        Bitboard(self.0.swap_bytes())
+    }
+
+    // Reverse the bits within each rank, a file <-> h file
+    pub const fn mirror_horizontal(&self) -> Bitboard {
+        const K1: u64 = 0x5555_5555_5555_5555;
+        const K2: u64 = 0x3333_3333_3333_3333;
+        const K4: u64 = 0x0F0F_0F0F_0F0F_0F0F;
+
+        let mut x = self.0;
+        x = ((x >> 1) & K1) | ((x & K1) << 1);
+        x = ((x >> 2) & K2) | ((x & K2) << 2);
+        x = ((x >> 4) & K4) | ((x & K4) << 4);
+        Bitboard(x)
+    }
+
+    pub const fn rotate_180(&self) -> Bitboard {
+        Bitboard(self.0.reverse_bits())
+    }
 }
@@ -1,6 +1,8 @@
 use std::fmt;
 
-#[derive(Debug, Clone, Copy)]
+use super::{colour, position};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct CastlingRights(pub u8);
 
 impl CastlingRights {
@@ -34,6 +36,78 @@ impl Default for CastlingRights {
     }
 }
 
+// === Castling rook files ===
+// The file each right's rook starts on, ordered WK, WQ, RK, RQ.
+// Standard chess uses h and a, Chess960 may put them on any file either side of the king.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CastlingRooks(pub [position::File; 4]);
+
+impl CastlingRooks {
+    pub fn new() -> Self {
+        Self([
+            position::File::H,
+            position::File::A,
+            position::File::H,
+            position::File::A,
+        ])
+    }
+
+    pub fn file(&self, right: Castling) -> Option<position::File> {
+        right.slot().map(|i| self.0[i])
+    }
+
+    pub fn set_file(&mut self, right: Castling, file: position::File) {
+        if let Some(i) = right.slot() {
+            self.0[i] = file;
+        }
+    }
+
+    // Where the rook must stand for the right to be used
+    pub fn rook_square(&self, right: Castling) -> Option<position::Position> {
+        Some(position::Position::new(self.file(right)?, right.back_rank()?))
+    }
+
+    // The rights lost when a piece moves from, or is captured on, a rook square
+    pub fn rights_touching(&self, pos: position::Position) -> CastlingRights {
+        let bits = [Castling::WK, Castling::WQ, Castling::RK, Castling::RQ]
+            .into_iter()
+            .filter(|right| self.rook_square(*right) == Some(pos))
+            .fold(0, |acc, right| acc | right.get_castlings_bits());
+
+        CastlingRights(bits)
+    }
+}
+
+impl Default for CastlingRooks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Both rights of one side, lost as soon as its king moves
+pub fn side_rights(side: colour::Colour<()>) -> CastlingRights {
+    match side {
+        colour::Colour::White(()) => CastlingRights::from_rights(&[Castling::WK, Castling::WQ]),
+        colour::Colour::Black(()) => CastlingRights::from_rights(&[Castling::RK, Castling::RQ]),
+    }
+}
+
+// Where the king and rook finish, the same in standard chess and Chess960
+pub fn destinations(right: Castling) -> Option<(position::Position, position::Position)> {
+    let rank = right.back_rank()?;
+    if right.is_kingside()? {
+        Some((
+            position::Position::new(position::File::G, rank),
+            position::Position::new(position::File::F, rank),
+        ))
+    } else {
+        Some((
+            position::Position::new(position::File::C, rank),
+            position::Position::new(position::File::D, rank),
+        ))
+    }
+}
+
 pub fn castling_rights_from_bits(cr: CastlingRights) -> impl Iterator<Item = Castling> {
     [
         Castling::WK,
@@ -79,8 +153,41 @@ impl Castling {
         }
     }
 
+    pub fn for_side(side: colour::Colour<()>, kingside: bool) -> Castling {
+        match (side, kingside) {
+            (colour::Colour::White(()), true) => Castling::WK,
+            (colour::Colour::White(()), false) => Castling::WQ,
+            (colour::Colour::Black(()), true) => Castling::RK,
+            (colour::Colour::Black(()), false) => Castling::RQ,
+        }
+    }
+
+    pub fn side(&self) -> Option<colour::Colour<()>> {
+        match self {
+            Castling::WK | Castling::WQ => Some(colour::Colour::White(())),
+            Castling::RK | Castling::RQ => Some(colour::Colour::Black(())),
+            Castling::None => None,
+        }
+    }
+
+    pub fn back_rank(&self) -> Option<position::Rank> {
+        self.side()
+            .map(|side| position::Rank::R1.relative(side))
+    }
+
+    // Index into per-right tables, in bit order
+    fn slot(&self) -> Option<usize> {
+        match self {
+            Castling::WK => Some(0),
+            Castling::WQ => Some(1),
+            Castling::RK => Some(2),
+            Castling::RQ => Some(3),
+            Castling::None => None,
+        }
+    }
+
     // Option bool egh my eyes!
-    pub(crate) fn is_kingside(&self) -> Option<bool> {
+    pub fn is_kingside(&self) -> Option<bool> {
         match self {
             Castling::WK => Some(true),
             Castling::WQ => Some(false),
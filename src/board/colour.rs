@@ -1,6 +1,6 @@
-use std::ops::Index;
+use std::ops::{Index, IndexMut};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Colour<T> {
     White(T),
     Black(T),
@@ -30,6 +30,15 @@ impl<T> Index<Colour<()>> for [T] {
     }
 }
 
+impl<T> IndexMut<Colour<()>> for [T] {
+    fn index_mut(&mut self, colour: Colour<()>) -> &mut Self::Output {
+        match colour {
+            Colour::White(()) => &mut self[0],
+            Colour::Black(()) => &mut self[1],
+        }
+    }
+}
+
 // Indexing Vec<T> with &Colour<()>
 impl<T> Index<&Colour<()>> for Vec<T> {
     type Output = T;
@@ -2,7 +2,7 @@ use crate::board::colour;
 use colored::*;
 use std::fmt::{self};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Piece(pub colour::Colour<Kind>);
 
 impl fmt::Display for Piece {
@@ -200,7 +200,14 @@ pub(crate) fn get_kind(piece: &Piece) -> Kind {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) fn get_colour(piece: &Piece) -> colour::Colour<()> {
+    match piece.0 {
+        colour::Colour::White(_) => colour::Colour::White(()),
+        colour::Colour::Black(_) => colour::Colour::Black(()),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Kind {
     Bishop,
     King,
@@ -1,12 +1,11 @@
 use std::{fmt, ops::{Index, IndexMut}};
-use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
-use super::error;
+use super::{colour, error};
 
 // The board positions
 #[repr(u8)]
-#[derive(Debug, EnumIter, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, EnumIter, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Position {
     A8, B8, C8, D8, E8, F8, G8, H8,
     A7, B7, C7, D7, E7, F7, G7, H7,
@@ -203,24 +202,52 @@ impl fmt::Display for Position {
 }
 
 impl Position {
+    // Every position in index order, A8 through H1
+    pub const ALL: [Position; 64] = {
+        use Position::*;
+        [
+            A8, B8, C8, D8, E8, F8, G8, H8,
+            A7, B7, C7, D7, E7, F7, G7, H7,
+            A6, B6, C6, D6, E6, F6, G6, H6,
+            A5, B5, C5, D5, E5, F5, G5, H5,
+            A4, B4, C4, D4, E4, F4, G4, H4,
+            A3, B3, C3, D3, E3, F3, G3, H3,
+            A2, B2, C2, D2, E2, F2, G2, H2,
+            A1, B1, C1, D1, E1, F1, G1, H1,
+        ]
+    };
+
+    // Convert a square index to Position
+    pub const fn from_index(index: u8) -> Option<Position> {
+        if index >= 64 {
+            None
+        } else {
+            Some(Self::ALL[index as usize])
+        }
+    }
+
     // Convert u64 to Position
-    pub fn from_u64(value: u64) -> Option<Position> {
+    pub const fn from_u64(value: u64) -> Option<Position> {
         if value >= 64 {
             None
         } else {
-            Position::iter().find(|&p| p as u64 == value)
+            Some(Self::ALL[value as usize])
         }
     }
 
     // Conver u32 to Position
-    pub(crate) fn from_u32(value: u32) -> Option<Position> {
+    pub(crate) const fn from_u32(value: u32) -> Option<Position> {
         if value >= 64 {
             None
         } else {
-            Position::iter().find(|&p| p as u32 == value)
+            Some(Self::ALL[value as usize])
         }
     }
 
+    pub const fn new(file: File, rank: Rank) -> Self {
+        Self::ALL[(7 - rank as usize) * 8 + file as usize]
+    }
+
     pub fn from_chars(file: char, rank: char) -> Option<Self> {
         use Position::*;
 
@@ -294,38 +321,201 @@ impl Position {
     }
 
     // Position manipluation
-    pub fn rank(&self) -> usize {
-        7 - ((*self as usize) / 8)
+    pub const fn rank(&self) -> Rank {
+        Rank::ALL[7 - (*self as usize) / 8]
+    }
+
+    pub const fn file(&self) -> File {
+        File::ALL[(*self as usize) % 8]
+    }
+
+    // Step by a number of files and ranks, None if we leave the board
+    pub const fn offset(&self, file_delta: i8, rank_delta: i8) -> Option<Self> {
+        let file = self.file() as i8 + file_delta;
+        let rank = self.rank() as i8 + rank_delta;
+
+        if file >= 0 && file < 8 && rank >= 0 && rank < 8 {
+            Some(Self::new(File::ALL[file as usize], Rank::ALL[rank as usize]))
+        } else {
+            None
+        }
+    }
+
+    pub const fn change_rank(&self, delta: i8) -> Option<Self> {
+        self.offset(0, delta)
+    }
+
+    pub const fn add_file(&self, delta: i8) -> Option<Self> {
+        self.offset(delta, 0)
+    }
+
+    // The rank as seen from a side's own back rank
+    pub const fn relative_rank(&self, side: colour::Colour<()>) -> Rank {
+        self.rank().relative(side)
+    }
+
+    // === Square geometry ===
+    // a1 is a dark square
+    pub const fn colour(&self) -> colour::Colour<()> {
+        if (self.file() as u8 + self.rank() as u8) & 1 == 0 {
+            colour::Colour::Black(())
+        } else {
+            colour::Colour::White(())
+        }
+    }
+
+    // Chebyshev distance, the number of king steps between two squares
+    pub const fn distance(&self, other: Position) -> u8 {
+        let files = self.file().distance(other.file());
+        let ranks = self.rank().distance(other.rank());
+        if files > ranks {
+            files
+        } else {
+            ranks
+        }
+    }
+
+    pub const fn manhattan_distance(&self, other: Position) -> u8 {
+        self.file().distance(other.file()) + self.rank().distance(other.rank())
     }
 
-    pub fn file(&self) -> usize {
-        (*self as usize) % 8
+    // Index of the a1-h8 direction diagonal, 0 (h1) to 14 (a8)
+    pub const fn diagonal(&self) -> u8 {
+        7 + self.rank() as u8 - self.file() as u8
     }
 
-    pub fn change_rank(&self, delta: i8) -> Option<Self> {
-        let rank = self.rank() as i8 + delta;
-        let file = self.file() as i8;
+    // Index of the a8-h1 direction anti-diagonal, 0 (a1) to 14 (h8)
+    pub const fn anti_diagonal(&self) -> u8 {
+        self.rank() as u8 + self.file() as u8
+    }
+
+    // Mirror across the middle of the board, a1 <-> a8
+    pub const fn flip_vertical(&self) -> Self {
+        Self::ALL[(*self as usize) ^ 56]
+    }
+
+    // Mirror across the d/e file boundary, a1 <-> h1
+    pub const fn flip_horizontal(&self) -> Self {
+        Self::ALL[(*self as usize) ^ 7]
+    }
+}
+
+// === Files ===
+#[repr(u8)]
+#[derive(Debug, EnumIter, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum File {
+    A, B, C, D, E, F, G, H,
+}
+
+impl File {
+    pub const ALL: [File; 8] = [
+        File::A, File::B, File::C, File::D, File::E, File::F, File::G, File::H,
+    ];
+
+    pub const fn from_index(index: u8) -> Option<Self> {
+        if index >= 8 {
+            None
+        } else {
+            Some(Self::ALL[index as usize])
+        }
+    }
 
-        if (0..8).contains(&rank) {
-            let actual_rank = 7 - rank;
-            let index = actual_rank * 8 + file;
-            Position::from_u64(index as u64)
+    pub const fn offset(&self, delta: i8) -> Option<Self> {
+        let file = *self as i8 + delta;
+        if file >= 0 && file < 8 {
+            Some(Self::ALL[file as usize])
         } else {
             None
         }
     }
 
-    pub fn add_file(&self, delta: i8) -> Option<Self> {
-        let rank = self.rank() as i8;
-        let file = self.file() as i8 + delta;
+    pub const fn distance(&self, other: File) -> u8 {
+        (*self as u8).abs_diff(other as u8)
+    }
+
+    pub const fn flip(&self) -> Self {
+        Self::ALL[7 - *self as usize]
+    }
+
+    pub const fn to_char(&self) -> char {
+        (b'a' + *self as u8) as char
+    }
+
+    pub const fn from_char(c: char) -> Option<Self> {
+        match c {
+            'a'..='h' => Self::from_index(c as u8 - b'a'),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for File {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+// === Ranks ===
+#[repr(u8)]
+#[derive(Debug, EnumIter, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rank {
+    R1, R2, R3, R4, R5, R6, R7, R8,
+}
+
+impl Rank {
+    pub const ALL: [Rank; 8] = [
+        Rank::R1, Rank::R2, Rank::R3, Rank::R4, Rank::R5, Rank::R6, Rank::R7, Rank::R8,
+    ];
 
-        if (0..8).contains(&file) {
-            let index = rank * 8 + file;
-            Position::from_u64(index as u64)
+    pub const fn from_index(index: u8) -> Option<Self> {
+        if index >= 8 {
+            None
+        } else {
+            Some(Self::ALL[index as usize])
+        }
+    }
+
+    pub const fn offset(&self, delta: i8) -> Option<Self> {
+        let rank = *self as i8 + delta;
+        if rank >= 0 && rank < 8 {
+            Some(Self::ALL[rank as usize])
         } else {
             None
         }
     }
+
+    pub const fn distance(&self, other: Rank) -> u8 {
+        (*self as u8).abs_diff(other as u8)
+    }
+
+    pub const fn flip(&self) -> Self {
+        Self::ALL[7 - *self as usize]
+    }
+
+    // Flip black's ranks so R1 is always the back rank
+    pub const fn relative(&self, side: colour::Colour<()>) -> Self {
+        match side {
+            colour::Colour::White(()) => *self,
+            colour::Colour::Black(()) => self.flip(),
+        }
+    }
+
+    pub const fn to_char(&self) -> char {
+        (b'1' + *self as u8) as char
+    }
+
+    pub const fn from_char(c: char) -> Option<Self> {
+        match c {
+            '1'..='8' => Self::from_index(c as u8 - b'1'),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
 }
 
 pub(crate) fn to_string(pos: Position) -> String {
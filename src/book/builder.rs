@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+
+use crate::{
+    board::colour,
+    consts,
+    engine::san,
+    gamestate::{boardstate, move_lens},
+    parsers::pgn,
+    traits::static_lookup as PRECOMP,
+};
+
+use super::polyglot;
+
+// === Results of one book move ===
+// Counted from the side playing the move, games without a result only add to `games`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MoveStats {
+    pub games: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MoveStats {
+    // The usual Polyglot weighting, a win counts for two draws
+    pub fn weight(&self) -> u32 {
+        2 * self.wins + self.draws
+    }
+
+    // Games with a result, the only ones that say anything about the move
+    pub fn decided(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    // Share of the points scored, 0.0 to 1.0, over the games with a result
+    pub fn score(&self) -> f64 {
+        let decided = self.decided();
+        if decided == 0 {
+            return 0.0;
+        }
+
+        self.weight() as f64 / (2 * decided) as f64
+    }
+
+    fn record(&mut self, mover: colour::Colour<()>, outcome: pgn::Outcome) {
+        self.games += 1;
+        match outcome {
+            pgn::Outcome::Win(side) if side == mover => self.wins += 1,
+            pgn::Outcome::Win(_) => self.losses += 1,
+            pgn::Outcome::Draw => self.draws += 1,
+            pgn::Outcome::Unknown => {}
+        }
+    }
+}
+
+// === Book builder ===
+// Replays games up to `max_ply` and tallies every move per Polyglot key.
+// Moves played in fewer than `min_games` games with a result, or scoring under
+// `min_score`, are left out of the finished book, as are those left with no weight.
+#[derive(Debug, Clone)]
+pub struct BookBuilder {
+    max_ply: usize,
+    min_games: u32,
+    min_score: f64,
+    stats: HashMap<u64, HashMap<u16, MoveStats>>,
+}
+
+impl Default for BookBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BookBuilder {
+    pub fn new() -> Self {
+        BookBuilder {
+            max_ply: 40,
+            min_games: 3,
+            min_score: 0.0,
+            stats: HashMap::new(),
+        }
+    }
+
+    pub fn max_ply(mut self, max_ply: usize) -> Self {
+        self.max_ply = max_ply;
+        self
+    }
+
+    pub fn min_games(mut self, min_games: u32) -> Self {
+        self.min_games = min_games;
+        self
+    }
+
+    pub fn min_score(mut self, min_score: f64) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    // Adds every game of a PGN collection, returning how many were used
+    pub fn add_pgn<A>(&mut self, input: &str, lookup: A) -> usize
+    where
+        A: PRECOMP::StaticAttack + Copy + 'static,
+    {
+        pgn::parse_games(input)
+            .iter()
+            .filter(|game| self.add_game(game, lookup) > 0)
+            .count()
+    }
+
+    // Replays one game, returning the number of plies added.
+    // Polyglot only covers standard chess, so other variants are skipped,
+    // and replay stops at the first move that cannot be read.
+    pub fn add_game<A>(&mut self, game: &pgn::PgnGame, lookup: A) -> usize
+    where
+        A: PRECOMP::StaticAttack + Copy + 'static,
+    {
+        if game
+            .tag("Variant")
+            .is_some_and(|v| !v.eq_ignore_ascii_case("standard") && !v.eq_ignore_ascii_case("chess"))
+        {
+            return 0;
+        }
+
+        let start = match game.tag("FEN") {
+            Some(fen) => boardstate::try_from_fen(fen),
+            None => boardstate::try_from_fen(consts::START_POSITION),
+        };
+        let Ok(mut state) = start else {
+            return 0;
+        };
+
+        let mut plies = 0;
+        for text in game.moves.iter().take(self.max_ply) {
+            let Some(mv) = san::decode(&state, text, lookup) else {
+                break;
+            };
+            let Some(raw) = polyglot::encode_move(mv) else {
+                break;
+            };
+
+            self.stats
+                .entry(polyglot::key(&state))
+                .or_default()
+                .entry(raw)
+                .or_default()
+                .record(state.side_to_move, game.outcome);
+
+            move_lens::History.make_move(&mut state, mv);
+            plies += 1;
+        }
+
+        plies
+    }
+
+    pub fn positions(&self) -> usize {
+        self.stats.len()
+    }
+
+    // Tallies for every move seen from a position, keyed by Polyglot move
+    pub fn stats(&self, key: u64) -> impl Iterator<Item = (u16, MoveStats)> + '_ {
+        self.stats
+            .get(&key)
+            .into_iter()
+            .flat_map(|moves| moves.iter().map(|(mv, stats)| (*mv, *stats)))
+    }
+
+    // Best moves first within a position. Weights beyond u16 are scaled down
+    // per position so the moves keep their relative odds. Polyglot readers never
+    // play a move of weight 0, so those are not written.
+    pub fn build(&self) -> polyglot::Book {
+        let mut entries = Vec::new();
+
+        for (key, moves) in &self.stats {
+            let mut kept: Vec<(u16, u32)> = moves
+                .iter()
+                .filter(|(_, stats)| stats.decided() >= self.min_games && stats.score() >= self.min_score)
+                .map(|(mv, stats)| (*mv, stats.weight()))
+                .collect();
+            kept.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+            let heaviest = kept.first().map(|(_, weight)| *weight).unwrap_or(0);
+            let scale = |weight: u32| {
+                if heaviest > u16::MAX as u32 {
+                    (weight as u64 * u16::MAX as u64 / heaviest as u64) as u16
+                } else {
+                    weight as u16
+                }
+            };
+
+            entries.extend(
+                kept.into_iter()
+                    .map(|(mv, weight)| (mv, scale(weight)))
+                    .filter(|(_, weight)| *weight > 0)
+                    .map(|(mv, weight)| polyglot::Entry {
+                        key: *key,
+                        mv,
+                        weight,
+                        learn: 0,
+                    }),
+            );
+        }
+
+        polyglot::Book::from_entries(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effects::static_attack_provider::StaticAttackProvider;
+
+    const GAMES: &str = r#"
+[Result "1-0"]
+1. e4 e5 2. Nf3 1-0
+
+[Result "1/2-1/2"]
+1. e4 c5 2. Nf3 1/2-1/2
+
+[Result "0-1"]
+1. e4 e5 2. Bc4 0-1
+
+[Result "0-1"]
+1. d4 d5 0-1
+
+[Result "*"]
+1. d4 Nf6 *
+
+[Result "*"]
+1. d4 Nf6 *
+"#;
+
+    fn weights(book: &polyglot::Book, fen: &str) -> Vec<(String, u16)> {
+        let state = boardstate::try_from_fen(fen).unwrap();
+        book.lookup(polyglot::key(&state))
+            .iter()
+            .map(|entry| {
+                let mv = polyglot::decode_move(&state, entry.mv, StaticAttackProvider).unwrap();
+                (crate::engine::uci::encode(mv, false), entry.weight)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn weights_count_wins_twice_and_draws_once() {
+        let mut builder = BookBuilder::new().min_games(1);
+        assert_eq!(builder.add_pgn(GAMES, StaticAttackProvider), 6);
+
+        let book = builder.build();
+
+        // e4 won once, drew once and lost once; d4 lost and was played twice without a result
+        assert_eq!(weights(&book, consts::START_POSITION), vec![("e2e4".to_string(), 3)]);
+
+        let after_e4_e5 = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2";
+        assert_eq!(weights(&book, after_e4_e5), vec![("g1f3".to_string(), 2)]);
+
+        // Nf6 only comes from games without a result
+        let after_d4 = "rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq - 0 1";
+        assert_eq!(weights(&book, after_d4), vec![("d7d5".to_string(), 2)]);
+    }
+
+    #[test]
+    fn games_without_a_result_do_not_count_towards_min_games() {
+        let mut builder = BookBuilder::new().min_games(2);
+        builder.add_pgn(GAMES, StaticAttackProvider);
+
+        let book = builder.build();
+        let after_d4 = "rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq - 0 1";
+        assert_eq!(weights(&book, after_d4), vec![]);
+        assert_eq!(weights(&book, consts::START_POSITION), vec![("e2e4".to_string(), 3)]);
+    }
+}
@@ -0,0 +1,31 @@
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    // Book length in bytes, which is not a whole number of entries
+    Truncated(usize),
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(err) => writeln!(f, "Could not read the book: {}", err)?,
+            Error::Truncated(len) => writeln!(
+                f,
+                "Book is {} bytes long, not a multiple of the 16 byte entry size",
+                len
+            )?,
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,278 @@
+use std::path::Path;
+
+use rand::Rng;
+
+use crate::{
+    board::{colour, pieces, position},
+    engine::{move_gen, movement as MOVE},
+    gamestate::{boardstate, zobrist},
+    traits::static_lookup as PRECOMP,
+};
+
+use super::{error, polyglot_keys::RANDOM64};
+
+// === Polyglot opening books ===
+// A book is a flat file of 16 byte big-endian entries sorted by position key.
+// Several entries share a key when the book knows more than one move for a position.
+const ENTRY_SIZE: usize = 16;
+
+const CASTLING_OFFSET: usize = 768;
+const EN_PASSANT_OFFSET: usize = 772;
+const TURN_OFFSET: usize = 780;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub key: u64,
+    pub mv: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl Entry {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Entry {
+            key: be(&bytes[0..8]),
+            mv: be(&bytes[8..10]) as u16,
+            weight: be(&bytes[10..12]) as u16,
+            learn: be(&bytes[12..16]) as u32,
+        }
+    }
+
+    fn to_bytes(self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0; ENTRY_SIZE];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.mv.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        bytes
+    }
+}
+
+fn be(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |acc, b| (acc << 8) | *b as u64)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Book {
+    entries: Vec<Entry>,
+}
+
+impl Book {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, error::Error> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(error::Error::Truncated(bytes.len()));
+        }
+
+        Ok(Self::from_entries(bytes.chunks_exact(ENTRY_SIZE).map(Entry::from_bytes).collect()))
+    }
+
+    pub fn from_entries(mut entries: Vec<Entry>) -> Self {
+        // Books are written sorted already, a stable sort keeps their move order
+        entries.sort_by_key(|entry| entry.key);
+
+        Book { entries }
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, error::Error> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.entries.iter().flat_map(|entry| entry.to_bytes()).collect()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), error::Error> {
+        Ok(std::fs::write(path, self.to_bytes())?)
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Every entry stored under a key, found by binary search
+    pub fn lookup(&self, key: u64) -> &[Entry] {
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let len = self.entries[start..].partition_point(|entry| entry.key == key);
+        &self.entries[start..start + len]
+    }
+
+    // Book moves for the position with their weights, entries that are not legal here are dropped
+    pub fn moves<A>(&self, state: &boardstate::State, lookup: A) -> Vec<(MOVE::Move, u16)>
+    where
+        A: PRECOMP::StaticAttack + Copy + 'static,
+    {
+        self.lookup(key(state))
+            .iter()
+            .filter_map(|entry| decode_move(state, entry.mv, lookup).map(|mv| (mv, entry.weight)))
+            .collect()
+    }
+
+    // Picks a book move with probability proportional to its weight.
+    // Pass a seeded rng, e.g. StdRng::seed_from_u64, for repeatable choices.
+    pub fn pick<A, R>(&self, state: &boardstate::State, rng: &mut R, lookup: A) -> Option<MOVE::Move>
+    where
+        A: PRECOMP::StaticAttack + Copy + 'static,
+        R: Rng,
+    {
+        let moves = self.moves(state, lookup);
+        let total: u32 = moves.iter().map(|(_, weight)| *weight as u32).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = rng.random_range(0..total);
+        moves
+            .into_iter()
+            .find(|(_, weight)| {
+                if roll < *weight as u32 {
+                    true
+                } else {
+                    roll -= *weight as u32;
+                    false
+                }
+            })
+            .map(|(mv, _)| mv)
+    }
+}
+
+// === Polyglot key ===
+// Unrelated to zobrist::hash, books are keyed with the published Random64 table
+pub fn key(state: &boardstate::State) -> u64 {
+    let material = state
+        .mailbox_layer
+        .0
+        .iter()
+        .zip(position::Position::ALL)
+        .filter_map(|(piece, pos)| piece.map(|p| RANDOM64[64 * piece_kind(p) + square(pos)]))
+        .fold(0, |acc, key| acc ^ key);
+
+    // Castling bits run white short, white long, black short, black long like the table
+    let castling = (0..4)
+        .filter(|bit| state.castling.0 & (1 << bit) != 0)
+        .fold(0, |acc, bit| acc ^ RANDOM64[CASTLING_OFFSET + bit]);
+
+    // Same rule as our own hash, the file only counts when a capture is possible
+    let en_passant = zobrist::capturable_en_passant(state)
+        .map(|file| RANDOM64[EN_PASSANT_OFFSET + file as usize])
+        .unwrap_or(0);
+
+    let turn = match state.side_to_move {
+        colour::Colour::White(()) => RANDOM64[TURN_OFFSET],
+        colour::Colour::Black(()) => 0,
+    };
+
+    material ^ castling ^ en_passant ^ turn
+}
+
+// Black pawn, white pawn, black knight, ... white king
+fn piece_kind(piece: pieces::Piece) -> usize {
+    let index = piece.index();
+    2 * (index % 6) + usize::from(index < 6)
+}
+
+// Polyglot numbers squares from a1 rather than a8
+fn square(pos: position::Position) -> usize {
+    8 * pos.rank() as usize + pos.file() as usize
+}
+
+// === Polyglot moves ===
+// Bits 0-5 hold the target, 6-11 the source and 12-14 the promotion.
+// Castling is written as the king taking its own rook, which is how we store it too.
+pub fn decode_move<A>(state: &boardstate::State, raw: u16, lookup: A) -> Option<MOVE::Move>
+where
+    A: PRECOMP::StaticAttack + Copy + 'static,
+{
+    let source = from_square(raw >> 6)?;
+    let target = from_square(raw)?;
+    let promotion = match (raw >> 12) & 7 {
+        0 => None,
+        1 => Some(pieces::Kind::Knight),
+        2 => Some(pieces::Kind::Bishop),
+        3 => Some(pieces::Kind::Rook),
+        4 => Some(pieces::Kind::Queen),
+        _ => return None,
+    };
+
+    move_gen::generate_legal_moves(state, lookup).find(|mv| {
+        !MOVE::is_drop(*mv)
+            && MOVE::source(*mv) == source
+            && MOVE::target(*mv) == target
+            && MOVE::promotion(*mv).map(|p| pieces::get_kind(&p)) == promotion
+    })
+}
+
+// Drops have no Polyglot encoding
+pub fn encode_move(mv: MOVE::Move) -> Option<u16> {
+    if MOVE::is_drop(mv) {
+        return None;
+    }
+
+    let promotion = match MOVE::promotion(mv).map(|p| pieces::get_kind(&p)) {
+        None => 0,
+        Some(pieces::Kind::Knight) => 1,
+        Some(pieces::Kind::Bishop) => 2,
+        Some(pieces::Kind::Rook) => 3,
+        Some(pieces::Kind::Queen) => 4,
+        Some(_) => return None,
+    };
+
+    Some((promotion << 12) | (square(MOVE::source(mv)) as u16) << 6 | square(MOVE::target(mv)) as u16)
+}
+
+fn from_square(bits: u16) -> Option<position::Position> {
+    let file = position::File::from_index((bits & 7) as u8)?;
+    let rank = position::Rank::from_index(((bits >> 3) & 7) as u8)?;
+    Some(position::Position::new(file, rank))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{effects::static_attack_provider::StaticAttackProvider, engine::uci};
+
+    // The test positions published with the Polyglot book format
+    const KEYS: [(&str, u64); 9] = [
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 0x463b96181691fc9c),
+        ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1", 0x823c9b50fd114196),
+        ("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2", 0x0756b94461c50fb0),
+        ("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2", 0x662fafb965db29d4),
+        ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", 0x22a48b5a8e47ff78),
+        ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 0 3", 0x652a607ca3f242c1),
+        ("rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 0 4", 0x00fdd303c946bdd9),
+        ("rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3", 0x3c8123ea7b067637),
+        ("rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4", 0x5c3f9b829b279560),
+    ];
+
+    #[test]
+    fn keys_match_the_published_vectors() {
+        for (fen, expected) in KEYS {
+            let state = boardstate::try_from_fen(fen).unwrap();
+            assert_eq!(key(&state), expected, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn moves_round_trip() {
+        // Castling is e1h1 in the book, a promotion keeps its piece
+        let cases = [
+            ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1g1", 4 << 6 | 7),
+            ("8/P6k/8/8/8/8/8/K7 w - - 0 1", "a7a8q", 4 << 12 | 48 << 6 | 56),
+        ];
+
+        for (fen, text, raw) in cases {
+            let state = boardstate::try_from_fen(fen).unwrap();
+            let mv = uci::decode(&state, text, false, StaticAttackProvider).unwrap();
+
+            assert_eq!(encode_move(mv), Some(raw), "{}", text);
+            assert_eq!(decode_move(&state, raw, StaticAttackProvider).map(|mv| mv.0), Some(mv.0), "{}", text);
+        }
+    }
+}
@@ -8,12 +8,21 @@ pub const KILLER_POSITION: &str =
 pub const CMK_POSITION: &str =
     "r2q1rk1/ppp2ppp/2n1bn2/2b1p3/3pP3/3P1NPP/PPP1NPB1/R1BQ1RK1 b - - 0 9 ";
 
+// === Variant starting positions ===
+pub const HORDE_POSITION: &str =
+    "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1";
+pub const RACING_KINGS_POSITION: &str = "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1";
+
 // Constants for board edges
 pub(crate) const NOT_A_FILE: u64 = 18374403900871474942;
 pub(crate) const NOT_H_FILE: u64 = 9187201950435737471;
 pub(crate) const NOT_HG_FILE: u64 = 4557430888798830399;
 pub(crate) const NOT_AB_FILE: u64 = 18229723555195321596;
 
+// Square colours, a8 is a light square
+pub(crate) const LIGHT_SQUARES: u64 = 0xAA55_AA55_AA55_AA55;
+pub(crate) const DARK_SQUARES: u64 = !LIGHT_SQUARES;
+
 // ==== Occupancy Bit count ====
 // Bishop relevant occupancy bit count for every square on board
 pub(crate) const BISHOP_RELEVANT_BITS: [u8; 64] = [
@@ -29,12 +38,6 @@ pub(crate) const ROOK_RELEVANT_BITS: [u8; 64] = [
 ];
 
 // === Magic numbers ===
-pub(crate) const BIT_TABLE: [u64; 64] = [
-    63, 30, 3, 32, 25, 41, 22, 33, 15, 50, 42, 13, 11, 53, 19, 34, 61, 29, 2, 51, 21, 43, 45, 10,
-    18, 47, 1, 54, 9, 57, 0, 35, 62, 31, 40, 4, 49, 5, 52, 26, 60, 6, 23, 44, 46, 27, 56, 16, 7,
-    39, 48, 24, 59, 14, 12, 55, 38, 28, 58, 20, 37, 17, 36, 8,
-];
-
 pub(crate) const ROOK_MAGIC_NUMBERS: [u64; 64] = [
     0x0980108000400160,
     0x8940100020004000,
@@ -90,23 +90,22 @@ pub(crate) fn gen_bishop_attacks<A, B, C>(
     static_lookup: A,
     relavent_bit_lookup: B,
     magic_number_lookup: C,
-) -> [[bitboard::Bitboard; 512]; 64]
+) -> Box<[[bitboard::Bitboard; 512]; 64]>
 where
     A: PRECOMP::StaticMask,
     B: const_lookup::RelaventBits,
     C: const_lookup::ConstMagicNumber,
 {
-    let mut slider: [[bitboard::Bitboard; 512]; 64] = [[bitboard::Bitboard::default(); 512]; 64];
+    // Built on the heap, as the rook table is
+    let mut slider: Box<[[bitboard::Bitboard; 512]; 64]> = vec![[bitboard::Bitboard::default(); 512]; 64]
+        .into_boxed_slice()
+        .try_into()
+        .expect("One table per square");
 
     for p in position::Position::iter() {
         let attk_mask = static_lookup.bishop(p);
-        let rel_bits_count = attk_mask.count_bits();
-        let occupancy_indices = 1 << rel_bits_count;
-
-        for idx in 0..occupancy_indices {
-            let mut occupancy = bitboard::Bitboard::new();
-            occupancy.set_occupancy(idx, &attk_mask);
 
+        for occupancy in attk_mask.subsets() {
             let magic_idx = (occupancy
                 .0
                 .wrapping_mul(magic_number_lookup.bishop(p)))
@@ -130,23 +129,22 @@ pub(crate) fn gen_rook_attacks<A, B, C>(
     static_lookup: A,
     relavent_bit_lookup: B,
     magic_number_lookup: C,
-) -> [[bitboard::Bitboard; 4096]; 64]
+) -> Box<[[bitboard::Bitboard; 4096]; 64]>
 where
     A: PRECOMP::StaticMask,
     B: const_lookup::RelaventBits,
     C: const_lookup::ConstMagicNumber,
 {
-    let mut slider: [[bitboard::Bitboard; 4096]; 64] = [[bitboard::Bitboard::default(); 4096]; 64];
+    // Built on the heap, the rook table alone would fill a thread's stack
+    let mut slider: Box<[[bitboard::Bitboard; 4096]; 64]> = vec![[bitboard::Bitboard::default(); 4096]; 64]
+        .into_boxed_slice()
+        .try_into()
+        .expect("One table per square");
 
     for p in position::Position::iter() {
         let attk_mask = static_lookup.rook(p);
-        let rel_bits_count = attk_mask.count_bits();
-        let occupancy_indices = 1 << rel_bits_count;
-
-        for idx in 0..occupancy_indices {
-            let mut occupancy = bitboard::Bitboard::new();
-            occupancy.set_occupancy(idx, &attk_mask);
 
+        for occupancy in attk_mask.subsets() {
             let magic_idx = (occupancy.0.wrapping_mul(magic_number_lookup.rook(p)))
                 >> (64 - relavent_bit_lookup.rook(p));
 
@@ -2,34 +2,13 @@ use crate::board::{bitboard, colour, position};
 use crate::traits::const_lookup;
 
 // === Attack Masks ===
-pub(crate) fn mask_pawn_attacks<C: const_lookup::ConstFileMask>(
-    position: position::Position,
-    side: &colour::Colour<()>,
-    lookup: C,
-) -> bitboard::Bitboard {
-    let mut attacks: u64 = 0;
+pub(crate) fn mask_pawn_attacks(position: position::Position, side: &colour::Colour<()>) -> bitboard::Bitboard {
     let bitboard = bitboard::Bitboard::new().set_bit(position);
 
     match side {
-        colour::Colour::White(()) => {
-            if (bitboard.0 >> 7) & lookup.not_a_file() != 0 {
-                attacks |= bitboard.0 >> 7;
-            }
-            if (bitboard.0 >> 9) & lookup.not_h_file() != 0 {
-                attacks |= bitboard.0 >> 9;
-            }
-        }
-        colour::Colour::Black(()) => {
-            if (bitboard.0 << 7) & lookup.not_h_file() != 0 {
-                attacks |= bitboard.0 << 7;
-            }
-            if (bitboard.0 << 9) & lookup.not_a_file() != 0 {
-                attacks |= bitboard.0 << 9;
-            }
-        }
-    };
-
-    bitboard::Bitboard::from(attacks)
+        colour::Colour::White(()) => bitboard.ne() | bitboard.nw(),
+        colour::Colour::Black(()) => bitboard.se() | bitboard.sw(),
+    }
 }
 
 pub(crate) fn mask_knight_attacks<C: const_lookup::ConstFileMask>(
@@ -70,42 +49,13 @@ pub(crate) fn mask_knight_attacks<C: const_lookup::ConstFileMask>(
     bitboard::Bitboard::from(attacks)
 }
 
-pub(crate) fn mask_king_attacks<C: const_lookup::ConstFileMask>(
-    position: position::Position,
-    _side: &colour::Colour<()>,
-    lookup: C,
-) -> bitboard::Bitboard {
+pub(crate) fn mask_king_attacks(position: position::Position, _side: &colour::Colour<()>) -> bitboard::Bitboard {
     let bitboard = bitboard::Bitboard::new().set_bit(position);
-    let mut attacks: u64 = 0;
 
-    // generate knight attacks
-    if bitboard.0 >> 8 != 0 {
-        attacks |= bitboard.0 >> 8;
-    }
-    if bitboard.0 >> 9 & lookup.not_h_file() != 0 {
-        attacks |= bitboard.0 >> 9;
-    }
-    if bitboard.0 >> 7 & lookup.not_a_file() != 0 {
-        attacks |= bitboard.0 >> 7;
-    }
-    if bitboard.0 >> 1 & lookup.not_h_file() != 0 {
-        attacks |= bitboard.0 >> 1;
-    }
-
-    if bitboard.0 << 8 != 0 {
-        attacks |= bitboard.0 << 8;
-    }
-    if bitboard.0 << 9 & lookup.not_h_file() != 0 {
-        attacks |= bitboard.0 << 9;
-    }
-    if bitboard.0 << 7 & lookup.not_a_file() != 0 {
-        attacks |= bitboard.0 << 7;
-    }
-    if bitboard.0 << 1 & lookup.not_h_file() != 0 {
-        attacks |= bitboard.0 << 1;
-    }
-
-    bitboard::Bitboard::from(attacks)
+    // One step in every direction
+    bitboard::Direction::ALL
+        .iter()
+        .fold(bitboard::Bitboard::new(), |attacks, dir| attacks | bitboard.shift(*dir))
 }
 
 pub(crate) fn mask_bishop_attacks(
@@ -141,7 +91,7 @@ pub(crate) fn mask_rook_attacks(
     _side: &colour::Colour<()>,
 ) -> bitboard::Bitboard {
     let mut attacks: u64 = 0;
-    let tb = |r: u64, f: u64| (r * 8 + f);
+    let tb = |r: u64, f: u64| r * 8 + f;
 
     // Target ranks and files
     let t_rank: u64 = position as u64 / 8; // Cloning an int is negligible
@@ -12,15 +12,8 @@ use crate::engine::{attack_generation as ATTK_GEN, attack_masks as ATTK_MSK};
 
 // Attacks
 pub(crate) static PAWN_ATTACKS: Lazy<[[bitboard::Bitboard; 64]; 2]> = Lazy::new(|| {
-    let lookup = FILE_MASK_LOOKUP::ConstFileMasks;
-    [
-        ATTK_GEN::gen_attacks(
-            ATTK_MSK::mask_pawn_attacks,
-            colour::Colour::White(()),
-            lookup,
-        ),
-        ATTK_GEN::gen_attacks(ATTK_MSK::mask_pawn_attacks, colour::Colour::Black(()), lookup),
-    ]
+    [colour::Colour::White(()), colour::Colour::Black(())]
+        .map(|side| position::Position::ALL.map(|pos| ATTK_MSK::mask_pawn_attacks(pos, &side)))
 });
 
 pub(crate) static KNIGHT_ATTACKS: Lazy<[bitboard::Bitboard; 64]> = Lazy::new(|| {
@@ -33,12 +26,7 @@ pub(crate) static KNIGHT_ATTACKS: Lazy<[bitboard::Bitboard; 64]> = Lazy::new(||
 });
 
 pub(crate) static KING_ATTACKS: Lazy<[bitboard::Bitboard; 64]> = Lazy::new(|| {
-    let lookup = FILE_MASK_LOOKUP::ConstFileMasks;
-    ATTK_GEN::gen_attacks(
-        ATTK_MSK::mask_king_attacks,
-        colour::Colour::White(()),
-        lookup,
-    )
+    position::Position::ALL.map(|pos| ATTK_MSK::mask_king_attacks(pos, &colour::Colour::White(())))
 });
 
 pub(crate) static BISHOP_ATTACKS: Lazy<Box<[[bitboard::Bitboard; 512]; 64]>> = Lazy::new(|| {
@@ -46,11 +34,7 @@ pub(crate) static BISHOP_ATTACKS: Lazy<Box<[[bitboard::Bitboard; 512]; 64]>> = L
     let rel_bits_lookup = REL_BITS_LOOKUP::RelavantBitsProvider;
     let magic_lookup = MAGIC_NUM_LOOKUP::MagicNumberProvider;
 
-    Box::new(ATTK_GEN::gen_bishop_attacks(
-        mask_lookup,
-        rel_bits_lookup,
-        magic_lookup,
-    ))
+    ATTK_GEN::gen_bishop_attacks(mask_lookup, rel_bits_lookup, magic_lookup)
 });
 
 pub(crate) static ROOK_ATTACKS: Lazy<Box<[[bitboard::Bitboard; 4096]; 64]>> = Lazy::new(|| {
@@ -58,11 +42,7 @@ pub(crate) static ROOK_ATTACKS: Lazy<Box<[[bitboard::Bitboard; 4096]; 64]>> = La
     let rel_bits_lookup = REL_BITS_LOOKUP::RelavantBitsProvider;
     let magic_lookup = MAGIC_NUM_LOOKUP::MagicNumberProvider;
 
-    Box::new(ATTK_GEN::gen_rook_attacks(
-        mask_lookup,
-        rel_bits_lookup,
-        magic_lookup,
-    ))
+    ATTK_GEN::gen_rook_attacks(mask_lookup, rel_bits_lookup, magic_lookup)
 });
 
 // Masks
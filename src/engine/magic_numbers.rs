@@ -3,10 +3,7 @@ use super::{
     attack_generation,
     attack_masks,
 };
-use crate::{
-    board::{bitboard, colour, position},
-    consts::BIT_TABLE,
-};
+use crate::board::{bitboard, colour, position};
 use rand::Rng;
 
 // Gen a radom number
@@ -26,25 +23,6 @@ fn few_bits() -> u64 {
     rand_u64() & rand_u64() & rand_u64()
 }
 
-fn pop_1st_bit(bb: u64) -> (u64, u64) {
-    let b = bb ^ (bb - 1);
-    let fold = ((b & 0xffffffff) ^ (b >> 32)) as u32;
-    let new_bb = bb & (bb - 1);
-    let index = ((fold.wrapping_mul(0x783a9b23)) >> 26) as usize;
-    (new_bb, BIT_TABLE[index])
-}
-
-fn index_u64(idx: usize, bits: usize, mut m: u64) -> u64 {
-    (0..bits).fold(0u64, |mut res, i| {
-        let (new_m, j) = pop_1st_bit(m);
-        m = new_m;
-        if (idx & (1 << i)) != 0 {
-            res |= 1u64 << j;
-        }
-        res
-    })
-}
-
 fn transform(b: u64, magic: u64, bits: u32) -> u32 {
     ((b.wrapping_mul(magic)) >> (64 - bits)) as u32
 }
@@ -62,8 +40,7 @@ pub fn find_magic(sq: position::Position, m: usize, bishop: bool) -> u64 {
 
     let n = mask.count_ones();
 
-    for i in 0..(1 << n) {
-        let occ = bitboard::Bitboard(index_u64(i, n as usize, mask));
+    for (i, occ) in bitboard::Bitboard(mask).subsets().enumerate() {
         b[i] = occ.0;
         a[i] = if bishop {
             attack_generation::fly_gen_bishop_attks(sq, &occ).0
@@ -4,7 +4,7 @@ use crate::{
         position,
     },
     engine::movement as MOVE,
-    gamestate::{boardstate, occupancy_layer},
+    gamestate::{boardstate, move_lens, occupancy_layer, variant},
     traits::static_lookup as PRECOMP,
 };
 use itertools::chain;
@@ -60,9 +60,134 @@ where
             lookup,
             pieces::Kind::King,
         ),
+        generate_drop_moves(chessboard, lookup),
     )
 }
 
+// === Legal moves ===
+// Filters the pseudo-legal moves, dropping any that leave the mover's king attacked.
+// Moves that could do so are made and unmade on one scratch copy of the position.
+// Once a variant's own win condition is met there are no moves left to play.
+pub fn generate_legal_moves<A>(
+    chessboard: &boardstate::State,
+    lookup: A,
+) -> impl Iterator<Item = MOVE::Move> + '_
+where
+    A: PRECOMP::StaticAttack + Copy + 'static,
+{
+    let finished =
+        variant::winner(chessboard, lookup).is_some() || variant::is_drawn(chessboard, lookup);
+    // Antichess captures are compulsory, any capture rules out every quiet move
+    let must_capture = chessboard.variant == variant::Variant::Antichess
+        && generate_moves(chessboard, lookup).any(|mv| MOVE::capture(mv).is_some());
+    let pinnable = pinnable_squares(chessboard, lookup);
+    let mut scratch = *chessboard;
+
+    generate_moves(chessboard, lookup)
+        .take_while(move |_| !finished)
+        .filter(move |mv| !must_capture || MOVE::capture(*mv).is_some())
+        .filter(move |mv| {
+            let en_passant = MOVE::piece(*mv).map(|p| pieces::get_kind(&p)) == Some(pieces::Kind::Pawn)
+                && chessboard.en_passant == Some(MOVE::target(*mv));
+
+            // Nothing else can uncover the king, those moves skip the make/unmake
+            !(pinnable.is_occupied(MOVE::source(*mv)) || en_passant)
+                || is_legal(&mut scratch, *mv, lookup)
+        })
+}
+
+// Squares a move may leave from and expose the king: the king's own and those of
+// pieces pinned to it. When already in check, or in variants where explosions and
+// checks given also decide legality, any move might.
+fn pinnable_squares<A: PRECOMP::StaticAttack + Copy>(
+    chessboard: &boardstate::State,
+    lookup: A,
+) -> bitboard::Bitboard {
+    let us = chessboard.side_to_move;
+    let Some(king) = boardstate::king_position(chessboard, us) else {
+        return bitboard::Bitboard(!0);
+    };
+    if matches!(
+        chessboard.variant,
+        variant::Variant::Atomic | variant::Variant::RacingKings
+    ) || boardstate::in_check(chessboard, lookup)
+    {
+        return bitboard::Bitboard(!0);
+    }
+
+    let theirs = |kind| chessboard.material_layer[pieces::from_colour_kind(&us.opp(), kind)];
+    let straight = theirs(pieces::Kind::Rook) | theirs(pieces::Kind::Queen);
+    let diagonal = theirs(pieces::Kind::Bishop) | theirs(pieces::Kind::Queen);
+    let occupied = occupancy_layer::get_both(&chessboard.occupancy_layer);
+
+    // Our pieces next in line from the king are pinned when lifting one lets a slider see it
+    (lookup.queen(king, occupied) & chessboard.occupancy_layer[us])
+        .iter()
+        .filter(|pos| {
+            let lifted = occupied ^ bitboard::Bitboard(1 << *pos as u64);
+            !(lookup.rook(king, lifted) & straight).is_empty()
+                || !(lookup.bishop(king, lifted) & diagonal).is_empty()
+        })
+        .fold(bitboard::Bitboard(1 << king as u64), |squares, pos| {
+            squares | bitboard::Bitboard(1 << pos as u64)
+        })
+}
+
+// Makes the move, checks the position it leaves, and unmakes it again
+pub fn is_legal<A: PRECOMP::StaticAttack + Copy>(
+    chessboard: &mut boardstate::State,
+    mv: MOVE::Move,
+    lookup: A,
+) -> bool {
+    // Atomic kings may not capture, they would explode themselves
+    if chessboard.variant == variant::Variant::Atomic
+        && MOVE::capture(mv).is_some()
+        && MOVE::piece(mv).map(|p| pieces::get_kind(&p)) == Some(pieces::Kind::King)
+    {
+        return false;
+    }
+
+    let side = chessboard.side_to_move;
+    let delta = move_lens::History.make_move(chessboard, mv);
+    let legal = leaves_king_safe(chessboard, side, lookup);
+    move_lens::History.unmake_move(chessboard, &delta);
+
+    legal
+}
+
+// Whether the position reached by a move of `mover` is one they may leave behind
+pub fn leaves_king_safe<A: PRECOMP::StaticAttack + Copy>(
+    next: &boardstate::State,
+    mover: colour::Colour<()>,
+    lookup: A,
+) -> bool {
+    // The mover's king must survive any explosion, blowing up the enemy king wins at once
+    if next.variant == variant::Variant::Atomic {
+        if boardstate::king_position(next, mover).is_none() {
+            return false;
+        }
+        if boardstate::king_position(next, mover.opp()).is_none() {
+            return true;
+        }
+    }
+
+    // Racing Kings forbids giving check as well as walking into it
+    if next.variant == variant::Variant::RacingKings
+        && boardstate::is_king_attacked(next, mover.opp(), lookup)
+    {
+        return false;
+    }
+
+    !boardstate::is_king_attacked(next, mover, lookup)
+}
+
+pub fn has_legal_moves<A>(chessboard: &boardstate::State, lookup: A) -> bool
+where
+    A: PRECOMP::StaticAttack + Copy + 'static,
+{
+    generate_legal_moves(chessboard, lookup).next().is_some()
+}
+
 // === Individual piece move gen ===
 pub fn generate_pawn_moves<A>(
     board: bitboard::Bitboard,
@@ -72,7 +197,7 @@ pub fn generate_pawn_moves<A>(
 where
     A: PRECOMP::StaticAttack + Copy + 'static,
 {
-    board.flat_map(move |source_square| {
+    board.into_iter().flat_map(move |source_square| {
         chain!(
             generate_pawn_pushes(source_square, chessboard),
             generate_pawn_pushes2(source_square, chessboard),
@@ -96,10 +221,12 @@ fn generate_pawn_targets(
         .change_rank(forward_one)
         .filter(|sq| !occupancy_layer::get_both(&chessboard.occupancy_layer).is_occupied(sq));
 
-    let is_start_rank = matches!(
-        (source_square.rank(), chessboard.side_to_move),
-        (6, colour::Colour::Black(())) | (1, colour::Colour::White(()))
-    );
+    // Horde pawns on the first rank may also double push
+    let is_start_rank = match source_square.relative_rank(chessboard.side_to_move) {
+        position::Rank::R2 => true,
+        position::Rank::R1 => chessboard.variant == variant::Variant::Horde,
+        _ => false,
+    };
 
     let target_two = source_square.change_rank(forward_two).filter(|sq| {
         !occupancy_layer::get_both(&chessboard.occupancy_layer).is_occupied(sq)
@@ -113,32 +240,54 @@ fn generate_pawn_targets(
 fn generate_pawn_pushes(
     source_square: position::Position,
     chessboard: &boardstate::State,
-) -> Option<MOVE::Move> {
+) -> impl Iterator<Item = MOVE::Move> {
     let (target_one, _target_two) = generate_pawn_targets(source_square, chessboard);
 
-    let is_promotion_rank = match chessboard.side_to_move {
-        colour::Colour::White(()) => source_square.rank() == 6,
-        colour::Colour::Black(()) => source_square.rank() == 1,
-    };
+    let is_promotion_rank =
+        source_square.relative_rank(chessboard.side_to_move) == position::Rank::R7;
+    let side = chessboard.side_to_move;
+    let rules = chessboard.variant;
 
-    if let Some(tgt1) = target_one {
+    target_one.into_iter().flat_map(move |tgt1| {
         let mv = MOVE::MoveBuilder::new()
-            .set_piece(pieces::from_colour_kind(
-                &chessboard.side_to_move,
-                pieces::Kind::Pawn,
-            ))
+            .set_piece(pieces::from_colour_kind(&side, pieces::Kind::Pawn))
             .set_source(source_square)
             .set_target(tgt1)
             .set_traits(if is_promotion_rank {
                 &[MOVE::MoveTrait::Promotion, MOVE::MoveTrait::Quiet]
             } else {
                 &[MOVE::MoveTrait::Quiet]
-            })
-            .build();
-        return Some(mv);
-    }
+            });
+        with_promotions(mv, side, is_promotion_rank, rules)
+    })
+}
 
-    None
+// Expands a move reaching the last rank into one move per promotion piece,
+// Antichess pawns may also become kings
+fn with_promotions(
+    mv: MOVE::MoveBuilder,
+    side: colour::Colour<()>,
+    promotes: bool,
+    rules: variant::Variant,
+) -> impl Iterator<Item = MOVE::Move> {
+    const PROMOTIONS: [pieces::Kind; 5] = [
+        pieces::Kind::Queen,
+        pieces::Kind::Rook,
+        pieces::Kind::Bishop,
+        pieces::Kind::Knight,
+        pieces::Kind::King,
+    ];
+    let choices = match (promotes, rules) {
+        (false, _) => 0,
+        (true, variant::Variant::Antichess) => PROMOTIONS.len(),
+        (true, _) => PROMOTIONS.len() - 1,
+    };
+
+    PROMOTIONS
+        .into_iter()
+        .take(choices)
+        .map(move |kind| mv.promotes(pieces::from_colour_kind(&side, kind)).build())
+        .chain((!promotes).then(|| mv.build()))
 }
 
 fn generate_pawn_pushes2(
@@ -147,28 +296,17 @@ fn generate_pawn_pushes2(
 ) -> Option<MOVE::Move> {
     let (_target_one, target_two) = generate_pawn_targets(source_square, chessboard);
 
-    let is_red_start =
-        source_square.rank() == 6 && chessboard.side_to_move == colour::Colour::Black(());
-    let is_white_start =
-        source_square.rank() == 1 && chessboard.side_to_move == colour::Colour::White(());
-
-    if is_red_start || is_white_start {
-        if let Some(tgt2) = target_two {
-            return Some(
-                MOVE::MoveBuilder::new()
-                    .set_traits(&[MOVE::MoveTrait::Quiet])
-                    .set_piece(pieces::from_colour_kind(
-                        &chessboard.side_to_move,
-                        pieces::Kind::Pawn,
-                    ))
-                    .set_source(source_square)
-                    .set_target(tgt2)
-                    .build(),
-            );
-        }
-    }
-
-    None
+    target_two.map(|tgt2| {
+        MOVE::MoveBuilder::new()
+            .set_traits(&[MOVE::MoveTrait::Quiet])
+            .set_piece(pieces::from_colour_kind(
+                &chessboard.side_to_move,
+                pieces::Kind::Pawn,
+            ))
+            .set_source(source_square)
+            .set_target(tgt2)
+            .build()
+    })
 }
 
 fn generate_enpassant<A: PRECOMP::StaticAttack + Copy>(
@@ -228,7 +366,7 @@ where
     let targets = lookup.pawn(source_square, chessboard.side_to_move)
         & chessboard.occupancy_layer.0[chessboard.side_to_move.opp()];
 
-    targets.into_iter().filter_map(move |target| {
+    targets.into_iter().flat_map(move |target| {
         let piece = pieces::from_colour_kind(&chessboard.side_to_move, pieces::Kind::Pawn);
         let checks = into_check(
             &MOVE::Detail {
@@ -240,32 +378,33 @@ where
             lookup,
         );
 
-        boardstate::get_piece_at_pos(chessboard, target).map(|capture| {
-            MOVE::MoveBuilder::new()
-                .set_traits(
-                    if (chessboard.side_to_move == colour::Colour::White(())
-                        && source_square.rank() == 6)
-                        || (chessboard.side_to_move == colour::Colour::Black(())
-                            && source_square.rank() == 1)
-                    {
+        let is_promotion_rank =
+            source_square.relative_rank(chessboard.side_to_move) == position::Rank::R7;
+
+        boardstate::get_piece_at_pos(chessboard, target)
+            .into_iter()
+            .flat_map(move |capture| {
+                let mv = MOVE::MoveBuilder::new()
+                    .set_traits(if is_promotion_rank {
                         &[MOVE::MoveTrait::Capture, MOVE::MoveTrait::Promotion]
                     } else if checks {
                         &[MOVE::MoveTrait::Capture, MOVE::MoveTrait::Check]
                     } else {
                         &[MOVE::MoveTrait::Capture]
-                    },
-                )
-                .set_piece(piece)
-                .set_source(source_square)
-                .set_target(target)
-                .captures(capture)
-                .build()
-        })
+                    })
+                    .set_piece(piece)
+                    .set_source(source_square)
+                    .set_target(target)
+                    .captures(capture);
+                with_promotions(mv, chessboard.side_to_move, is_promotion_rank, chessboard.variant)
+            })
     })
 }
 
 // === Castle moves ===
-// Add castling into check
+// The king may not castle out of, through or into check.
+// Castling is encoded as the king taking its own rook, which also covers Chess960
+// where the king or rook may already stand on its destination.
 pub fn generate_castle_moves<A>(
     chessboard: &boardstate::State,
     lookup: A,
@@ -273,60 +412,114 @@ pub fn generate_castle_moves<A>(
 where
     A: PRECOMP::StaticAttack + Copy + 'static,
 {
-    let occ = occupancy_layer::get_both(&chessboard.occupancy_layer);
+    let side = chessboard.side_to_move;
+    let king = pieces::from_colour_kind(&side, pieces::Kind::King);
+    let rook = pieces::from_colour_kind(&side, pieces::Kind::Rook);
+    let king_from = boardstate::king_position(chessboard, side);
+
+    // Antichess kings are ordinary pieces and never castle
+    let castles = chessboard.variant != variant::Variant::Antichess;
+
     castling::castling_rights_from_bits(chessboard.castling)
-        .flat_map(move |cr| match cr {
-            castling::Castling::WK if chessboard.side_to_move == colour::Colour::White(()) => {
-                if !boardstate::is_attacked(chessboard, position::Position::E1, lookup)
-                    && !boardstate::is_attacked(chessboard, position::Position::G1, lookup)
-                    && !occ.is_occupied(position::Position::F1)
-                    && !occ.is_occupied(position::Position::G1)
-                {
-                    return Some((position::Position::E1, position::Position::G1));
-                }
-                None
-            }
-            castling::Castling::WQ if chessboard.side_to_move == colour::Colour::White(()) => {
-                if !boardstate::is_attacked(chessboard, position::Position::E1, lookup)
-                    && !boardstate::is_attacked(chessboard, position::Position::C1, lookup)
-                    && !occ.is_occupied(position::Position::D1)
-                    && !occ.is_occupied(position::Position::C1)
-                    && !occ.is_occupied(position::Position::B1)
-                {
-                    return Some((position::Position::E1, position::Position::C1));
-                }
-                None
-            }
-            castling::Castling::RK if chessboard.side_to_move == colour::Colour::Black(()) => {
-                if !boardstate::is_attacked(chessboard, position::Position::E8, lookup)
-                    && !boardstate::is_attacked(chessboard, position::Position::G8, lookup)
-                    && !occ.is_occupied(position::Position::F8)
-                    && !occ.is_occupied(position::Position::G8)
-                {
-                    return Some((position::Position::E8, position::Position::G8));
-                }
-                None
-            }
-            castling::Castling::RQ if chessboard.side_to_move == colour::Colour::Black(()) => {
-                if !boardstate::is_attacked(chessboard, position::Position::E8, lookup)
-                    && !boardstate::is_attacked(chessboard, position::Position::C8, lookup)
-                    && !occ.is_occupied(position::Position::D8)
-                    && !occ.is_occupied(position::Position::C8)
-                    && !occ.is_occupied(position::Position::B8)
-                {
-                    return Some((position::Position::E8, position::Position::C8));
-                }
-                None
+        .filter(move |cr| castles && cr.side() == Some(side))
+        .filter_map(move |cr| {
+            let king_from = king_from?;
+            let rook_from = chessboard.castling_rooks.rook_square(cr)?;
+            let (king_to, rook_to) = castling::destinations(cr)?;
+
+            if chessboard.mailbox_layer[rook_from] != Some(rook) {
+                return None;
             }
-            _ => None,
+
+            // Every square either piece crosses must be empty, bar the two castling pieces
+            let occ = occupancy_layer::get_both(&chessboard.occupancy_layer)
+                & !bitboard::Bitboard::new().set_bit(king_from).set_bit(rook_from);
+            let king_path = rank_between(king_from, king_to);
+            let path = king_path | rank_between(rook_from, rook_to);
+
+            // An Atomic king next to the enemy king cannot be taken, so it is never in check there
+            let shielded = |sq: position::Position| {
+                chessboard.variant == variant::Variant::Atomic
+                    && boardstate::king_position(chessboard, side.opp())
+                        .is_some_and(|enemy| lookup.king(sq).is_occupied(enemy))
+            };
+            // Lift the king so it does not hide its own path from sliders behind it.
+            // The destination is left to the legality check, once the rook has moved in.
+            let mut lifted = *chessboard;
+            lifted.toggle_piece(king, king_from);
+            let safe = king_path
+                .pop_bit(king_to)
+                .set_bit(king_from)
+                .iter()
+                .all(|sq| shielded(sq) || !boardstate::is_attacked(&lifted, sq, lookup));
+
+            (occ.is_disjoint(path) && safe).then(|| {
+                MOVE::MoveBuilder::new()
+                    .set_traits(&[MOVE::MoveTrait::Castle])
+                    .set_piece(king)
+                    .set_source(king_from)
+                    .set_target(rook_from)
+                    .build()
+            })
         })
-        .map(|(king_from, king_to)| {
+}
+
+// Squares on a rank from one square to another, both ends included
+fn rank_between(from: position::Position, to: position::Position) -> bitboard::Bitboard {
+    let (low, high) = if from.file() <= to.file() {
+        (from.file(), to.file())
+    } else {
+        (to.file(), from.file())
+    };
+
+    position::File::ALL
+        .into_iter()
+        .filter(|file| *file >= low && *file <= high)
+        .map(|file| position::Position::new(file, from.rank()))
+        .collect()
+}
+
+// === Drop moves ===
+// Any held piece may go on any empty square, pawns not on the first or last rank.
+// Only Crazyhouse ever fills the pockets, elsewhere this yields nothing.
+fn generate_drop_moves<A>(
+    chessboard: &boardstate::State,
+    lookup: A,
+) -> impl Iterator<Item = MOVE::Move> + '_
+where
+    A: PRECOMP::StaticAttack + Copy + 'static,
+{
+    const BACK_RANKS: bitboard::Bitboard = bitboard::Bitboard(0xFF00_0000_0000_00FF);
+    let side = chessboard.side_to_move;
+    let empty = !occupancy_layer::get_both(&chessboard.occupancy_layer);
+
+    chessboard.pockets.held(side).flat_map(move |kind| {
+        let targets = if kind == pieces::Kind::Pawn {
+            empty & !BACK_RANKS
+        } else {
+            empty
+        };
+        let piece = pieces::from_colour_kind(&side, kind);
+
+        targets.into_iter().map(move |trgt| {
+            let detail = MOVE::Detail {
+                piece,
+                source: trgt,
+                target: trgt,
+            };
+
             MOVE::MoveBuilder::new()
-                .set_traits(&[MOVE::MoveTrait::Castle])
-                .set_source(king_from)
-                .set_target(king_to)
+                .set_traits(if into_check(&detail, chessboard, lookup) {
+                    &[MOVE::MoveTrait::Drop, MOVE::MoveTrait::Check]
+                } else {
+                    &[MOVE::MoveTrait::Drop]
+                })
+                .set_piece(piece)
+                .set_source(trgt)
+                .set_target(trgt)
                 .build()
         })
+    })
 }
 
 // === Major piece moves ===
@@ -16,7 +16,8 @@ pub struct Detail {
 // A tightly packed u32 repesentation of a move
 // | Bit Index     | Field Name    | Size (bits) | Description                                 |
 // |---------------|---------------|-------------|---------------------------------------------|
-// | 31-20         | MoveType      | 12          | Bitfield of traits (QUIET, CHECK, etc.)     |
+// | 31-28         | Promoted      | 4           | ID of the promotion piece (or 0 if none)    |
+// | 27-20         | MoveType      | 8           | Bitfield of traits (QUIET, CHECK, etc.)     |
 // | 19-16         | Piece         | 4           | Moving piece ID                             |
 // | 15-10         | Source        | 6           | Source square index (0-63)                  |
 // | 9-4           | Target        | 6           | Target square index (0-63)                  |
@@ -49,15 +50,21 @@ impl fmt::Display for Move {
             None => "None".to_owned(),
         };
 
+        let promo = match promotion(*self) {
+            Some(p) => format!(" | Promotes: {}", p),
+            None => String::new(),
+        };
+
         writeln!(
             f,
-            "Move: {:08X} | Traits: [{}] | Piece: {} | Movement: {} -> {} | Captures: {}",
+            "Move: {:08X} | Traits: [{}] | Piece: {} | Movement: {} -> {} | Captures: {}{}",
             self.0,
             traits_str,
             piece(*self).expect("Invalid piece configureation."),
             source(*self),
             target(*self),
             cap,
+            promo,
         )
     }
 }
@@ -69,7 +76,7 @@ impl Move {
             .map(|t| t.bit())
             .fold(0, |acc, b| acc | b);
         // Clear current trait bits first
-        self.0 &= !(0xFFF << 20);
+        self.0 &= !(0xFF << 20);
         // Set new traits
         self.0 |= (flags as u32) << 20;
     }
@@ -78,7 +85,7 @@ impl Move {
 // === Action Data access funcs ===
 pub fn traits(action: Move) -> impl Iterator<Item = MoveTrait> {
     MoveTrait::ALL.iter().flat_map(move |(trait_type, mask)| {
-        if (((action.0 >> 20) & 0xFFF) as u16) & mask != 0 {
+        if (((action.0 >> 20) & 0xFF) as u16) & mask != 0 {
             return Some(*trait_type);
         }
         None
@@ -105,6 +112,15 @@ pub fn capture(action: Move) -> Option<pieces::Piece> {
         .expect("Move has invalid capture piece configuration.")
 }
 
+pub fn promotion(action: Move) -> Option<pieces::Piece> {
+    pieces::try_from_u8(((action.0 >> 28) & 0xF) as u8)
+        .expect("Move has invalid promotion piece configuration.")
+}
+
+pub fn is_drop(action: Move) -> bool {
+    (action.0 >> 20) & (MoveTrait::Drop.bit() as u32) != 0
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MoveTrait {
     Quiet,
@@ -113,6 +129,8 @@ pub enum MoveTrait {
     Promotion,
     Enpassant,
     Castle,
+    // Crazyhouse drop from the pocket, the source square repeats the target
+    Drop,
 }
 
 // === Display ===
@@ -126,6 +144,7 @@ impl fmt::Display for MoveTrait {
             Promotion => "Promotion".magenta(),
             Enpassant => "Enpassant".cyan(),
             Castle    => "Castle".blue(),
+            Drop      => "Drop".green(),
         };
         write!(f, "{}", styled)?;
 
@@ -134,13 +153,14 @@ impl fmt::Display for MoveTrait {
 }
 
 impl MoveTrait {
-    const ALL: [(MoveTrait, u16); 6] = [
+    const ALL: [(MoveTrait, u16); 7] = [
         (MoveTrait::Quiet, 1 << 0),
         (MoveTrait::Capture, 1 << 1),
         (MoveTrait::Check, 1 << 2),
         (MoveTrait::Promotion, 1 << 3),
         (MoveTrait::Enpassant, 1 << 4),
         (MoveTrait::Castle, 1 << 5),
+        (MoveTrait::Drop, 1 << 6),
     ];
 
     pub fn bit(self) -> u16 {
@@ -176,7 +196,7 @@ impl MoveBuilder {
             .map(|t| t.bit())
             .fold(0, |acc, b| acc | b);
 
-        result.0 &= !(0xFFF << 20); // Clear current trait bits
+        result.0 &= !(0xFF << 20); // Clear current trait bits
         result.0 |= (flags as u32) << 20; // Set new traits
 
         result
@@ -214,6 +234,14 @@ impl MoveBuilder {
         result
     }
 
+    pub fn promotes(self, promoted: pieces::Piece) -> Self {
+        let mut result = self;
+        let promo: u8 = promoted.into();
+        result.0 &= !(0xF << 28); // Clear bits 28–31
+        result.0 |= (promo as u32) << 28;
+        result
+    }
+
 
     pub fn build(self) -> Move {
         Move(self.0)
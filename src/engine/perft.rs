@@ -0,0 +1,53 @@
+use crate::{
+    engine::{move_gen, movement as MOVE},
+    gamestate::{boardstate, move_lens},
+    traits::static_lookup as PRECOMP,
+};
+
+// === Performance test ===
+// Counts the leaf nodes of the move tree to a fixed depth,
+// making and unmaking moves in place on a single State
+pub fn perft<A>(chessboard: &mut boardstate::State, depth: u32, lookup: A) -> u64
+where
+    A: PRECOMP::StaticAttack + Copy + 'static,
+{
+    if depth == 0 {
+        return 1;
+    }
+
+    let lens = move_lens::History;
+    let moves: Vec<MOVE::Move> = move_gen::generate_legal_moves(chessboard, lookup).collect();
+
+    moves
+        .into_iter()
+        .map(|mv| {
+            let delta = lens.make_move(chessboard, mv);
+            let nodes = perft(chessboard, depth - 1, lookup);
+            lens.unmake_move(chessboard, &delta);
+            nodes
+        })
+        .sum()
+}
+
+// Perft split by root move, handy for diffing against another engine
+pub fn divide<A>(
+    chessboard: &mut boardstate::State,
+    depth: u32,
+    lookup: A,
+) -> Vec<(MOVE::Move, u64)>
+where
+    A: PRECOMP::StaticAttack + Copy + 'static,
+{
+    let lens = move_lens::History;
+    let moves: Vec<MOVE::Move> = move_gen::generate_legal_moves(chessboard, lookup).collect();
+
+    moves
+        .into_iter()
+        .map(|mv| {
+            let delta = lens.make_move(chessboard, mv);
+            let nodes = perft(chessboard, depth.saturating_sub(1), lookup);
+            lens.unmake_move(chessboard, &delta);
+            (mv, nodes)
+        })
+        .collect()
+}
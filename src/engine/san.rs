@@ -0,0 +1,182 @@
+use crate::{
+    board::{pieces, position},
+    engine::{move_gen, movement as MOVE, uci},
+    gamestate::{boardstate, move_lens},
+    traits::static_lookup as PRECOMP,
+};
+
+// === Writing SAN ===
+// Writes a legal move as PGN does: the piece letter, as much of the source
+// square as tells it apart from the same kind of piece reaching the same
+// square, x for a capture, =Q for a promotion, and + or # for check and mate.
+// Castling is O-O or O-O-O, and a Crazyhouse drop is written as in UCI, e.g. P@e4
+pub fn encode<A>(state: &boardstate::State, mv: MOVE::Move, lookup: A) -> String
+where
+    A: PRECOMP::StaticAttack + Copy + 'static,
+{
+    let mut text = if MOVE::is_drop(mv) {
+        uci::encode(mv, false)
+    } else if is_castle(mv) {
+        if MOVE::target(mv).file() > MOVE::source(mv).file() {
+            "O-O".to_string()
+        } else {
+            "O-O-O".to_string()
+        }
+    } else {
+        movement(state, mv, lookup)
+    };
+
+    let mut next = *state;
+    move_lens::History.make_move(&mut next, mv);
+    if boardstate::in_check(&next, lookup) {
+        text.push(if move_gen::has_legal_moves(&next, lookup) { '+' } else { '#' });
+    }
+
+    text
+}
+
+// Everything but the check mark for a move of a piece on the board
+fn movement<A>(state: &boardstate::State, mv: MOVE::Move, lookup: A) -> String
+where
+    A: PRECOMP::StaticAttack + Copy + 'static,
+{
+    let source = MOVE::source(mv);
+    let target = MOVE::target(mv);
+    let kind = MOVE::piece(mv).map(|p| pieces::get_kind(&p));
+    let capture = MOVE::capture(mv).is_some();
+    let mut text = String::new();
+
+    if kind == Some(pieces::Kind::Pawn) {
+        if capture {
+            text.push(source.file().to_char());
+        }
+    } else {
+        text.extend(MOVE::piece(mv).map(|p| char::from(p).to_ascii_uppercase()));
+
+        // Other pieces of the same kind that could also go to the target
+        let rivals: Vec<position::Position> = move_gen::generate_legal_moves(state, lookup)
+            .filter(|other| {
+                !MOVE::is_drop(*other)
+                    && !is_castle(*other)
+                    && MOVE::target(*other) == target
+                    && MOVE::source(*other) != source
+                    && MOVE::piece(*other).map(|p| pieces::get_kind(&p)) == kind
+            })
+            .map(MOVE::source)
+            .collect();
+
+        if !rivals.is_empty() {
+            if rivals.iter().all(|pos| pos.file() != source.file()) {
+                text.push(source.file().to_char());
+            } else if rivals.iter().all(|pos| pos.rank() != source.rank()) {
+                text.push(source.rank().to_char());
+            } else {
+                text.push_str(&position::to_string(source));
+            }
+        }
+    }
+
+    if capture {
+        text.push('x');
+    }
+    text.push_str(&position::to_string(target));
+
+    if let Some(promoted) = MOVE::promotion(mv) {
+        text.push('=');
+        text.push(char::from(promoted).to_ascii_uppercase());
+    }
+
+    text
+}
+
+// === Reading SAN ===
+// Reads moves as written in PGN, e.g. Nbd7, exd5, e8=Q+ or O-O.
+// Check marks and annotations are ignored, a move that fits more than one
+// legal move is rejected rather than guessed.
+pub fn decode<A>(state: &boardstate::State, text: &str, lookup: A) -> Option<MOVE::Move>
+where
+    A: PRECOMP::StaticAttack + Copy + 'static,
+{
+    let text = text.trim().trim_end_matches(['+', '#', '!', '?']);
+
+    // Crazyhouse drops read the same as in UCI, a pawn drop may leave out its letter
+    if let Some(square) = text.strip_prefix('@') {
+        return uci::decode(state, &format!("P@{}", square), false, lookup);
+    }
+    if text.contains('@') {
+        return uci::decode(state, text, false, lookup);
+    }
+
+    match text {
+        "O-O" | "0-0" => return castle(state, true, lookup),
+        "O-O-O" | "0-0-0" => return castle(state, false, lookup),
+        _ => {}
+    }
+
+    let mut chars: Vec<char> = text.chars().filter(|c| !matches!(c, 'x' | ':' | '-' | '=')).collect();
+
+    let promotion = match chars.last() {
+        Some(c) if "NBRQK".contains(*c) && chars.len() > 2 => {
+            let kind = piece_kind(*c);
+            chars.pop();
+            kind
+        }
+        _ => None,
+    };
+
+    let kind = match chars.first() {
+        Some(c) if c.is_ascii_uppercase() => {
+            let kind = piece_kind(*c)?;
+            chars.remove(0);
+            kind
+        }
+        _ => pieces::Kind::Pawn,
+    };
+
+    if chars.len() < 2 {
+        return None;
+    }
+    let target = position::Position::from_chars(chars[chars.len() - 2], chars[chars.len() - 1])?;
+
+    // Whatever sits between the piece and the target narrows down the source
+    let hint = &chars[..chars.len() - 2];
+    let file = hint.iter().find_map(|c| position::File::from_char(*c));
+    let rank = hint.iter().find_map(|c| position::Rank::from_char(*c));
+
+    let mut found = move_gen::generate_legal_moves(state, lookup).filter(|mv| {
+        !MOVE::is_drop(*mv)
+            && !is_castle(*mv)
+            && MOVE::piece(*mv).map(|p| pieces::get_kind(&p)) == Some(kind)
+            && MOVE::target(*mv) == target
+            && file.is_none_or(|f| MOVE::source(*mv).file() == f)
+            && rank.is_none_or(|r| MOVE::source(*mv).rank() == r)
+            && MOVE::promotion(*mv).map(|p| pieces::get_kind(&p)) == promotion
+    });
+
+    let mv = found.next()?;
+    found.next().is_none().then_some(mv)
+}
+
+fn castle<A>(state: &boardstate::State, kingside: bool, lookup: A) -> Option<MOVE::Move>
+where
+    A: PRECOMP::StaticAttack + Copy + 'static,
+{
+    move_gen::generate_legal_moves(state, lookup).find(|mv| {
+        is_castle(*mv) && (MOVE::target(*mv).file() > MOVE::source(*mv).file()) == kingside
+    })
+}
+
+fn is_castle(mv: MOVE::Move) -> bool {
+    MOVE::traits(mv).any(|t| t == MOVE::MoveTrait::Castle)
+}
+
+fn piece_kind(c: char) -> Option<pieces::Kind> {
+    match c {
+        'N' => Some(pieces::Kind::Knight),
+        'B' => Some(pieces::Kind::Bishop),
+        'R' => Some(pieces::Kind::Rook),
+        'Q' => Some(pieces::Kind::Queen),
+        'K' => Some(pieces::Kind::King),
+        _ => None,
+    }
+}
@@ -0,0 +1,62 @@
+use crate::{
+    board::{castling, pieces, position},
+    engine::{move_gen, movement as MOVE},
+    gamestate::boardstate,
+    traits::static_lookup as PRECOMP,
+};
+
+// === UCI move notation ===
+// Long algebraic notation, e.g. e2e4 or e7e8q, and N@f3 for a Crazyhouse drop.
+// Castling is stored as the king taking its own rook. With UCI_Chess960 that is
+// also how it is written, otherwise the king's destination square is sent instead.
+pub fn encode(mv: MOVE::Move, chess960: bool) -> String {
+    if MOVE::is_drop(mv) {
+        let kind = MOVE::piece(mv)
+            .map(|p| char::from(p).to_ascii_uppercase())
+            .unwrap_or('?');
+        return format!("{}@{}", kind, position::to_string(MOVE::target(mv)));
+    }
+
+    let promo = MOVE::promotion(mv)
+        .map(|p| char::from(p).to_ascii_lowercase().to_string())
+        .unwrap_or_default();
+
+    let target = if chess960 {
+        MOVE::target(mv)
+    } else {
+        castle_destination(mv).unwrap_or(MOVE::target(mv))
+    };
+
+    format!(
+        "{}{}{}",
+        position::to_string(MOVE::source(mv)),
+        position::to_string(target),
+        promo
+    )
+}
+
+// Finds the legal move written as `text` in the given position
+pub fn decode<A>(
+    state: &boardstate::State,
+    text: &str,
+    chess960: bool,
+    lookup: A,
+) -> Option<MOVE::Move>
+where
+    A: PRECOMP::StaticAttack + Copy + 'static,
+{
+    let text = text.trim();
+    move_gen::generate_legal_moves(state, lookup)
+        .find(|mv| encode(*mv, chess960).eq_ignore_ascii_case(text))
+}
+
+// Where the king lands for a castle move
+fn castle_destination(mv: MOVE::Move) -> Option<position::Position> {
+    if !MOVE::traits(mv).any(|t| t == MOVE::MoveTrait::Castle) {
+        return None;
+    }
+
+    let side = pieces::get_colour(&MOVE::piece(mv)?);
+    let kingside = MOVE::target(mv).file() > MOVE::source(mv).file();
+    castling::destinations(castling::Castling::for_side(side, kingside)).map(|(king_to, _)| king_to)
+}
@@ -0,0 +1,329 @@
+use crate::{
+    board::{colour, pieces, position},
+    consts,
+    gamestate::boardstate,
+};
+
+use super::{kpk, params};
+
+// Scores past this are wins the general terms could never add up to
+pub const KNOWN_WIN: i32 = 10000;
+
+// Scale factors are out of this, applied to the endgame half of the score
+pub const SCALE_NORMAL: i32 = 64;
+
+// === Material signature ===
+// How many of each piece both sides have, read off the material layer.
+// Picks out the endings that get a scoring function of their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signature([usize; 12]);
+
+impl Signature {
+    pub fn from_state(state: &boardstate::State) -> Self {
+        Signature(std::array::from_fn(|idx| state.material_layer[idx].count_bits()))
+    }
+
+    pub fn count(&self, side: colour::Colour<()>, kind: pieces::Kind) -> usize {
+        self.0[pieces::from_colour_kind(&side, kind).index()]
+    }
+
+    // Knights, bishops, rooks and queens
+    pub fn pieces(&self, side: colour::Colour<()>) -> usize {
+        [pieces::Kind::Knight, pieces::Kind::Bishop, pieces::Kind::Rook, pieces::Kind::Queen]
+            .into_iter()
+            .map(|kind| self.count(side, kind))
+            .sum()
+    }
+
+    pub fn is_bare(&self, side: colour::Colour<()>) -> bool {
+        self.pieces(side) == 0 && self.count(side, pieces::Kind::Pawn) == 0
+    }
+
+    // Only the given pieces beside the king
+    pub fn is_exactly(&self, side: colour::Colour<()>, kinds: &[pieces::Kind]) -> bool {
+        let others = [
+            pieces::Kind::Pawn,
+            pieces::Kind::Knight,
+            pieces::Kind::Bishop,
+            pieces::Kind::Rook,
+            pieces::Kind::Queen,
+        ];
+
+        others
+            .into_iter()
+            .all(|kind| self.count(side, kind) == kinds.iter().filter(|k| **k == kind).count())
+    }
+}
+
+// === Specialised endings ===
+// Exact or far better informed scores for the endings the general terms
+// misjudge, from white's side. None when the material is not one of them
+pub fn evaluate(state: &boardstate::State, params: &params::Params) -> Option<i32> {
+    let signature = Signature::from_state(state);
+
+    [colour::Colour::White(()), colour::Colour::Black(())]
+        .into_iter()
+        .find_map(|strong| {
+            specialised(state, &signature, params, strong).map(|value| match strong {
+                colour::Colour::White(()) => value,
+                colour::Colour::Black(()) => -value,
+            })
+        })
+}
+
+// The value for the strong side when its ending is known
+fn specialised(
+    state: &boardstate::State,
+    signature: &Signature,
+    params: &params::Params,
+    strong: colour::Colour<()>,
+) -> Option<i32> {
+    use pieces::Kind::*;
+
+    let weak = strong.opp();
+
+    if signature.is_bare(weak) {
+        if signature.is_exactly(strong, &[Pawn]) {
+            return Some(kpk(state, params, strong));
+        }
+        if signature.count(strong, Pawn) > 0 {
+            return None;
+        }
+
+        // A lone minor or two knights cannot force mate
+        if signature.is_exactly(strong, &[])
+            || signature.is_exactly(strong, &[Knight])
+            || signature.is_exactly(strong, &[Bishop])
+            || signature.is_exactly(strong, &[Knight, Knight])
+        {
+            return Some(0);
+        }
+        if signature.is_exactly(strong, &[Bishop, Knight]) {
+            return Some(kbnk(state, params, strong));
+        }
+        if signature.count(strong, Queen) > 0
+            || signature.count(strong, Rook) > 0
+            || bishop_pair(state, strong)
+            || (signature.count(strong, Bishop) > 0 && signature.count(strong, Knight) > 0)
+        {
+            return Some(kxk(state, signature, params, strong));
+        }
+
+        return None;
+    }
+
+    if signature.is_exactly(weak, &[Pawn]) {
+        if signature.is_exactly(strong, &[Rook]) {
+            return Some(krkp(state, params, strong));
+        }
+        if signature.is_exactly(strong, &[Queen]) {
+            return Some(kqkp(state, params, strong));
+        }
+    }
+
+    None
+}
+
+// === Scaling ===
+// How much of the endgame score to trust, out of SCALE_NORMAL, for
+// positions that are drawn or drawish despite the material
+pub fn scale_factor(state: &boardstate::State, eg: i32) -> i32 {
+    use pieces::Kind::*;
+
+    let signature = Signature::from_state(state);
+    let strong = if eg >= 0 { colour::Colour::White(()) } else { colour::Colour::Black(()) };
+    let weak = strong.opp();
+
+    // Rook pawns with a bishop that does not control the queening square
+    if signature.pieces(strong) == 1
+        && signature.count(strong, Bishop) == 1
+        && signature.count(strong, Pawn) > 0
+        && signature.is_bare(weak)
+    {
+        if let Some(queening) = rook_pawn_queening(state, strong) {
+            let bishop = square(state, strong, Bishop);
+            let weak_king = square(state, weak, King);
+
+            if bishop.colour() != queening.colour() && weak_king.distance(queening) <= 1 {
+                return 0;
+            }
+        }
+    }
+
+    // Opposite-coloured bishops hold a pawn or two down, less so with more on the board
+    if signature.count(strong, Bishop) == 1
+        && signature.count(weak, Bishop) == 1
+        && square(state, strong, Bishop).colour() != square(state, weak, Bishop).colour()
+    {
+        return if signature.pieces(strong) == 1 && signature.pieces(weak) == 1 { 18 } else { 46 };
+    }
+
+    SCALE_NORMAL
+}
+
+// === Scoring functions ===
+fn kpk(state: &boardstate::State, params: &params::Params, strong: colour::Colour<()>) -> i32 {
+    // The bitbase is solved with white holding the pawn
+    let relative = |pos: position::Position| match strong {
+        colour::Colour::White(()) => pos,
+        colour::Colour::Black(()) => pos.flip_vertical(),
+    };
+    let strong_king = relative(square(state, strong, pieces::Kind::King));
+    let pawn = relative(square(state, strong, pieces::Kind::Pawn));
+    let weak_king = relative(square(state, strong.opp(), pieces::Kind::King));
+
+    if kpk::probe(strong_king, pawn, weak_king, state.side_to_move == strong) {
+        KNOWN_WIN + params.material(pieces::Kind::Pawn).eg + 10 * pawn.rank() as i32
+    } else {
+        0
+    }
+}
+
+// Mate with enough material, the weak king goes to the edge
+fn kxk(
+    state: &boardstate::State,
+    signature: &Signature,
+    params: &params::Params,
+    strong: colour::Colour<()>,
+) -> i32 {
+    let strong_king = square(state, strong, pieces::Kind::King);
+    let weak_king = square(state, strong.opp(), pieces::Kind::King);
+
+    let material: i32 = [pieces::Kind::Knight, pieces::Kind::Bishop, pieces::Kind::Rook, pieces::Kind::Queen]
+        .into_iter()
+        .map(|kind| signature.count(strong, kind) as i32 * params.material(kind).eg)
+        .sum();
+
+    KNOWN_WIN + material + push_to_edge(weak_king) + push_close(strong_king, weak_king)
+}
+
+// Bishop and knight only mate in a corner the bishop can reach
+fn kbnk(state: &boardstate::State, params: &params::Params, strong: colour::Colour<()>) -> i32 {
+    let strong_king = square(state, strong, pieces::Kind::King);
+    let weak_king = square(state, strong.opp(), pieces::Kind::King);
+    let bishop = square(state, strong, pieces::Kind::Bishop);
+
+    let corners = if bishop.colour() == position::Position::A1.colour() {
+        [position::Position::A1, position::Position::H8]
+    } else {
+        [position::Position::A8, position::Position::H1]
+    };
+    let corner = corners
+        .into_iter()
+        .map(|corner| weak_king.manhattan_distance(corner) as i32)
+        .min()
+        .unwrap_or(0);
+
+    KNOWN_WIN
+        + params.material(pieces::Kind::Bishop).eg
+        + params.material(pieces::Kind::Knight).eg
+        + 20 * (14 - corner)
+        + push_close(strong_king, weak_king)
+}
+
+// Rook against pawn, a win unless the pawn is far on and its king is near
+fn krkp(state: &boardstate::State, params: &params::Params, strong: colour::Colour<()>) -> i32 {
+    // Seen from the strong side, the pawn runs down the board
+    let relative = |kind: pieces::Kind, side: colour::Colour<()>| {
+        let pos = square(state, side, kind);
+        match strong {
+            colour::Colour::White(()) => pos,
+            colour::Colour::Black(()) => pos.flip_vertical(),
+        }
+    };
+    let strong_king = relative(pieces::Kind::King, strong);
+    let weak_king = relative(pieces::Kind::King, strong.opp());
+    let rook = relative(pieces::Kind::Rook, strong);
+    let pawn = relative(pieces::Kind::Pawn, strong.opp());
+
+    let queening = position::Position::new(pawn.file(), position::Rank::R1);
+    let ahead = pawn.change_rank(-1).unwrap_or(queening);
+    let strong_to_move = state.side_to_move == strong;
+    let rook_value = params.material(pieces::Kind::Rook).eg;
+
+    if strong_king.file() == pawn.file() && strong_king.rank() < pawn.rank() {
+        // The king stands in front of the pawn
+        rook_value - strong_king.distance(pawn) as i32
+    } else if weak_king.distance(pawn) >= 3 + !strong_to_move as u8 && weak_king.distance(rook) >= 3 {
+        // The pawn's king is too far from both pawn and rook
+        rook_value - strong_king.distance(pawn) as i32
+    } else if weak_king.rank() <= position::Rank::R3
+        && weak_king.distance(pawn) == 1
+        && strong_king.rank() >= position::Rank::R4
+        && strong_king.distance(pawn) > 2 + strong_to_move as u8
+    {
+        // Far on and escorted, the rook has to give itself up
+        80 - 8 * strong_king.distance(pawn) as i32
+    } else {
+        200 - 8
+            * (strong_king.distance(ahead) as i32
+                - weak_king.distance(ahead) as i32
+                - pawn.distance(queening) as i32)
+    }
+}
+
+// Queen against pawn, a win unless a rook or bishop pawn is one step from queening
+fn kqkp(state: &boardstate::State, params: &params::Params, strong: colour::Colour<()>) -> i32 {
+    let weak = strong.opp();
+    let strong_king = square(state, strong, pieces::Kind::King);
+    let weak_king = square(state, weak, pieces::Kind::King);
+    let pawn = square(state, weak, pieces::Kind::Pawn);
+
+    let drawish_file = matches!(
+        pawn.file(),
+        position::File::A | position::File::C | position::File::F | position::File::H
+    );
+    let mut value = push_close(strong_king, weak_king);
+
+    if pawn.relative_rank(weak) != position::Rank::R7 || weak_king.distance(pawn) != 1 || !drawish_file {
+        value += params.material(pieces::Kind::Queen).eg - params.material(pieces::Kind::Pawn).eg;
+    }
+
+    value
+}
+
+// === Helpers ===
+// Square of a piece the signature says there is exactly one of
+fn square(state: &boardstate::State, side: colour::Colour<()>, kind: pieces::Kind) -> position::Position {
+    state.material_layer[pieces::from_colour_kind(&side, kind)]
+        .get_ls1b()
+        .unwrap_or(position::Position::A8)
+}
+
+// Bishops on both colours of square
+pub(crate) fn bishop_pair(state: &boardstate::State, side: colour::Colour<()>) -> bool {
+    let bishops = state.material_layer[pieces::from_colour_kind(&side, pieces::Kind::Bishop)];
+    let mut colours = bishops.iter().map(|pos| pos.colour());
+
+    match colours.next() {
+        Some(first) => colours.any(|other| other != first),
+        None => false,
+    }
+}
+
+// Queening square when every pawn of the side is on the a-file or every one on the h-file
+fn rook_pawn_queening(state: &boardstate::State, side: colour::Colour<()>) -> Option<position::Position> {
+    let pawns = state.material_layer[pieces::from_colour_kind(&side, pieces::Kind::Pawn)];
+    let last = position::Rank::R8.relative(side);
+
+    if (pawns & consts::NOT_A_FILE).is_empty() {
+        Some(position::Position::new(position::File::A, last))
+    } else if (pawns & consts::NOT_H_FILE).is_empty() {
+        Some(position::Position::new(position::File::H, last))
+    } else {
+        None
+    }
+}
+
+// Larger the nearer the king is to a corner
+fn push_to_edge(pos: position::Position) -> i32 {
+    let file = (2 * pos.file() as i32 - 7).abs();
+    let rank = (2 * pos.rank() as i32 - 7).abs();
+
+    10 * (file + rank)
+}
+
+fn push_close(a: position::Position, b: position::Position) -> i32 {
+    20 * (7 - a.distance(b) as i32)
+}
+
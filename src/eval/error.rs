@@ -0,0 +1,33 @@
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    // A line of a parameter file that could not be read, holds the line number and why
+    Parameter(usize, String),
+    // A line of a position file that could not be read, holds the line number and why
+    Position(usize, String),
+    // Nothing to tune against
+    NoPositions,
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(err) => writeln!(f, "Could not read or write the file: {}", err)?,
+            Error::Parameter(line, reason) => writeln!(f, "Bad parameter on line {}: {}", line, reason)?,
+            Error::Position(line, reason) => writeln!(f, "Bad position on line {}: {}", line, reason)?,
+            Error::NoPositions => writeln!(f, "No positions to tune with")?,
+        }
+
+        Ok(())
+    }
+}
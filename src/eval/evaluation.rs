@@ -0,0 +1,199 @@
+use std::cell::RefCell;
+
+use crate::{
+    board::{colour, pieces, position},
+    effects::static_attack_provider,
+    gamestate::boardstate,
+    traits::static_lookup as PRECOMP,
+};
+
+use super::{endgame, king_safety, mobility, params, pawns, score, trace};
+
+// One evaluator with the default weights per thread, so threads never wait on
+// each other and each keeps its own pawn table
+thread_local! {
+    static DEFAULT: RefCell<Evaluator<static_attack_provider::StaticAttackProvider>> =
+        RefCell::new(Evaluator::new(static_attack_provider::StaticAttackProvider));
+}
+
+// Names of the general terms, in the order breakdown gives them
+pub const TERMS: [&str; 6] = [
+    "Material",
+    "Piece-square",
+    "Pawns",
+    "Passed pawns",
+    "King safety",
+    "Mobility",
+];
+
+// Phase weight of a knight, bishop, rook and queen
+const PHASE_WEIGHTS: [(pieces::Kind, i32); 4] = [
+    (pieces::Kind::Knight, 1),
+    (pieces::Kind::Bishop, 1),
+    (pieces::Kind::Rook, 2),
+    (pieces::Kind::Queen, 4),
+];
+
+// === Static evaluation ===
+// Scores a position in centipawns. Endings with a scoring function of their
+// own skip the general terms, everything else is tapered between the
+// middlegame and endgame values and scaled for drawish material.
+#[derive(Debug, Clone)]
+pub struct Evaluator<A> {
+    lookup: A,
+    params: params::Params,
+    pawn_table: pawns::PawnTable,
+}
+
+impl<A> Evaluator<A>
+where
+    A: PRECOMP::StaticAttack,
+{
+    pub fn new(lookup: A) -> Self {
+        Evaluator {
+            lookup,
+            params: params::Params::default(),
+            pawn_table: pawns::PawnTable::default(),
+        }
+    }
+
+    pub fn with_params(mut self, params: params::Params) -> Self {
+        self.params = params;
+        self.pawn_table.clear();
+        self
+    }
+
+    pub fn with_pawn_table(mut self, entries: usize) -> Self {
+        self.pawn_table = pawns::PawnTable::new(entries);
+        self
+    }
+
+    pub fn params(&self) -> &params::Params {
+        &self.params
+    }
+
+    pub fn pawn_table(&self) -> &pawns::PawnTable {
+        &self.pawn_table
+    }
+
+    // From the side to move's point of view
+    pub fn evaluate(&mut self, state: &boardstate::State) -> i32 {
+        let value = match endgame::evaluate(state, &self.params) {
+            Some(value) => value,
+            None => {
+                let score = total(&self.breakdown(state));
+                score.taper(phase(state), endgame::scale_factor(state, score.eg))
+            }
+        };
+
+        match state.side_to_move {
+            colour::Colour::White(()) => value,
+            colour::Colour::Black(()) => -value,
+        }
+    }
+
+    // Every term for both sides, and how they were put together
+    pub fn trace(&mut self, state: &boardstate::State) -> trace::Trace {
+        let breakdown = self.breakdown(state);
+        let score = total(&breakdown);
+        let phase = phase(state);
+        let scale = endgame::scale_factor(state, score.eg);
+        let specialised = endgame::evaluate(state, &self.params);
+
+        trace::Trace {
+            board: state.material_layer,
+            terms: TERMS
+                .into_iter()
+                .zip(breakdown)
+                .map(|(name, [white, black])| trace::Term { name, white, black })
+                .collect(),
+            phase,
+            scale,
+            specialised,
+            value: specialised.unwrap_or_else(|| score.taper(phase, scale)),
+        }
+    }
+
+    fn breakdown(&mut self, state: &boardstate::State) -> [[score::Score; 2]; TERMS.len()] {
+        let entry = self.pawn_table.probe(state, &self.params);
+        breakdown(state, &self.params, &self.lookup, &entry)
+    }
+}
+
+// The general terms, each split into white's and black's share
+pub(crate) fn breakdown<A>(
+    state: &boardstate::State,
+    params: &params::Params,
+    lookup: &A,
+    entry: &pawns::PawnEntry,
+) -> [[score::Score; 2]; TERMS.len()]
+where
+    A: PRECOMP::StaticAttack,
+{
+    let side = |us: colour::Colour<()>| {
+        let (material, squares) = material(state, params, us);
+        [
+            material,
+            squares,
+            entry.score[us],
+            pawns::passers(state, entry, params, us),
+            king_safety::side(state, params, lookup, us),
+            mobility::side(state, params, lookup, us),
+        ]
+    };
+    let white = side(colour::Colour::White(()));
+    let black = side(colour::Colour::Black(()));
+
+    std::array::from_fn(|term| [white[term], black[term]])
+}
+
+// Material and piece-square values of one side
+fn material(
+    state: &boardstate::State,
+    params: &params::Params,
+    us: colour::Colour<()>,
+) -> (score::Score, score::Score) {
+    state
+        .mailbox_layer
+        .0
+        .iter()
+        .zip(position::Position::ALL)
+        .filter_map(|(piece, pos)| piece.filter(|p| pieces::get_colour(p) == us).map(|p| (p, pos)))
+        .fold((score::Score::ZERO, score::Score::ZERO), |(material, squares), (piece, pos)| {
+            (
+                material + params.material(pieces::get_kind(&piece)),
+                squares + params.square(piece, pos),
+            )
+        })
+}
+
+// White minus black over every term
+pub(crate) fn total(breakdown: &[[score::Score; 2]]) -> score::Score {
+    breakdown
+        .iter()
+        .fold(score::Score::ZERO, |acc, [white, black]| acc + *white - *black)
+}
+
+// Evaluate with the default weights
+pub fn evaluate(state: &boardstate::State) -> i32 {
+    DEFAULT.with(|evaluator| evaluator.borrow_mut().evaluate(state))
+}
+
+// Trace with the default weights
+pub fn eval_trace(state: &boardstate::State) -> trace::Trace {
+    DEFAULT.with(|evaluator| evaluator.borrow_mut().trace(state))
+}
+
+// MAX_PHASE with all the pieces on, 0 with only kings and pawns
+pub fn phase(state: &boardstate::State) -> i32 {
+    let phase: i32 = PHASE_WEIGHTS
+        .iter()
+        .map(|(kind, weight)| {
+            let white = state.material_layer[pieces::Piece(colour::Colour::White(*kind))].count_bits();
+            let black = state.material_layer[pieces::Piece(colour::Colour::Black(*kind))].count_bits();
+            (white + black) as i32 * weight
+        })
+        .sum();
+
+    phase.min(score::MAX_PHASE)
+}
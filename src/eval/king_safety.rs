@@ -0,0 +1,225 @@
+use crate::{
+    board::{bitboard, colour, pieces, position},
+    gamestate::{boardstate, occupancy_layer},
+    traits::static_lookup as PRECOMP,
+};
+
+use super::{mobility, params, score};
+
+// Danger stops growing here, past it the king is lost whatever else happens
+const MAX_DANGER: i32 = 500;
+
+// Attack units to danger, slow with one or two hits and steep once the attack
+// is well supported, then flat
+const DANGER: [i32; 64] = danger_table();
+
+const fn danger_table() -> [i32; 64] {
+    let mut table = [0; 64];
+    let mut units = 0;
+    while units < 64 {
+        let danger = (units * units / 5 + units) as i32;
+        table[units] = if danger > MAX_DANGER { MAX_DANGER } else { danger };
+        units += 1;
+    }
+
+    table
+}
+
+// The pieces that count towards an attack, in the order of Params::attack_weights
+const ATTACKERS: [pieces::Kind; 4] = [
+    pieces::Kind::Knight,
+    pieces::Kind::Bishop,
+    pieces::Kind::Rook,
+    pieces::Kind::Queen,
+];
+
+// === King safety ===
+// Pawn cover in front of each king, enemy pawns coming at it, open files
+// beside it, and how hard the enemy pieces hit the squares around it.
+// White minus black
+pub fn evaluate<A>(state: &boardstate::State, params: &params::Params, lookup: &A) -> score::Score
+where
+    A: PRECOMP::StaticAttack,
+{
+    side(state, params, lookup, colour::Colour::White(()))
+        - side(state, params, lookup, colour::Colour::Black(()))
+}
+
+// One side's terms
+pub(crate) fn side<A>(state: &boardstate::State, params: &params::Params, lookup: &A, us: colour::Colour<()>) -> score::Score
+where
+    A: PRECOMP::StaticAttack,
+{
+    // Variants such as Horde can leave a side without a king
+    let Some(king) = state.material_layer[pieces::from_colour_kind(&us, pieces::Kind::King)].get_ls1b() else {
+        return score::Score::ZERO;
+    };
+
+    shelter(state, params, us, king) + attack(state, params, lookup, us, king)
+}
+
+// === Pawn shield and storm ===
+fn shelter(
+    state: &boardstate::State,
+    params: &params::Params,
+    us: colour::Colour<()>,
+    king: position::Position,
+) -> score::Score {
+    let ours = state.material_layer[pieces::from_colour_kind(&us, pieces::Kind::Pawn)];
+    let theirs = state.material_layer[pieces::from_colour_kind(&us.opp(), pieces::Kind::Pawn)];
+    let ahead = bitboard::Bitboard(1 << king as u64).front_span(us).rank_fill();
+
+    // A king on the edge is covered by the same three files as one a file in
+    let centre = king.file().clamp(position::File::B, position::File::G) as i8;
+    let mut total = score::Score::ZERO;
+
+    for delta in -1..=1 {
+        let Some(file) = position::File::from_index((centre + delta) as u8) else {
+            continue;
+        };
+        let mask = bitboard::Bitboard(1 << position::Position::new(file, position::Rank::R1) as u64).file_fill();
+
+        let nearest = |pawns: bitboard::Bitboard| {
+            (pawns & mask & ahead)
+                .iter()
+                .map(|pawn| pawn.rank().distance(king.rank()))
+                .min()
+        };
+
+        total += match nearest(ours) {
+            Some(distance) if distance < 4 => params.shield[distance as usize],
+            _ => params.shield[0],
+        };
+
+        total += match nearest(theirs) {
+            Some(distance) if distance < 4 => {
+                let stormer = (theirs & mask & ahead)
+                    .iter()
+                    .find(|pawn| pawn.rank().distance(king.rank()) == distance);
+                let blocked = stormer
+                    .and_then(|pawn| pawn.change_rank(match us {
+                        colour::Colour::White(()) => -1,
+                        colour::Colour::Black(()) => 1,
+                    }))
+                    .map(|stop| ours.is_occupied(stop as u64))
+                    .unwrap_or(false);
+
+                if blocked {
+                    params.storm_blocked
+                } else {
+                    params.storm[distance as usize]
+                }
+            }
+            _ => params.storm[0],
+        };
+
+        if (ours & mask).is_empty() {
+            total += if (theirs & mask).is_empty() {
+                params.king_open_file
+            } else {
+                params.king_half_open_file
+            };
+        }
+    }
+
+    total
+}
+
+// === Attack units ===
+// Every enemy piece that reaches the king's zone adds its weight per square hit.
+// A lone attacker is not an attack, so nothing counts until there are two.
+fn attack<A>(
+    state: &boardstate::State,
+    params: &params::Params,
+    lookup: &A,
+    us: colour::Colour<()>,
+    king: position::Position,
+) -> score::Score
+where
+    A: PRECOMP::StaticAttack,
+{
+    let zone = lookup.king(king) | bitboard::Bitboard(1 << king as u64);
+    let occupied = occupancy_layer::get_both(&state.occupancy_layer);
+
+    let mut attackers = 0;
+    let mut units = 0;
+
+    for (kind, weight) in ATTACKERS.into_iter().zip(params.attack_weights) {
+        for pos in state.material_layer[pieces::from_colour_kind(&us.opp(), kind)].iter() {
+            let hits = (mobility::piece_attacks(lookup, kind, pos, occupied) & zone).count_bits();
+
+            if hits > 0 {
+                attackers += 1;
+                units += weight * hits as i32;
+            }
+        }
+    }
+
+    if attackers < 2 {
+        return score::Score::ZERO;
+    }
+
+    let danger = DANGER[units.clamp(0, DANGER.len() as i32 - 1) as usize];
+    score::Score::new(
+        -danger * params.king_danger.mg / 100,
+        -danger * params.king_danger.eg / 100,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effects::static_attack_provider::StaticAttackProvider;
+
+    const WHITE: colour::Colour<()> = colour::Colour::White(());
+
+    fn white_king(fen: &str, params: &params::Params) -> score::Score {
+        side(&boardstate::try_from_fen(fen).unwrap(), params, &StaticAttackProvider, WHITE)
+    }
+
+    #[test]
+    fn open_files_beside_the_king() {
+        let mut params = params::Params::zeroed();
+        params.king_open_file = score::Score::new(-30, -3);
+        params.king_half_open_file = score::Score::new(-20, -2);
+
+        // Full cover, then no pawn at all on g, then only a black one
+        assert_eq!(white_king("6k1/8/8/8/8/8/5PPP/6K1 w - - 0 1", &params), score::Score::ZERO);
+        assert_eq!(white_king("6k1/8/8/8/8/8/5P1P/6K1 w - - 0 1", &params), params.king_open_file);
+        assert_eq!(white_king("6k1/6p1/8/8/8/8/5P1P/6K1 w - - 0 1", &params), params.king_half_open_file);
+
+        // A king in the corner looks at the same three files as one beside it
+        assert_eq!(white_king("6k1/8/8/8/8/8/5P1P/7K w - - 0 1", &params), params.king_open_file);
+    }
+
+    #[test]
+    fn pawn_shield_by_distance() {
+        let mut params = params::Params::zeroed();
+        params.shield = [
+            score::Score::new(-40, 0),
+            score::Score::new(20, 0),
+            score::Score::new(10, 0),
+            score::Score::new(5, 0),
+        ];
+
+        assert_eq!(white_king("6k1/8/8/8/8/8/5PPP/6K1 w - - 0 1", &params), params.shield[1] * 3);
+        assert_eq!(
+            white_king("6k1/8/8/8/8/6P1/5P1P/6K1 w - - 0 1", &params),
+            params.shield[1] * 2 + params.shield[2]
+        );
+        assert_eq!(white_king("6k1/8/8/8/8/8/8/6K1 w - - 0 1", &params), params.shield[0] * 3);
+    }
+
+    #[test]
+    fn attacks_need_two_pieces() {
+        let mut params = params::Params::zeroed();
+        params.king_danger = score::Score::new(100, 0);
+
+        // The knight alone hits g1 and h2, the queen then joins in on h2
+        let knight = white_king("6k1/8/8/8/8/5n2/5PPP/6K1 w - - 0 1", &params);
+        let both = white_king("6k1/8/8/8/7q/5n2/5PPP/6K1 w - - 0 1", &params);
+
+        assert_eq!(knight, score::Score::ZERO);
+        assert!(both.mg < 0 && both.eg == 0, "{:?}", both);
+    }
+}
@@ -0,0 +1,246 @@
+use once_cell::sync::Lazy;
+
+use crate::board::position;
+
+// === KPK bitbase ===
+// Win or draw for king and pawn against king, solved for every position the
+// first time it is needed. The table holds white with the pawn on files a-d,
+// probe mirrors the board into that. Squares here run a1 = 0 to h8 = 63.
+
+// Both sides to move, both kings, and the pawn on a2-d7
+const SIZE: usize = 2 * 24 * 64 * 64;
+
+// Results are flags so the results of a position's moves can be OR-ed together
+const INVALID: u8 = 0;
+const UNKNOWN: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 4;
+
+static BITBASE: Lazy<Vec<u64>> = Lazy::new(|| {
+    let mut results: Vec<u8> = (0..SIZE).map(classify_start).collect();
+
+    // Resolve from the positions already known until nothing changes
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for idx in 0..SIZE {
+            if results[idx] == UNKNOWN {
+                let result = classify(idx, &results);
+                if result != UNKNOWN {
+                    results[idx] = result;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    let mut bits = vec![0u64; SIZE / 64];
+    for (idx, result) in results.iter().enumerate() {
+        if *result == WIN {
+            bits[idx / 64] |= 1 << (idx % 64);
+        }
+    }
+
+    bits
+});
+
+// True when white wins, with the pawn belonging to white
+pub fn probe(
+    white_king: position::Position,
+    pawn: position::Position,
+    black_king: position::Position,
+    white_to_move: bool,
+) -> bool {
+    let (mut wk, mut bk, mut psq) = (square(white_king), square(black_king), square(pawn));
+    if psq & 7 > 3 {
+        wk ^= 7;
+        bk ^= 7;
+        psq ^= 7;
+    }
+
+    let idx = index(!white_to_move, wk, bk, psq);
+    BITBASE[idx / 64] & (1 << (idx % 64)) != 0
+}
+
+fn square(pos: position::Position) -> usize {
+    pos as usize ^ 56
+}
+
+fn index(black_to_move: bool, white_king: usize, black_king: usize, pawn: usize) -> usize {
+    let pawn = (pawn >> 3) - 1 + 6 * (pawn & 7);
+    white_king | black_king << 6 | (black_to_move as usize) << 12 | pawn << 13
+}
+
+fn decode(idx: usize) -> (bool, usize, usize, usize) {
+    let pawn = idx >> 13;
+    (
+        (idx >> 12) & 1 == 1,
+        idx & 63,
+        (idx >> 6) & 63,
+        (pawn % 6 + 1) * 8 + pawn / 6,
+    )
+}
+
+fn distance(a: usize, b: usize) -> usize {
+    (a & 7).abs_diff(b & 7).max((a >> 3).abs_diff(b >> 3))
+}
+
+fn king_moves(sq: usize) -> impl Iterator<Item = usize> {
+    (0..64).filter(move |to| distance(sq, *to) == 1)
+}
+
+fn pawn_attacks(pawn: usize, sq: usize) -> bool {
+    sq >> 3 == (pawn >> 3) + 1 && (sq & 7).abs_diff(pawn & 7) == 1
+}
+
+// What can be told without looking at any move
+fn classify_start(idx: usize) -> u8 {
+    let (black_to_move, wk, bk, psq) = decode(idx);
+    let push = psq + 8;
+
+    if distance(wk, bk) <= 1 || wk == psq || bk == psq || (!black_to_move && pawn_attacks(psq, bk)) {
+        INVALID
+    } else if !black_to_move
+        && psq >> 3 == 6
+        && wk != push
+        && (distance(bk, push) > 1 || distance(wk, push) == 1)
+    {
+        // The pawn queens and cannot be taken
+        WIN
+    } else if black_to_move
+        && (king_moves(bk).all(|to| distance(wk, to) <= 1 || pawn_attacks(psq, to))
+            || (distance(bk, psq) == 1 && distance(wk, psq) > 1))
+    {
+        // Stalemate, or the pawn falls
+        DRAW
+    } else {
+        UNKNOWN
+    }
+}
+
+// White wins if any move wins, black draws if any move draws
+fn classify(idx: usize, results: &[u8]) -> u8 {
+    let (black_to_move, wk, bk, psq) = decode(idx);
+    let mut found = INVALID;
+
+    if black_to_move {
+        for to in king_moves(bk) {
+            found |= results[index(false, wk, to, psq)];
+        }
+    } else {
+        for to in king_moves(wk) {
+            found |= results[index(true, to, bk, psq)];
+        }
+
+        // Pushes onto a king's square land on an invalid position and add nothing
+        if psq >> 3 < 6 {
+            found |= results[index(true, wk, bk, psq + 8)];
+        }
+        if psq >> 3 == 1 && psq + 8 != wk && psq + 8 != bk {
+            found |= results[index(true, wk, bk, psq + 16)];
+        }
+    }
+
+    let (good, bad) = if black_to_move { (DRAW, WIN) } else { (WIN, DRAW) };
+    if found & good != 0 {
+        good
+    } else if found & UNKNOWN != 0 {
+        UNKNOWN
+    } else {
+        bad
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        effects::static_attack_provider::StaticAttackProvider,
+        gamestate::boardstate,
+        tablebase::{dtm, retrograde},
+    };
+
+    use position::Position::*;
+
+    #[test]
+    fn textbook_positions() {
+        // White king, pawn, black king, white to move, white wins
+        let cases = [
+            // The king two ranks ahead of its pawn wins whoever moves
+            (E6, E4, E8, true, true),
+            (E6, E4, E8, false, true),
+            // Opposition decides with the king one rank ahead
+            (E4, E3, E6, true, false),
+            (E4, E3, E6, false, true),
+            // The pawn outruns the king, and mirrored onto the other wing
+            (H1, A5, H8, false, true),
+            (A1, H5, A8, false, true),
+            // The pawn falls
+            (H1, E4, D4, false, false),
+            // The defending king reaches the rook pawn's corner
+            (C5, A5, A8, true, false),
+            (F5, H5, H8, true, false),
+        ];
+
+        for (white_king, pawn, black_king, white_to_move, wins) in cases {
+            assert_eq!(
+                probe(white_king, pawn, black_king, white_to_move),
+                wins,
+                "{:?} {:?} {:?} white to move {}",
+                white_king,
+                pawn,
+                black_king,
+                white_to_move
+            );
+        }
+    }
+
+    // Minutes without optimisations, a few seconds with them
+    #[test]
+    #[cfg_attr(debug_assertions, ignore = "slow without --release")]
+    fn agrees_with_the_retrograde_table() {
+        let mut generator = retrograde::Generator::new(StaticAttackProvider);
+        let table = generator.generate("KPvK").unwrap();
+        let mut wins = 0;
+        let pawn_squares = position::Position::ALL
+            .into_iter()
+            .filter(|p| !matches!(p.rank(), position::Rank::R1 | position::Rank::R8));
+
+        for white_king in position::Position::ALL {
+            for black_king in position::Position::ALL {
+                for pawn in pawn_squares.clone() {
+                    if white_king == black_king || pawn == white_king || pawn == black_king {
+                        continue;
+                    }
+
+                    for white_to_move in [true, false] {
+                        let mut board = ['1'; 64];
+                        board[white_king as usize] = 'K';
+                        board[black_king as usize] = 'k';
+                        board[pawn as usize] = 'P';
+
+                        let placement = board
+                            .chunks(8)
+                            .map(|rank| rank.iter().collect::<String>())
+                            .collect::<Vec<_>>()
+                            .join("/");
+                        let fen = format!("{} {} - - 0 1", placement, if white_to_move { 'w' } else { 'b' });
+                        let Ok(state) = boardstate::try_from_fen_strict(&fen) else {
+                            continue;
+                        };
+
+                        let expected = matches!(
+                            (table.probe(&state), white_to_move),
+                            (Some(dtm::Dtm::Win(_)), true) | (Some(dtm::Dtm::Loss(_)), false)
+                        );
+                        assert_eq!(probe(white_king, pawn, black_king, white_to_move), expected, "{}", fen);
+                        wins += expected as u32;
+                    }
+                }
+            }
+        }
+
+        // White to move and black to move together
+        assert_eq!(wins, 222564);
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{
+    board::{bitboard, colour, pieces, position},
+    gamestate::{boardstate, occupancy_layer},
+    traits::static_lookup as PRECOMP,
+};
+
+use super::{endgame, params, pawns, score};
+
+// === Mobility and piece activity ===
+// Squares each piece reaches with the same attack lookups the move generator
+// uses, leaving out squares of our own and squares enemy pawns guard.
+// Then the placement terms: the bishop pair, rooks on open files and the
+// seventh, knights on outposts, and pieces shut in. White minus black
+pub fn evaluate<A>(state: &boardstate::State, params: &params::Params, lookup: &A) -> score::Score
+where
+    A: PRECOMP::StaticAttack,
+{
+    side(state, params, lookup, colour::Colour::White(()))
+        - side(state, params, lookup, colour::Colour::Black(()))
+}
+
+// Squares a piece attacks on the board as it stands
+pub(crate) fn piece_attacks<A>(
+    lookup: &A,
+    kind: pieces::Kind,
+    pos: position::Position,
+    occupied: bitboard::Bitboard,
+) -> bitboard::Bitboard
+where
+    A: PRECOMP::StaticAttack,
+{
+    match kind {
+        pieces::Kind::Knight => lookup.knight(pos),
+        pieces::Kind::Bishop => lookup.bishop(pos, occupied),
+        pieces::Kind::Rook => lookup.rook(pos, occupied),
+        pieces::Kind::Queen => lookup.queen(pos, occupied),
+        pieces::Kind::King => lookup.king(pos),
+        pieces::Kind::Pawn => bitboard::Bitboard(0),
+    }
+}
+
+// One side's terms
+pub(crate) fn side<A>(state: &boardstate::State, params: &params::Params, lookup: &A, us: colour::Colour<()>) -> score::Score
+where
+    A: PRECOMP::StaticAttack,
+{
+    let them = us.opp();
+    let ours = state.material_layer[pieces::from_colour_kind(&us, pieces::Kind::Pawn)];
+    let theirs = state.material_layer[pieces::from_colour_kind(&them, pieces::Kind::Pawn)];
+    let occupied = occupancy_layer::get_both(&state.occupancy_layer);
+    let area = !(state.occupancy_layer[us] | pawns::attacks(theirs, them));
+
+    let pieces_of = |kind: pieces::Kind| state.material_layer[pieces::from_colour_kind(&us, kind)];
+    let mut total = score::Score::ZERO;
+
+    // Mobility
+    for pos in pieces_of(pieces::Kind::Knight).iter() {
+        let count = (piece_attacks(lookup, pieces::Kind::Knight, pos, occupied) & area).count_bits();
+        total += params.knight_mobility[count];
+    }
+    for pos in pieces_of(pieces::Kind::Bishop).iter() {
+        let count = (piece_attacks(lookup, pieces::Kind::Bishop, pos, occupied) & area).count_bits();
+        total += params.bishop_mobility[count];
+    }
+    for pos in pieces_of(pieces::Kind::Queen).iter() {
+        let count = (piece_attacks(lookup, pieces::Kind::Queen, pos, occupied) & area).count_bits();
+        total += params.queen_mobility[count];
+    }
+
+    let king = pieces_of(pieces::Kind::King).get_ls1b();
+    let their_king = state.material_layer[pieces::from_colour_kind(&them, pieces::Kind::King)].get_ls1b();
+
+    for pos in pieces_of(pieces::Kind::Rook).iter() {
+        let count = (piece_attacks(lookup, pieces::Kind::Rook, pos, occupied) & area).count_bits();
+        total += params.rook_mobility[count];
+
+        // Open and half open files
+        let file = bitboard::Bitboard(1 << pos as u64).file_fill();
+        if (file & ours).is_empty() {
+            total += if (file & theirs).is_empty() {
+                params.rook_open_file
+            } else {
+                params.rook_semi_open_file
+            };
+        }
+
+        // The seventh, while it holds pawns or cuts the king off on the eighth
+        let rank = bitboard::Bitboard(1 << pos as u64).rank_fill();
+        if pos.relative_rank(us) == position::Rank::R7
+            && (!(rank & theirs).is_empty()
+                || their_king.is_some_and(|king| king.relative_rank(us) == position::Rank::R8))
+        {
+            total += params.rook_seventh;
+        }
+
+        // Shut in on the back rank by its own uncastled king
+        if let Some(king) = king {
+            let rank = position::Rank::R1.relative(us);
+            let towards_corner = if king.file() < position::File::E {
+                pos.file() < king.file()
+            } else {
+                pos.file() > king.file()
+            };
+
+            if count <= 3 && pos.rank() == rank && king.rank() == rank && towards_corner {
+                total += params.trapped_rook;
+            }
+        }
+    }
+
+    // Outposts, guarded by a pawn and out of reach of every enemy pawn
+    for pos in pieces_of(pieces::Kind::Knight).iter() {
+        let knight = bitboard::Bitboard(1 << pos as u64);
+        let rank = pos.relative_rank(us);
+
+        if (position::Rank::R4..=position::Rank::R6).contains(&rank)
+            && !(knight & pawns::attacks(ours, us)).is_empty()
+            && (knight.attack_span(us) & theirs).is_empty()
+        {
+            total += params.knight_outpost;
+        }
+    }
+
+    // A bishop that took a rook pawn and is walled in by the next pawn along
+    for pos in pieces_of(pieces::Kind::Bishop).iter() {
+        let relative = match us {
+            colour::Colour::White(()) => pos,
+            colour::Colour::Black(()) => pos.flip_vertical(),
+        };
+        let wall = match relative {
+            position::Position::A7 => Some(position::Position::B6),
+            position::Position::H7 => Some(position::Position::G6),
+            _ => None,
+        };
+        let wall = wall.map(|wall| match us {
+            colour::Colour::White(()) => wall,
+            colour::Colour::Black(()) => wall.flip_vertical(),
+        });
+
+        if wall.is_some_and(|wall| theirs.is_occupied(wall as u64)) {
+            total += params.trapped_bishop;
+        }
+    }
+
+    if endgame::bishop_pair(state, us) {
+        total += params.bishop_pair;
+    }
+
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effects::static_attack_provider::StaticAttackProvider;
+
+    fn score(fen: &str, params: &params::Params) -> score::Score {
+        evaluate(&boardstate::try_from_fen(fen).unwrap(), params, &StaticAttackProvider)
+    }
+
+    #[test]
+    fn rooks_on_the_seventh() {
+        let mut params = params::Params::zeroed();
+        params.rook_seventh = score::Score::new(20, 40);
+
+        // Cutting the king off, then eating pawns, then neither
+        assert_eq!(score("6k1/3R4/8/8/8/8/8/6K1 w - - 0 1", &params), params.rook_seventh);
+        assert_eq!(score("8/p2R4/6k1/8/8/8/8/6K1 w - - 0 1", &params), params.rook_seventh);
+        assert_eq!(score("8/3R4/6k1/8/8/8/8/6K1 w - - 0 1", &params), score::Score::ZERO);
+
+        // Black's seventh is the second rank
+        assert_eq!(score("6k1/8/8/8/8/8/3r4/6K1 w - - 0 1", &params), -params.rook_seventh);
+    }
+
+    #[test]
+    fn bishops_trapped_behind_a_pawn() {
+        let mut params = params::Params::zeroed();
+        params.trapped_bishop = score::Score::new(-150, -150);
+
+        assert_eq!(score("6k1/B7/1p6/8/8/8/8/6K1 w - - 0 1", &params), params.trapped_bishop);
+        assert_eq!(score("6k1/B7/8/1p6/8/8/8/6K1 w - - 0 1", &params), score::Score::ZERO);
+        assert_eq!(score("6k1/7B/6p1/8/8/8/8/6K1 w - - 0 1", &params), params.trapped_bishop);
+        assert_eq!(score("6k1/8/8/8/8/1P6/b7/6K1 w - - 0 1", &params), -params.trapped_bishop);
+    }
+}
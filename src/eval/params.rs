@@ -0,0 +1,463 @@
+use std::{fmt, fs, path::Path};
+
+use crate::board::{colour, pieces, position};
+
+use super::{error, score::Score};
+
+// Names of the kinds in the order material and psqt hold them
+const KIND_NAMES: [&str; 6] = ["pawn", "knight", "bishop", "rook", "queen", "king"];
+
+// === Evaluation weights ===
+// Everything the evaluator multiplies by lives here, so a different set of
+// weights can be swapped in without touching the terms themselves.
+// Piece-square tables are written from white's side, a8 first, the way the
+// board reads. Black looks its squares up mirrored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Params {
+    // Indexed by pawn, knight, bishop, rook, queen, king
+    pub material: [Score; 6],
+    pub psqt: [[Score; 64]; 6],
+
+    // Pawn structure, per pawn
+    pub doubled: Score,
+    pub isolated: Score,
+    pub backward: Score,
+    // Defended or side by side, and passed, by relative rank
+    pub connected: [Score; 8],
+    pub passed: [Score; 8],
+    // Passed pawn extras, multiplied by how far up the board the pawn is
+    pub passed_free: Score,
+    pub passed_own_king: Score,
+    pub passed_enemy_king: Score,
+
+    // King safety, per file beside the king. Indexed by the ranks between
+    // the king and the nearest pawn on the file, 0 when there is none near
+    pub shield: [Score; 4],
+    pub storm: [Score; 4],
+    // An enemy pawn stopped by one of ours
+    pub storm_blocked: Score,
+    pub king_open_file: Score,
+    pub king_half_open_file: Score,
+    // Attack units for each king zone square a knight, bishop, rook or queen hits
+    pub attack_weights: [i32; 4],
+    // Percent of the danger table counted in the middlegame and endgame
+    pub king_danger: Score,
+
+    // Mobility by the number of safe squares a piece reaches
+    pub knight_mobility: [Score; 9],
+    pub bishop_mobility: [Score; 14],
+    pub rook_mobility: [Score; 15],
+    pub queen_mobility: [Score; 28],
+    // Piece activity
+    pub bishop_pair: Score,
+    pub rook_open_file: Score,
+    pub rook_semi_open_file: Score,
+    pub rook_seventh: Score,
+    pub knight_outpost: Score,
+    pub trapped_rook: Score,
+    pub trapped_bishop: Score,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Params {
+            material: [
+                Score::new(82, 94),
+                Score::new(337, 281),
+                Score::new(365, 297),
+                Score::new(477, 512),
+                Score::new(1025, 936),
+                Score::ZERO,
+            ],
+            psqt: [
+                table(&PAWN_MG, &PAWN_EG),
+                table(&KNIGHT_MG, &KNIGHT_EG),
+                table(&BISHOP_MG, &BISHOP_EG),
+                table(&ROOK_MG, &ROOK_EG),
+                table(&QUEEN_MG, &QUEEN_EG),
+                table(&KING_MG, &KING_EG),
+            ],
+
+            doubled: Score::new(-10, -25),
+            isolated: Score::new(-8, -15),
+            backward: Score::new(-8, -12),
+            connected: [
+                Score::ZERO,
+                Score::ZERO,
+                Score::new(6, 2),
+                Score::new(10, 6),
+                Score::new(16, 12),
+                Score::new(28, 24),
+                Score::new(45, 40),
+                Score::ZERO,
+            ],
+            passed: [
+                Score::ZERO,
+                Score::new(2, 5),
+                Score::new(4, 8),
+                Score::new(8, 15),
+                Score::new(15, 30),
+                Score::new(30, 60),
+                Score::new(50, 90),
+                Score::ZERO,
+            ],
+            passed_free: Score::new(0, 8),
+            passed_own_king: Score::new(0, -3),
+            passed_enemy_king: Score::new(0, 6),
+
+            shield: [Score::new(-24, 0), Score::new(16, 0), Score::new(8, 0), Score::new(2, 0)],
+            storm: [Score::ZERO, Score::new(-8, 0), Score::new(-24, 0), Score::new(-14, 0)],
+            storm_blocked: Score::new(-4, 0),
+            king_open_file: Score::new(-22, 0),
+            king_half_open_file: Score::new(-12, 0),
+            attack_weights: [2, 2, 3, 5],
+            king_danger: Score::new(100, 20),
+
+            knight_mobility: curve((-40, 30), (-50, 25)),
+            bishop_mobility: curve((-35, 60), (-50, 70)),
+            rook_mobility: curve((-25, 45), (-40, 90)),
+            queen_mobility: curve((-20, 60), (-40, 110)),
+            bishop_pair: Score::new(30, 50),
+            rook_open_file: Score::new(40, 10),
+            rook_semi_open_file: Score::new(18, 8),
+            rook_seventh: Score::new(10, 25),
+            knight_outpost: Score::new(25, 12),
+            trapped_rook: Score::new(-40, -10),
+            trapped_bishop: Score::new(-80, -60),
+        }
+    }
+}
+
+impl Params {
+    pub fn material(&self, kind: pieces::Kind) -> Score {
+        self.material[kind_index(kind)]
+    }
+
+    // Piece-square value for a piece of either colour
+    pub fn square(&self, piece: pieces::Piece, pos: position::Position) -> Score {
+        let square = match pieces::get_colour(&piece) {
+            colour::Colour::White(()) => pos as usize,
+            colour::Colour::Black(()) => pos as usize ^ 56,
+        };
+
+        self.psqt[kind_index(pieces::get_kind(&piece))][square]
+    }
+
+    // === Tunable weights ===
+    // Every Score weight in a fixed order. The attack weights are left out,
+    // they pick a row of the danger table rather than add to the score
+    pub fn values(&self) -> Vec<Score> {
+        self.clone().slots().into_iter().map(|(_, value)| *value).collect()
+    }
+
+    pub fn names() -> Vec<String> {
+        Params::default().slots().into_iter().map(|(name, _)| name).collect()
+    }
+
+    pub fn set_values(&mut self, values: &[Score]) {
+        for ((_, slot), value) in self.slots().into_iter().zip(values) {
+            *slot = *value;
+        }
+    }
+
+    fn slots(&mut self) -> Vec<(String, &mut Score)> {
+        let mut slots: Vec<(String, &mut Score)> = Vec::new();
+
+        for (name, value) in KIND_NAMES.iter().zip(self.material.iter_mut()) {
+            slots.push((format!("material.{}", name), value));
+        }
+        for (name, table) in KIND_NAMES.iter().zip(self.psqt.iter_mut()) {
+            for (pos, value) in position::Position::ALL.into_iter().zip(table.iter_mut()) {
+                slots.push((format!("psqt.{}.{}", name, position::to_string(pos)), value));
+            }
+        }
+
+        let singles: [(&str, &mut Score); 17] = [
+            ("doubled", &mut self.doubled),
+            ("isolated", &mut self.isolated),
+            ("backward", &mut self.backward),
+            ("passed_free", &mut self.passed_free),
+            ("passed_own_king", &mut self.passed_own_king),
+            ("passed_enemy_king", &mut self.passed_enemy_king),
+            ("storm_blocked", &mut self.storm_blocked),
+            ("king_open_file", &mut self.king_open_file),
+            ("king_half_open_file", &mut self.king_half_open_file),
+            ("king_danger", &mut self.king_danger),
+            ("bishop_pair", &mut self.bishop_pair),
+            ("rook_open_file", &mut self.rook_open_file),
+            ("rook_semi_open_file", &mut self.rook_semi_open_file),
+            ("rook_seventh", &mut self.rook_seventh),
+            ("knight_outpost", &mut self.knight_outpost),
+            ("trapped_rook", &mut self.trapped_rook),
+            ("trapped_bishop", &mut self.trapped_bishop),
+        ];
+        for (name, value) in singles {
+            slots.push((name.to_string(), value));
+        }
+
+        let arrays: [(&str, &mut [Score]); 8] = [
+            ("connected", &mut self.connected),
+            ("passed", &mut self.passed),
+            ("shield", &mut self.shield),
+            ("storm", &mut self.storm),
+            ("knight_mobility", &mut self.knight_mobility),
+            ("bishop_mobility", &mut self.bishop_mobility),
+            ("rook_mobility", &mut self.rook_mobility),
+            ("queen_mobility", &mut self.queen_mobility),
+        ];
+        for (name, values) in arrays {
+            for (idx, value) in values.iter_mut().enumerate() {
+                slots.push((format!("{}.{}", name, idx), value));
+            }
+        }
+
+        slots
+    }
+
+    // === Parameter files ===
+    // One weight a line as `name mg eg`, blank lines and # comments are skipped.
+    // Weights a file leaves out keep their default value.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, error::Error> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), error::Error> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    pub fn parse(text: &str) -> Result<Self, error::Error> {
+        let mut params = Params::default();
+
+        for (number, line) in text.lines().enumerate().map(|(idx, line)| (idx + 1, line)) {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut fields = line.split_whitespace();
+            let name = fields.next().unwrap_or("");
+            let numbers = fields
+                .map(|field| field.parse::<i32>())
+                .collect::<Result<Vec<i32>, _>>()
+                .map_err(|err| error::Error::Parameter(number, err.to_string()))?;
+
+            if name == "attack_weights" {
+                params.attack_weights = numbers
+                    .try_into()
+                    .map_err(|_| error::Error::Parameter(number, "expected four attack weights".to_string()))?;
+                continue;
+            }
+
+            let [mg, eg] = numbers[..] else {
+                return Err(error::Error::Parameter(number, format!("expected two values for {}", name)));
+            };
+            let mut slots = params.slots();
+            let (_, slot) = slots
+                .iter_mut()
+                .find(|(slot, _)| slot == name)
+                .ok_or_else(|| error::Error::Parameter(number, format!("unknown weight {}", name)))?;
+            **slot = Score::new(mg, eg);
+        }
+
+        Ok(params)
+    }
+}
+
+// === Write the weights out in the parameter file layout ===
+impl fmt::Display for Params {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# name mg eg")?;
+        for (name, value) in Params::names().iter().zip(self.values()) {
+            writeln!(f, "{} {} {}", name, value.mg, value.eg)?;
+        }
+
+        let [knight, bishop, rook, queen] = self.attack_weights;
+        writeln!(f, "attack_weights {} {} {} {}", knight, bishop, rook, queen)
+    }
+}
+
+pub(crate) fn kind_index(kind: pieces::Kind) -> usize {
+    match kind {
+        pieces::Kind::Pawn => 0,
+        pieces::Kind::Knight => 1,
+        pieces::Kind::Bishop => 2,
+        pieces::Kind::Rook => 3,
+        pieces::Kind::Queen => 4,
+        pieces::Kind::King => 5,
+    }
+}
+
+fn table(mg: &[i32; 64], eg: &[i32; 64]) -> [Score; 64] {
+    std::array::from_fn(|sq| Score::new(mg[sq], eg[sq]))
+}
+
+// Rises quickly over the first few squares and levels off, from (low, high) in each phase
+fn curve<const N: usize>(mg: (i32, i32), eg: (i32, i32)) -> [Score; N] {
+    std::array::from_fn(|count| {
+        let t = (count as f64 / (N - 1) as f64).sqrt();
+        let lerp = |(low, high): (i32, i32)| low + ((high - low) as f64 * t).round() as i32;
+        Score::new(lerp(mg), lerp(eg))
+    })
+}
+
+// === Piece-square tables ===
+const PAWN_MG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     60,  70,  70,  80,  80,  70,  70,  60,
+     15,  20,  30,  40,  40,  30,  20,  15,
+      5,  10,  15,  28,  28,  15,  10,   5,
+      0,   0,  10,  22,  22,  10,   0,   0,
+      5,  -2,  -5,   5,   5,  -5,  -2,   5,
+      5,  10,  10, -15, -15,  10,  10,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+const PAWN_EG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+    110, 110, 105, 100, 100, 105, 110, 110,
+     60,  60,  55,  50,  50,  55,  60,  60,
+     30,  28,  22,  18,  18,  22,  28,  30,
+     15,  12,   8,   5,   5,   8,  12,  15,
+      5,   5,   0,   0,   0,   0,   5,   5,
+      5,   5,   2,   0,   0,   2,   5,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+const KNIGHT_MG: [i32; 64] = [
+    -60, -35, -25, -20, -20, -25, -35, -60,
+    -35, -15,   0,   5,   5,   0, -15, -35,
+    -25,   5,  15,  20,  20,  15,   5, -25,
+    -20,   5,  20,  25,  25,  20,   5, -20,
+    -20,   0,  18,  22,  22,  18,   0, -20,
+    -25,   0,  12,  15,  15,  12,   0, -25,
+    -35, -15,   0,   5,   5,   0, -15, -35,
+    -60, -30, -25, -20, -20, -25, -30, -60,
+];
+
+const KNIGHT_EG: [i32; 64] = [
+    -50, -35, -25, -20, -20, -25, -35, -50,
+    -35, -20,  -5,   0,   0,  -5, -20, -35,
+    -25,  -5,  10,  15,  15,  10,  -5, -25,
+    -20,   0,  15,  20,  20,  15,   0, -20,
+    -20,   0,  15,  20,  20,  15,   0, -20,
+    -25,  -5,  10,  15,  15,  10,  -5, -25,
+    -35, -20,  -5,   0,   0,  -5, -20, -35,
+    -50, -35, -25, -20, -20, -25, -35, -50,
+];
+
+const BISHOP_MG: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+const BISHOP_EG: [i32; 64] = [
+    -15, -10,  -8,  -5,  -5,  -8, -10, -15,
+    -10,  -3,   0,   2,   2,   0,  -3, -10,
+     -8,   0,   5,   8,   8,   5,   0,  -8,
+     -5,   2,   8,  12,  12,   8,   2,  -5,
+     -5,   2,   8,  12,  12,   8,   2,  -5,
+     -8,   0,   5,   8,   8,   5,   0,  -8,
+    -10,  -3,   0,   2,   2,   0,  -3, -10,
+    -15, -10,  -8,  -5,  -5,  -8, -10, -15,
+];
+
+const ROOK_MG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+      5,  10,  10,  10,  10,  10,  10,   5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+      0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+const ROOK_EG: [i32; 64] = [
+      5,   5,   5,   5,   5,   5,   5,   5,
+     10,  12,  12,  12,  12,  12,  12,  10,
+      2,   2,   2,   2,   2,   2,   2,   2,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+     -2,   0,   0,   0,   0,   0,   0,  -2,
+     -4,  -2,  -2,  -2,  -2,  -2,  -2,  -4,
+     -5,  -3,   0,   0,   0,   0,  -3,  -5,
+];
+
+const QUEEN_MG: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+const QUEEN_EG: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -10,   5,  10,  12,  12,  10,   5, -10,
+     -5,   5,  12,  15,  15,  12,   5,  -5,
+     -5,   5,  12,  15,  15,  12,   5,  -5,
+    -10,   5,  10,  12,  12,  10,   5, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+const KING_MG: [i32; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+const KING_EG: [i32; 64] = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+#[cfg(test)]
+impl Params {
+    // Every Score weight zero, so a test can switch on only the term it looks at
+    pub(crate) fn zeroed() -> Self {
+        let mut params = Params::default();
+        params.set_values(&vec![Score::ZERO; params.values().len()]);
+        params
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parameter_files_round_trip() {
+        let mut params = Params::default();
+        let values: Vec<Score> = (0..params.values().len() as i32)
+            .map(|idx| Score::new(idx - 300, 7 * idx % 101))
+            .collect();
+        params.set_values(&values);
+        params.attack_weights = [3, 1, 4, 1];
+
+        assert_eq!(Params::parse(&params.to_string()).unwrap(), params);
+        assert_eq!(Params::parse(&Params::default().to_string()).unwrap(), Params::default());
+    }
+}
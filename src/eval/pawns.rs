@@ -0,0 +1,282 @@
+use crate::{
+    board::{bitboard, colour, pieces, position},
+    gamestate::{boardstate, occupancy_layer, zobrist},
+};
+
+use super::{params, score};
+
+// Entries in a table made with Default
+const DEFAULT_ENTRIES: usize = 1 << 14;
+
+// === Pawn structure ===
+// Terms that only depend on where the pawns are. Pawns move rarely,
+// so they are worked out once per structure and kept in a table
+// keyed by zobrist::pawn_hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PawnEntry {
+    pub key: u64,
+    // Structure score of white and black
+    pub score: [score::Score; 2],
+    // Passed pawns of white and black, for the terms that also need the kings
+    pub passed: [bitboard::Bitboard; 2],
+}
+
+// === Pawn hash table ===
+// Always replace, the newest structure is the one most likely to come up again
+#[derive(Debug, Clone)]
+pub struct PawnTable {
+    entries: Vec<Option<PawnEntry>>,
+    hits: u64,
+    misses: u64,
+}
+
+impl Default for PawnTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_ENTRIES)
+    }
+}
+
+impl PawnTable {
+    // Rounded up to a power of two so a key can be masked to an index
+    pub fn new(entries: usize) -> Self {
+        PawnTable {
+            entries: vec![None; entries.max(1).next_power_of_two()],
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn probe(&mut self, state: &boardstate::State, params: &params::Params) -> PawnEntry {
+        let key = zobrist::pawn_hash(state);
+        let slot = key as usize & (self.entries.len() - 1);
+
+        if let Some(entry) = self.entries[slot].filter(|entry| entry.key == key) {
+            self.hits += 1;
+            return entry;
+        }
+
+        self.misses += 1;
+        let entry = evaluate(state, params, key);
+        self.entries[slot] = Some(entry);
+        entry
+    }
+
+    // Weights changed, every stored score is stale
+    pub fn clear(&mut self) {
+        self.entries.iter_mut().for_each(|entry| *entry = None);
+        self.hits = 0;
+        self.misses = 0;
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+}
+
+// Structure terms for both sides
+pub fn evaluate(state: &boardstate::State, params: &params::Params, key: u64) -> PawnEntry {
+    let (white, white_passed) = side(state, params, colour::Colour::White(()));
+    let (black, black_passed) = side(state, params, colour::Colour::Black(()));
+
+    PawnEntry {
+        key,
+        score: [white, black],
+        passed: [white_passed, black_passed],
+    }
+}
+
+fn side(
+    state: &boardstate::State,
+    params: &params::Params,
+    us: colour::Colour<()>,
+) -> (score::Score, bitboard::Bitboard) {
+    let ours = state.material_layer[pieces::from_colour_kind(&us, pieces::Kind::Pawn)];
+    let theirs = state.material_layer[pieces::from_colour_kind(&us.opp(), pieces::Kind::Pawn)];
+    let our_attacks = attacks(ours, us);
+    let their_attacks = attacks(theirs, us.opp());
+
+    let mut total = score::Score::ZERO;
+    let mut passed = bitboard::Bitboard(0);
+
+    for pos in ours.iter() {
+        let pawn = bitboard::Bitboard(1 << pos as u64);
+        let rank = pos.relative_rank(us) as usize;
+        let neighbours = (pawn.east() | pawn.west()).file_fill() & ours;
+        let stop = push(pawn, us);
+
+        // Another of ours behind on the same file
+        if !(pawn.rear_span(us) & ours).is_empty() {
+            total += params.doubled;
+        }
+
+        let supported = !(pawn & our_attacks).is_empty();
+        let phalanx = !((pawn.east() | pawn.west()) & ours).is_empty();
+
+        if neighbours.is_empty() {
+            total += params.isolated;
+        } else if !supported && !phalanx {
+            // Nothing beside or behind can come up to defend it, and it cannot safely advance
+            let behind = (pawn | pawn.rear_span(us)).rank_fill();
+            if (neighbours & behind).is_empty() && !(stop & (theirs | their_attacks)).is_empty() {
+                total += params.backward;
+            }
+        }
+
+        if supported || phalanx {
+            total += params.connected[rank];
+        }
+
+        // Passed, and not stuck behind a pawn of our own
+        if (pawn.passed_span(us) & theirs).is_empty() && (pawn.front_span(us) & ours).is_empty() {
+            passed |= pawn;
+            total += params.passed[rank];
+        }
+    }
+
+    (total, passed)
+}
+
+// === Passed pawn extras ===
+// The parts of a passer's value that depend on the kings and on what is in its way,
+// so they cannot be cached with the structure
+pub fn passers(
+    state: &boardstate::State,
+    entry: &PawnEntry,
+    params: &params::Params,
+    us: colour::Colour<()>,
+) -> score::Score {
+    let occupied = occupancy_layer::get_both(&state.occupancy_layer);
+
+    entry.passed[us]
+        .iter()
+        .map(|pos| passer(state, params, us, pos, occupied))
+        .fold(score::Score::ZERO, |acc, value| acc + value)
+}
+
+fn passer(
+    state: &boardstate::State,
+    params: &params::Params,
+    us: colour::Colour<()>,
+    pos: position::Position,
+    occupied: bitboard::Bitboard,
+) -> score::Score {
+    // Only counts from the 4th rank up, more the further on
+    let weight = (pos.relative_rank(us) as i32 - 2).max(0);
+    if weight == 0 {
+        return score::Score::ZERO;
+    }
+    let Some(block) = pos.change_rank(match us {
+        colour::Colour::White(()) => 1,
+        colour::Colour::Black(()) => -1,
+    }) else {
+        return score::Score::ZERO;
+    };
+
+    let mut value = score::Score::ZERO;
+    let king = |side: colour::Colour<()>| {
+        state.material_layer[pieces::from_colour_kind(&side, pieces::Kind::King)].get_ls1b()
+    };
+    if let Some(own) = king(us) {
+        value += params.passed_own_king * (own.distance(block).min(5) as i32 * weight);
+    }
+    if let Some(enemy) = king(us.opp()) {
+        value += params.passed_enemy_king * (enemy.distance(block).min(5) as i32 * weight);
+    }
+
+    let path = bitboard::Bitboard(1 << pos as u64).front_span(us);
+    if (path & occupied).is_empty() {
+        value += params.passed_free * weight;
+    }
+
+    value
+}
+
+fn push(pawns: bitboard::Bitboard, side: colour::Colour<()>) -> bitboard::Bitboard {
+    match side {
+        colour::Colour::White(()) => pawns.north(),
+        colour::Colour::Black(()) => pawns.south(),
+    }
+}
+
+// Squares the pawns of a side attack
+pub(crate) fn attacks(pawns: bitboard::Bitboard, side: colour::Colour<()>) -> bitboard::Bitboard {
+    match side {
+        colour::Colour::White(()) => pawns.ne() | pawns.nw(),
+        colour::Colour::Black(()) => pawns.se() | pawns.sw(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: colour::Colour<()> = colour::Colour::White(());
+    const BLACK: colour::Colour<()> = colour::Colour::Black(());
+
+    fn structure(fen: &str, params: &params::Params) -> PawnEntry {
+        let state = boardstate::try_from_fen(fen).unwrap();
+        evaluate(&state, params, zobrist::pawn_hash(&state))
+    }
+
+    #[test]
+    fn isolated_and_doubled_pawns() {
+        let mut params = params::Params::zeroed();
+        params.isolated = score::Score::new(-5, -15);
+        params.doubled = score::Score::new(-11, -23);
+
+        // Alone on the a-file, then with another behind it
+        let entry = structure("4k3/8/8/8/8/8/P7/4K3 w - - 0 1", &params);
+        assert_eq!(entry.score, [params.isolated, score::Score::ZERO]);
+
+        let entry = structure("4k3/8/8/8/8/P7/P7/4K3 w - - 0 1", &params);
+        assert_eq!(entry.score[0], params.isolated * 2 + params.doubled);
+
+        // A neighbour on the b-file means neither is isolated
+        let entry = structure("4k3/8/8/8/8/8/PP6/4K3 w - - 0 1", &params);
+        assert_eq!(entry.score[0], score::Score::ZERO);
+    }
+
+    #[test]
+    fn passed_pawns_score_by_rank() {
+        let mut params = params::Params::zeroed();
+        params.passed = std::array::from_fn(|rank| score::Score::new(10 * rank as i32, rank as i32));
+
+        let entry = structure("4k3/8/8/4P3/8/8/8/4K3 w - - 0 1", &params);
+        assert_eq!(entry.score, [params.passed[4], score::Score::ZERO]);
+        assert_eq!(entry.passed[WHITE], bitboard::Bitboard(1 << position::Position::E5 as u64));
+
+        // Black's pawn on d6 is in front on the next file, and is itself stopped by e5
+        let entry = structure("4k3/8/3p4/4P3/8/8/8/4K3 w - - 0 1", &params);
+        assert_eq!(entry.score, [score::Score::ZERO, score::Score::ZERO]);
+        assert!(entry.passed[WHITE].is_empty() && entry.passed[BLACK].is_empty());
+
+        // Black's passer counts from its own side of the board
+        let entry = structure("4k3/8/8/8/8/3p4/8/4K3 w - - 0 1", &params);
+        assert_eq!(entry.score, [score::Score::ZERO, params.passed[5]]);
+    }
+
+    #[test]
+    fn the_table_hits_on_the_second_probe() {
+        let params = params::Params::default();
+        let mut table = PawnTable::new(16);
+        let state = boardstate::try_from_fen("4k3/pp6/8/8/8/8/PP6/4K3 w - - 0 1").unwrap();
+
+        let first = table.probe(&state, &params);
+        assert_eq!((table.hits(), table.misses()), (0, 1));
+        assert_eq!(table.probe(&state, &params), first);
+        assert_eq!((table.hits(), table.misses()), (1, 1));
+
+        // Moving a king leaves the pawn structure, and its entry, as they were
+        let moved = boardstate::try_from_fen("3k4/pp6/8/8/8/8/PP6/3K4 b - - 0 1").unwrap();
+        assert_eq!(table.probe(&moved, &params), first);
+        assert_eq!((table.hits(), table.misses()), (2, 1));
+
+        table.clear();
+        table.probe(&state, &params);
+        assert_eq!((table.hits(), table.misses()), (0, 1));
+    }
+}
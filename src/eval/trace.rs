@@ -0,0 +1,115 @@
+use std::fmt;
+
+use crate::gamestate::material_layer;
+
+use super::score;
+
+// === Evaluation trace ===
+// What went into an evaluation, term by term, for working out why a
+// position scores what it does
+#[derive(Debug, Clone)]
+pub struct Trace {
+    pub board: material_layer::MaterialLayer,
+    pub terms: Vec<Term>,
+    // Game phase out of MAX_PHASE, and the endgame scale out of 64
+    pub phase: i32,
+    pub scale: i32,
+    // Set when a specialised ending replaced the general terms
+    pub specialised: Option<i32>,
+    // Final value in centipawns from white's side
+    pub value: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Term {
+    pub name: &'static str,
+    pub white: score::Score,
+    pub black: score::Score,
+}
+
+impl Term {
+    pub fn net(&self) -> score::Score {
+        self.white - self.black
+    }
+}
+
+impl Trace {
+    // White minus black over every term, before tapering
+    pub fn total(&self) -> score::Score {
+        self.terms
+            .iter()
+            .fold(score::Score::ZERO, |acc, term| acc + term.net())
+    }
+}
+
+// === Display the trace as a table under the board ===
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rule = format!("{:-<15}+{:-<15}+{:-<15}+{:-<14}", "", "", "", "");
+
+        writeln!(f, "{}", self.board)?;
+        writeln!(f, "{:<14} | {:^13} | {:^13} | {:^13}", "Term", "White", "Black", "Total")?;
+        writeln!(f, "{:<14} | {:>6} {:>6} | {:>6} {:>6} | {:>6} {:>6}", "", "MG", "EG", "MG", "EG", "MG", "EG")?;
+        writeln!(f, "{}", rule)?;
+
+        for term in &self.terms {
+            writeln!(f, "{:<14} | {} | {} | {}", term.name, term.white, term.black, term.net())?;
+        }
+
+        let white = self.terms.iter().fold(score::Score::ZERO, |acc, term| acc + term.white);
+        let black = self.terms.iter().fold(score::Score::ZERO, |acc, term| acc + term.black);
+        writeln!(f, "{}", rule)?;
+        writeln!(f, "{:<14} | {} | {} | {}", "Total", white, black, self.total())?;
+        writeln!(f)?;
+
+        writeln!(f, "Phase: {} of {}", self.phase, score::MAX_PHASE)?;
+        writeln!(f, "Endgame scale: {} of 64", self.scale)?;
+        if let Some(value) = self.specialised {
+            writeln!(f, "Specialised ending: {}", pawns(value))?;
+        }
+        writeln!(f, "Evaluation: {} (white side)", pawns(self.value))
+    }
+}
+
+fn pawns(centipawns: i32) -> String {
+    format!("{:+.2}", centipawns as f64 / 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{board::colour, eval::evaluation, gamestate::boardstate};
+
+    #[test]
+    fn terms_taper_to_the_evaluation() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+            "8/5pk1/6p1/8/3R4/6P1/5PK1/2r5 b - - 0 1",
+            "8/8/4k3/8/2B5/8/3K4/6N1 w - - 0 1",
+        ];
+
+        for fen in fens {
+            let state = boardstate::try_from_fen(fen).unwrap();
+            let trace = evaluation::eval_trace(&state);
+            let value = match state.side_to_move {
+                colour::Colour::White(()) => evaluation::evaluate(&state),
+                colour::Colour::Black(()) => -evaluation::evaluate(&state),
+            };
+
+            assert_eq!(trace.value, value, "{}", fen);
+            if trace.specialised.is_none() {
+                assert_eq!(trace.total().taper(trace.phase, trace.scale), trace.value, "{}", fen);
+            }
+        }
+    }
+
+    #[test]
+    fn specialised_endings_replace_the_terms() {
+        let state = boardstate::try_from_fen("8/8/8/4k3/8/8/4P3/4K3 w - - 0 1").unwrap();
+        let trace = evaluation::eval_trace(&state);
+
+        assert_eq!(trace.specialised, Some(trace.value));
+        assert_eq!(trace.value, evaluation::evaluate(&state));
+    }
+}
@@ -0,0 +1,445 @@
+use std::{fs, path::Path};
+
+use crate::{
+    board::{colour, pieces, position},
+    gamestate::boardstate,
+    traits::static_lookup as PRECOMP,
+};
+
+use super::{endgame, error, evaluation, params, pawns, score};
+
+// Weights before these are material and piece-square values, read straight off the board
+const TABLE_WEIGHTS: usize = 6 + 6 * 64;
+
+// Value given to a single weight when reading its coefficient off the evaluation.
+// Large so the king danger percentage survives the integer division
+const PROBE: i32 = 1000;
+
+// === Labelled positions ===
+// A game result from white's side, 1 for a win, 0.5 for a draw and 0 for a loss.
+// Takes 1-0 style results and numbers. A bare number needs its decimal point,
+// otherwise it could be the move number ending an unlabelled FEN
+pub fn parse_result(token: &str) -> Option<f64> {
+    let token = token.trim_end_matches(';');
+    let inner = token.trim_matches(|c: char| matches!(c, '"' | '[' | ']' | '(' | ')'));
+    let quoted = inner.len() < token.len();
+
+    match inner {
+        "1-0" => Some(1.0),
+        "0-1" => Some(0.0),
+        "1/2-1/2" | "1/2" | "=" => Some(0.5),
+        number if quoted || number.contains('.') => {
+            number.parse::<f64>().ok().filter(|result| (0.0..=1.0).contains(result))
+        }
+        _ => None,
+    }
+}
+
+// A FEN followed by its result, such as `<fen> [0.5]`, `<fen> 1-0` or `<fen> c9 "0-1";`
+pub fn parse_line(line: &str) -> Result<(boardstate::State, f64), String> {
+    let line = line.trim().trim_end_matches(';').trim_end();
+    let (fen, token) = line
+        .rsplit_once(char::is_whitespace)
+        .ok_or_else(|| "expected a FEN and a result".to_string())?;
+
+    let result = parse_result(token).ok_or_else(|| format!("unknown result {}", token))?;
+    let fen = fen.trim_end();
+    let fen = fen.strip_suffix("c9").unwrap_or(fen);
+    let state = boardstate::try_from_fen(fen.trim()).map_err(|err| err.to_string())?;
+
+    Ok((state, result))
+}
+
+// === Features ===
+// The evaluation of a position as a sum over the weights, each weight times
+// how many more times white earns it than black. Phase and scale are fixed
+// when the position is read.
+#[derive(Debug, Clone)]
+pub struct Sample {
+    // Weight index and coefficient, zero coefficients left out
+    pub features: Vec<(usize, f64)>,
+    // Share of the middlegame value, and the endgame scale, both 0 to 1
+    pub phase: f64,
+    pub scale: f64,
+    pub result: f64,
+}
+
+impl Sample {
+    // Evaluation in centipawns from white's side
+    pub fn evaluate(&self, weights: &[[f64; 2]]) -> f64 {
+        let (mg, eg) = self
+            .features
+            .iter()
+            .fold((0.0, 0.0), |(mg, eg), (idx, coefficient)| {
+                (mg + weights[*idx][0] * coefficient, eg + weights[*idx][1] * coefficient)
+            });
+
+        mg * self.phase + eg * self.scale * (1.0 - self.phase)
+    }
+}
+
+// === Optimisers ===
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Optimiser {
+    GradientDescent { rate: f64 },
+    Adam { rate: f64, beta1: f64, beta2: f64 },
+}
+
+impl Default for Optimiser {
+    fn default() -> Self {
+        Optimiser::Adam {
+            rate: 1.0,
+            beta1: 0.9,
+            beta2: 0.999,
+        }
+    }
+}
+
+// === Texel tuning ===
+// Fits the weights so the logistic of the evaluation predicts game results.
+// The loss is the mean squared difference between result and prediction,
+// with the logistic's scaling constant fitted to the starting weights first.
+pub struct Tuner<A> {
+    lookup: A,
+    params: params::Params,
+    // The starting weights with every Score zeroed but one, for the weights
+    // that are not read straight off the board
+    probes: Vec<(usize, params::Params)>,
+    samples: Vec<Sample>,
+    optimiser: Optimiser,
+    epochs: usize,
+    k: f64,
+}
+
+impl<A> Tuner<A>
+where
+    A: PRECOMP::StaticAttack,
+{
+    pub fn new(lookup: A, params: params::Params) -> Self {
+        let values = params.values();
+        let probes = (TABLE_WEIGHTS..values.len())
+            .map(|idx| {
+                let mut one_hot = vec![score::Score::ZERO; values.len()];
+                one_hot[idx] = score::Score::new(PROBE, 0);
+
+                let mut probe = params.clone();
+                probe.set_values(&one_hot);
+                (idx, probe)
+            })
+            .collect();
+
+        Tuner {
+            lookup,
+            params,
+            probes,
+            samples: Vec::new(),
+            optimiser: Optimiser::default(),
+            epochs: 1000,
+            k: 1.0,
+        }
+    }
+
+    pub fn with_optimiser(mut self, optimiser: Optimiser) -> Self {
+        self.optimiser = optimiser;
+        self
+    }
+
+    pub fn with_epochs(mut self, epochs: usize) -> Self {
+        self.epochs = epochs;
+        self
+    }
+
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    pub fn k(&self) -> f64 {
+        self.k
+    }
+
+    // Reads one labelled position per line, returns how many were kept
+    pub fn load_positions(&mut self, path: impl AsRef<Path>) -> Result<usize, error::Error> {
+        let text = fs::read_to_string(path)?;
+        let before = self.samples.len();
+
+        for (number, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+
+            let (state, result) = parse_line(line).map_err(|reason| error::Error::Position(number + 1, reason))?;
+            self.add_position(&state, result);
+        }
+
+        Ok(self.samples.len() - before)
+    }
+
+    // Positions decided by a specialised ending do not depend on the weights and are skipped
+    pub fn add_position(&mut self, state: &boardstate::State, result: f64) -> bool {
+        match self.extract(state, result) {
+            Some(sample) => {
+                self.samples.push(sample);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn extract(&self, state: &boardstate::State, result: f64) -> Option<Sample> {
+        if endgame::evaluate(state, &self.params).is_some() {
+            return None;
+        }
+
+        let mut coefficients = vec![0.0; self.params.values().len()];
+
+        // Material and piece-square tables count the pieces on the board
+        for (piece, pos) in state
+            .mailbox_layer
+            .0
+            .iter()
+            .zip(position::Position::ALL)
+            .filter_map(|(piece, pos)| piece.map(|p| (p, pos)))
+        {
+            let kind = params::kind_index(pieces::get_kind(&piece));
+            let (sign, square) = match pieces::get_colour(&piece) {
+                colour::Colour::White(()) => (1.0, pos as usize),
+                colour::Colour::Black(()) => (-1.0, pos as usize ^ 56),
+            };
+
+            coefficients[kind] += sign;
+            coefficients[6 + kind * 64 + square] += sign;
+        }
+
+        // Every other term is linear in its weight, so evaluating with only that
+        // weight set gives its coefficient
+        for (idx, probe) in &self.probes {
+            let entry = pawns::evaluate(state, probe, 0);
+            let score = evaluation::total(&evaluation::breakdown(state, probe, &self.lookup, &entry));
+            coefficients[*idx] = score.mg as f64 / PROBE as f64;
+        }
+
+        let entry = pawns::evaluate(state, &self.params, 0);
+        let score = evaluation::total(&evaluation::breakdown(state, &self.params, &self.lookup, &entry));
+        let phase = evaluation::phase(state) as f64 / score::MAX_PHASE as f64;
+        let scale = endgame::scale_factor(state, score.eg) as f64 / endgame::SCALE_NORMAL as f64;
+
+        Some(Sample {
+            features: coefficients
+                .into_iter()
+                .enumerate()
+                .filter(|(_, coefficient)| *coefficient != 0.0)
+                .collect(),
+            phase,
+            scale,
+            result,
+        })
+    }
+
+    // Mean squared difference between results and predicted results
+    pub fn error(&self, weights: &[[f64; 2]], k: f64) -> f64 {
+        let total: f64 = self
+            .samples
+            .iter()
+            .map(|sample| (sample.result - sigmoid(sample.evaluate(weights), k)).powi(2))
+            .sum();
+
+        total / self.samples.len().max(1) as f64
+    }
+
+    // Finds the scaling constant that best turns the starting evaluation into results
+    pub fn fit_k(&mut self) -> Result<f64, error::Error> {
+        if self.samples.is_empty() {
+            return Err(error::Error::NoPositions);
+        }
+
+        let weights = weights(&self.params);
+        let (mut low, mut high, mut step) = (0.0, 10.0, 1.0);
+        let mut best = self.k;
+
+        // Scan, then scan again a tenth as finely around the best so far
+        for _ in 0..6 {
+            let mut best_error = f64::MAX;
+            let mut k = low;
+            while k <= high {
+                let error = self.error(&weights, k);
+                if error < best_error {
+                    best_error = error;
+                    best = k;
+                }
+                k += step;
+            }
+
+            low = (best - step).max(0.0);
+            high = best + step;
+            step /= 10.0;
+        }
+
+        self.k = best;
+        Ok(best)
+    }
+
+    // Runs the optimiser over every sample each epoch, reporting the epoch and error as it goes
+    pub fn tune(&mut self, mut report: impl FnMut(usize, f64)) -> Result<params::Params, error::Error> {
+        self.fit_k()?;
+
+        let mut weights = weights(&self.params);
+        let mut momentum = vec![[0.0; 2]; weights.len()];
+        let mut velocity = vec![[0.0; 2]; weights.len()];
+
+        for epoch in 1..=self.epochs {
+            let gradient = self.gradient(&weights);
+
+            for (idx, grad) in gradient.iter().enumerate() {
+                for phase in 0..2 {
+                    weights[idx][phase] -= match self.optimiser {
+                        Optimiser::GradientDescent { rate } => rate * grad[phase],
+                        Optimiser::Adam { rate, beta1, beta2 } => {
+                            momentum[idx][phase] = beta1 * momentum[idx][phase] + (1.0 - beta1) * grad[phase];
+                            velocity[idx][phase] =
+                                beta2 * velocity[idx][phase] + (1.0 - beta2) * grad[phase].powi(2);
+
+                            let m = momentum[idx][phase] / (1.0 - beta1.powi(epoch as i32));
+                            let v = velocity[idx][phase] / (1.0 - beta2.powi(epoch as i32));
+                            rate * m / (v.sqrt() + 1e-8)
+                        }
+                    };
+                }
+            }
+
+            report(epoch, self.error(&weights, self.k));
+        }
+
+        let mut tuned = self.params.clone();
+        tuned.set_values(
+            &weights
+                .iter()
+                .map(|[mg, eg]| score::Score::new(mg.round() as i32, eg.round() as i32))
+                .collect::<Vec<_>>(),
+        );
+
+        Ok(tuned)
+    }
+
+    // Derivative of the error for each weight's middlegame and endgame value
+    fn gradient(&self, weights: &[[f64; 2]]) -> Vec<[f64; 2]> {
+        let mut gradient = vec![[0.0; 2]; weights.len()];
+        let ln10 = std::f64::consts::LN_10;
+
+        for sample in &self.samples {
+            let predicted = sigmoid(sample.evaluate(weights), self.k);
+            let slope = -2.0 * (sample.result - predicted) * predicted * (1.0 - predicted) * self.k * ln10 / 400.0;
+
+            let mg = slope * sample.phase;
+            let eg = slope * sample.scale * (1.0 - sample.phase);
+            for (idx, coefficient) in &sample.features {
+                gradient[*idx][0] += mg * coefficient;
+                gradient[*idx][1] += eg * coefficient;
+            }
+        }
+
+        let count = self.samples.len().max(1) as f64;
+        gradient
+            .into_iter()
+            .map(|[mg, eg]| [mg / count, eg / count])
+            .collect()
+    }
+}
+
+fn weights(params: &params::Params) -> Vec<[f64; 2]> {
+    params
+        .values()
+        .into_iter()
+        .map(|value| [value.mg as f64, value.eg as f64])
+        .collect()
+}
+
+// Expected result for white from an evaluation in centipawns
+fn sigmoid(eval: f64, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * eval / 400.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{consts, effects::static_attack_provider::StaticAttackProvider};
+
+    const POSITIONS: [&str; 5] = [
+        consts::START_POSITION,
+        consts::TRICKY_POSITION,
+        consts::CMK_POSITION,
+        "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N2N2/PP2BPPP/R2QKB1R b KQ - 3 8",
+        "8/5pk1/6p1/3P4/1p3P2/6P1/5K2/8 w - - 0 40",
+    ];
+
+    #[test]
+    fn results_are_read_when_unambiguous() {
+        let fen = "8/5pk1/6p1/3P4/1p3P2/6P1/5K2/8 w - - 0 40";
+
+        for (label, expected) in [("1-0", 1.0), ("[0.5]", 0.5), ("1.0", 1.0), ("c9 \"0-1\";", 0.0), ("\"1\"", 1.0)] {
+            let (_, result) = parse_line(&format!("{} {}", fen, label)).unwrap();
+            assert_eq!(result, expected, "{}", label);
+        }
+
+        // Without a label the full move number is not a result
+        assert!(matches!(parse_line(fen), Err(reason) if reason.contains("unknown result")));
+        assert!(parse_line(consts::START_POSITION).is_err());
+    }
+
+    fn tuner() -> Tuner<StaticAttackProvider> {
+        let mut tuner = Tuner::new(StaticAttackProvider, params::Params::default());
+        for (fen, result) in POSITIONS.iter().zip([0.5, 1.0, 0.0, 0.5, 1.0]) {
+            assert!(tuner.add_position(&boardstate::try_from_fen(fen).unwrap(), result), "{}", fen);
+        }
+        tuner
+    }
+
+    #[test]
+    fn features_rebuild_the_evaluation() {
+        let tuner = tuner();
+        let weights = weights(&tuner.params);
+        let mut evaluator = evaluation::Evaluator::new(StaticAttackProvider);
+
+        for (fen, sample) in POSITIONS.iter().zip(tuner.samples()) {
+            let state = boardstate::try_from_fen(fen).unwrap();
+            let expected = match state.side_to_move {
+                colour::Colour::White(()) => evaluator.evaluate(&state),
+                colour::Colour::Black(()) => -evaluator.evaluate(&state),
+            };
+
+            // The evaluator rounds its phase and scale steps to whole centipawns
+            let value = sample.evaluate(&weights);
+            assert!((value - expected as f64).abs() <= 2.0, "{}: {} against {}", fen, value, expected);
+        }
+    }
+
+    #[test]
+    fn gradient_matches_finite_differences() {
+        let tuner = tuner();
+        let weights = weights(&tuner.params);
+        let gradient = tuner.gradient(&weights);
+        let step = 0.01;
+
+        for sample in tuner.samples() {
+            for (idx, _) in &sample.features {
+                for phase in 0..2 {
+                    let nudged = |by: f64| {
+                        let mut weights = weights.clone();
+                        weights[*idx][phase] += by;
+                        tuner.error(&weights, tuner.k())
+                    };
+                    let estimate = (nudged(step) - nudged(-step)) / (2.0 * step);
+
+                    let tolerance = 1e-9 + 1e-4 * estimate.abs();
+                    assert!(
+                        (gradient[*idx][phase] - estimate).abs() <= tolerance,
+                        "weight {} phase {}: {} against {}",
+                        idx,
+                        phase,
+                        gradient[*idx][phase],
+                        estimate
+                    );
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use super::{material_layer, occupancy_layer};
+use super::{
+    error as state_error, mailbox_layer, material_layer, occupancy_layer, pocket_layer, variant,
+    zobrist,
+};
 use crate::{
     board::{bitboard, castling, colour, pieces, position},
     effects::static_attack_provider as STATIC_ATTK_LOOKUP,
@@ -9,15 +12,26 @@ use std::fmt;
 use strum::IntoEnumIterator;
 
 // === The full chessboard with meta data ===
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct State {
     pub material_layer: material_layer::MaterialLayer,
     pub occupancy_layer: occupancy_layer::OccupancyLayer,
+    pub mailbox_layer: mailbox_layer::MailboxLayer,
     pub side_to_move: colour::Colour<()>,
     pub en_passant: Option<position::Position>,
     pub castling: castling::CastlingRights,
+    pub castling_rooks: castling::CastlingRooks,
     pub half_moves: u32,
     pub full_moves: u32,
+    // Zobrist key, kept in step with the board by make/unmake
+    pub key: u64,
+    pub variant: variant::Variant,
+    // Checks given by each side, only counted at Three-check
+    pub checks: [u8; 2],
+    // Crazyhouse pieces in hand, and the squares holding promoted pieces
+    // which go back to the pocket as pawns when captured
+    pub pockets: pocket_layer::PocketLayer,
+    pub promoted: bitboard::Bitboard,
 }
 
 // === Display the full chessboard information ===
@@ -47,6 +61,15 @@ impl fmt::Display for State {
         } else {
             write!(f, "None")?;
         }
+        if self.variant != variant::Variant::Standard {
+            write!(f, "\nVariant: {}", self.variant)?;
+        }
+        if self.variant == variant::Variant::ThreeCheck {
+            write!(f, "\nChecks given: {}+{}", self.checks[0], self.checks[1])?;
+        }
+        if self.variant == variant::Variant::Crazyhouse {
+            write!(f, "\nPockets: [{}]", self.pockets)?;
+        }
         writeln!(f, "\nHalf move count: {}", self.half_moves)?;
         writeln!(f, "Full move count: {}", self.full_moves)?;
 
@@ -60,26 +83,43 @@ impl Default for State {
         Self {
             material_layer: material_layer::MaterialLayer([bitboard::Bitboard::new(); 12]),
             occupancy_layer: occupancy_layer::OccupancyLayer([bitboard::Bitboard::new(); 2]),
+            mailbox_layer: mailbox_layer::MailboxLayer::empty(),
             side_to_move: colour::Colour::White(()),
             en_passant: None,
             castling: castling::CastlingRights::new(),
+            castling_rooks: castling::CastlingRooks::new(),
             half_moves: 0,
             full_moves: 0,
+            key: 0,
+            variant: variant::Variant::Standard,
+            checks: [0; 2],
+            pockets: pocket_layer::PocketLayer::new(),
+            promoted: bitboard::Bitboard::new(),
         }
     }
 }
 
 impl State {
     pub fn new() -> Self {
-        Self {
+        let mut state = Self {
             material_layer: material_layer::MaterialLayer::new(),
             occupancy_layer: occupancy_layer::OccupancyLayer::new(),
+            mailbox_layer: mailbox_layer::MailboxLayer::new(),
             side_to_move: colour::Colour::White(()),
             en_passant: None,
-            castling: castling::CastlingRights::new(),
+            castling: castling::CastlingRights(0b1111),
+            castling_rooks: castling::CastlingRooks::new(),
             half_moves: 0,
-            full_moves: 0,
-        }
+            full_moves: 1,
+            key: 0,
+            variant: variant::Variant::Standard,
+            checks: [0; 2],
+            pockets: pocket_layer::PocketLayer::new(),
+            promoted: bitboard::Bitboard::new(),
+        };
+        state.key = zobrist::hash(&state);
+
+        state
     }
 
     pub fn generate_occ(&mut self) {
@@ -115,32 +155,176 @@ impl State {
     }
 }
 
+// === In place updates ===
+impl State {
+    // Flip a piece on or off a square, keeping every layer in sync
+    pub fn toggle_piece(&mut self, piece: pieces::Piece, pos: position::Position) {
+        let bit = 1u64 << pos as u64;
+        let index = piece.index();
+
+        // Material layer is ordered white pieces then black pieces
+        self.material_layer.0[index] ^= bit;
+        self.occupancy_layer.0[index / 6] ^= bit;
+        self.mailbox_layer[pos] = if self.material_layer.0[index].0 & bit != 0 {
+            Some(piece)
+        } else {
+            None
+        };
+        self.key ^= zobrist::piece_key(piece, pos);
+    }
+}
+
+// === Validation ===
+impl State {
+    // Checks the position could arise in a legal game, returning the first problem found
+    pub fn validate(&self) -> Result<(), state_error::Error> {
+        let lookup = STATIC_ATTK_LOOKUP::StaticAttackProvider;
+
+        for side in [colour::Colour::White(()), colour::Colour::Black(())] {
+            // The horde is White's pawns and pieces without a king
+            let is_horde =
+                self.variant == variant::Variant::Horde && side == colour::Colour::White(());
+
+            // Antichess kings are ordinary pieces, any number of them is fine
+            let kings = self.material_layer[pieces::from_colour_kind(&side, pieces::Kind::King)]
+                .popcount();
+            let expected = if is_horde { 0 } else { 1 };
+            if kings != expected && self.variant != variant::Variant::Antichess {
+                return Err(state_error::Error::KingCount { side, count: kings });
+            }
+
+            // Horde pawns start on the first rank
+            let pawns = self.material_layer[pieces::from_colour_kind(&side, pieces::Kind::Pawn)];
+            if let Some(pos) = pawns.iter().find(|pos| {
+                pos.relative_rank(side) == position::Rank::R8
+                    || (pos.relative_rank(side) == position::Rank::R1 && !is_horde)
+            }) {
+                return Err(state_error::Error::PawnOnBackRank(pos));
+            }
+
+            // Captured pieces change sides in Crazyhouse, so only the total is bounded
+            if self.variant == variant::Variant::Crazyhouse || is_horde {
+                continue;
+            }
+
+            let count = self.occupancy_layer[side].popcount();
+            if count > 16 {
+                return Err(state_error::Error::TooManyPieces { side, count });
+            }
+            if pawns.popcount() > 8 {
+                return Err(state_error::Error::TooManyPawns {
+                    side,
+                    count: pawns.popcount(),
+                });
+            }
+        }
+
+        if is_king_attacked(self, self.side_to_move.opp(), lookup) {
+            return Err(state_error::Error::OpponentInCheck);
+        }
+
+        if let Some(right) = castling::castling_rights_from_bits(self.castling)
+            .find(|right| !self.is_valid_castling_right(*right))
+        {
+            return Err(state_error::Error::CastlingRights(right));
+        }
+
+        if let Some(target) = self.en_passant {
+            if !self.is_valid_en_passant(target) {
+                return Err(state_error::Error::EnPassant(target));
+            }
+        }
+
+        Ok(())
+    }
+
+    // The king stands on its back rank with the right's rook on the correct side of it
+    fn is_valid_castling_right(&self, right: castling::Castling) -> bool {
+        let (Some(side), Some(rook), Some(kingside)) = (
+            right.side(),
+            self.castling_rooks.rook_square(right),
+            right.is_kingside(),
+        ) else {
+            return true;
+        };
+
+        let Some(king) = king_position(self, side) else {
+            return false;
+        };
+
+        king.rank() == rook.rank()
+            && (rook.file() > king.file()) == kingside
+            && self.mailbox_layer[rook] == Some(pieces::from_colour_kind(&side, pieces::Kind::Rook))
+    }
+
+    // The opponent just pushed a pawn two squares past the target:
+    // the target and the pawn's start square are empty and the pawn stands beyond it
+    fn is_valid_en_passant(&self, target: position::Position) -> bool {
+        let mover = self.side_to_move;
+        let pushed = pieces::from_colour_kind(&mover.opp(), pieces::Kind::Pawn);
+
+        let on_rank = target.rank() == position::Rank::R6.relative(mover);
+        let pawn = position::Position::new(target.file(), position::Rank::R5.relative(mover));
+        let start = position::Position::new(target.file(), position::Rank::R7.relative(mover));
+
+        on_rank
+            && self.mailbox_layer[target].is_none()
+            && self.mailbox_layer[start].is_none()
+            && self.mailbox_layer[pawn] == Some(pushed)
+    }
+}
+
 // === Chess notation parsers ===
 pub fn try_from_fen(fen: &str) -> Result<State, Error> {
     crate::parsers::fen::parse(fen)
 }
 
+// Parses under the given variant's rules, reading any variant specific fields
+pub fn try_from_fen_variant(fen: &str, rules: variant::Variant) -> Result<State, Error> {
+    crate::parsers::fen::parse_variant(fen, rules)
+}
+
+// Parses then validates, rejecting positions that cannot arise in a legal game
+pub fn try_from_fen_strict(fen: &str) -> Result<State, Error> {
+    crate::parsers::fen::parse_strict(fen, variant::Variant::Standard)
+}
+
+// As above, under the given variant's rules
+pub fn try_from_fen_variant_strict(fen: &str, rules: variant::Variant) -> Result<State, Error> {
+    crate::parsers::fen::parse_strict(fen, rules)
+}
+
 // === Chess notation serializers ===
 pub fn to_fen(state: State) -> Result<String, crate::parsers::error::Error> {
     crate::parsers::fen::serialize(state)
 }
 
 // === Attacks ===
+// Is pos attacked by the side not to move
 pub fn is_attacked<A: PRECOMP::StaticAttack>(
     board: &State,
     pos: position::Position,
     sttk_attk: A,
+) -> bool {
+    is_attacked_by(board, pos, board.side_to_move.opp(), sttk_attk)
+}
+
+pub fn is_attacked_by<A: PRECOMP::StaticAttack>(
+    board: &State,
+    pos: position::Position,
+    attacker: colour::Colour<()>,
+    sttk_attk: A,
 ) -> bool {
     let occ = occupancy_layer::get_both(&board.occupancy_layer);
-    let attacker = board.side_to_move.opp();
 
     [
+        // A pawn attacks pos from the squares a defending pawn on pos would attack
         board.material_layer[pieces::from_colour_kind(&attacker, pieces::Kind::Pawn)]
-            & sttk_attk.pawn(pos, attacker),
+            & sttk_attk.pawn(pos, attacker.opp()),
         board.material_layer[pieces::from_colour_kind(&attacker, pieces::Kind::Knight)]
             & sttk_attk.knight(pos),
         board.material_layer[pieces::from_colour_kind(&attacker, pieces::Kind::King)]
-            & sttk_attk.knight(pos), // ← intentional reuse? Maybe clarify if king table is separate
+            & sttk_attk.king(pos),
         board.material_layer[pieces::from_colour_kind(&attacker, pieces::Kind::Bishop)]
             & sttk_attk.bishop(pos, occ),
         board.material_layer[pieces::from_colour_kind(&attacker, pieces::Kind::Rook)]
@@ -152,6 +336,41 @@ pub fn is_attacked<A: PRECOMP::StaticAttack>(
     .any(|bb| bb.0 != 0)
 }
 
+pub fn king_position(board: &State, side: colour::Colour<()>) -> Option<position::Position> {
+    board.material_layer[pieces::from_colour_kind(&side, pieces::Kind::King)].get_ls1b()
+}
+
+// Is the given side's king attacked
+pub fn is_king_attacked<A: PRECOMP::StaticAttack>(
+    board: &State,
+    side: colour::Colour<()>,
+    sttk_attk: A,
+) -> bool {
+    // Antichess kings can be taken like any other piece, there is no check
+    if board.variant == variant::Variant::Antichess {
+        return false;
+    }
+
+    let Some(king) = king_position(board, side) else {
+        return false;
+    };
+
+    // Taking an Atomic king next to its own would blow up both, so that is never check
+    if board.variant == variant::Variant::Atomic
+        && king_position(board, side.opp())
+            .is_some_and(|enemy| sttk_attk.king(king).is_occupied(enemy))
+    {
+        return false;
+    }
+
+    is_attacked_by(board, king, side.opp(), sttk_attk)
+}
+
+// Is the side to move in check
+pub fn in_check<A: PRECOMP::StaticAttack>(board: &State, sttk_attk: A) -> bool {
+    is_king_attacked(board, board.side_to_move, sttk_attk)
+}
+
 pub fn current_attacks(board: &State) -> bitboard::Bitboard {
     // Marker for static lookup
     let atk_provider = STATIC_ATTK_LOOKUP::StaticAttackProvider;
@@ -167,11 +386,147 @@ pub fn current_attacks(board: &State) -> bitboard::Bitboard {
 }
 
 pub fn get_piece_at_pos(board: &State, pos: position::Position) -> Option<pieces::Piece> {
-    for (i, bb) in board.material_layer.0.iter().enumerate() {
-        if bb.is_occupied(pos) {
-            return pieces::Piece::try_from(i).ok();
+    board.mailbox_layer[pos]
+}
+
+// === Debug consistency checks ===
+// Verifies the mailbox, material and occupancy layers all describe the same board.
+// Called through debug_assert! so release builds skip the work.
+pub fn check_layers(board: &State) -> Result<(), String> {
+    // Every material bitboard must be disjoint
+    let mut seen = bitboard::Bitboard::new();
+    for (i, bb) in board.material_layer.iter().enumerate() {
+        if seen.intersects(*bb) {
+            return Err(format!(
+                "Material bitboard {} overlaps another piece at {:?}",
+                i,
+                (seen & *bb).iter().collect::<Vec<_>>()
+            ));
         }
+        seen |= *bb;
     }
 
-    None
+    // The mailbox must agree with the material layer square by square
+    for pos in position::Position::iter() {
+        let from_material = board
+            .material_layer
+            .iter()
+            .position(|bb| bb.is_occupied(pos))
+            .and_then(|i| pieces::Piece::try_from(i).ok());
+
+        if from_material != board.mailbox_layer[pos] {
+            return Err(format!(
+                "Mailbox has {:?} at {} but the material layer has {:?}",
+                board.mailbox_layer[pos], pos, from_material
+            ));
+        }
+    }
+
+    // The occupancy layer must be the union of each side's material
+    let expected = occupancy_layer::generate_occ(board.material_layer);
+    for side in [colour::Colour::White(()), colour::Colour::Black(())] {
+        if expected[side] != board.occupancy_layer[side] {
+            return Err(format!(
+                "Occupancy for {:?} is {:#018X} but the material layer gives {:#018X}",
+                side, board.occupancy_layer[side].0, expected[side].0
+            ));
+        }
+    }
+
+    // The incrementally updated key must match a full rehash
+    let expected = zobrist::hash(board);
+    if expected != board.key {
+        return Err(format!(
+            "Zobrist key is {:#018X} but hashing the board gives {:#018X}",
+            board.key, expected
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: colour::Colour<()> = colour::Colour::White(());
+    const BLACK: colour::Colour<()> = colour::Colour::Black(());
+
+    fn invalid(fen: &str) -> Option<state_error::Error> {
+        try_from_fen(fen).unwrap().validate().err()
+    }
+
+    #[test]
+    fn legal_positions_pass() {
+        assert_eq!(invalid("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), None);
+        assert_eq!(invalid("4k3/8/8/4pP2/8/8/8/4K3 w - e6 0 1"), None);
+        // A Chess960 right with the king off the e-file
+        assert_eq!(invalid("4k3/8/8/8/8/8/8/3K3R w K - 0 1"), None);
+    }
+
+    #[test]
+    fn king_count() {
+        assert_eq!(
+            invalid("4k3/8/8/8/8/8/8/2K1K1K1 w - - 0 1"),
+            Some(state_error::Error::KingCount { side: WHITE, count: 3 })
+        );
+        assert_eq!(
+            invalid("8/8/8/8/8/8/8/4K3 w - - 0 1"),
+            Some(state_error::Error::KingCount { side: BLACK, count: 0 })
+        );
+    }
+
+    #[test]
+    fn pawn_on_back_rank() {
+        assert_eq!(
+            invalid("4k3/8/8/8/8/8/8/P3K3 w - - 0 1"),
+            Some(state_error::Error::PawnOnBackRank(position::Position::A1))
+        );
+    }
+
+    #[test]
+    fn too_many_pieces() {
+        assert_eq!(
+            invalid("rnbqkbnr/pppppppp/8/8/4N3/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            Some(state_error::Error::TooManyPieces { side: WHITE, count: 17 })
+        );
+    }
+
+    #[test]
+    fn too_many_pawns() {
+        assert_eq!(
+            invalid("rnbqkbnr/pppppppp/8/8/4P3/8/PPPPPPPP/R1BQKBNR w KQkq - 0 1"),
+            Some(state_error::Error::TooManyPawns { side: WHITE, count: 9 })
+        );
+    }
+
+    #[test]
+    fn opponent_in_check() {
+        assert_eq!(invalid("4k2R/8/8/8/8/8/8/4K3 w - - 0 1"), Some(state_error::Error::OpponentInCheck));
+    }
+
+    #[test]
+    fn castling_rights() {
+        // No rook to castle with, and a king off its back rank
+        assert_eq!(
+            invalid("4k3/8/8/8/8/8/8/4K3 w K - 0 1"),
+            Some(state_error::Error::CastlingRights(castling::Castling::WK))
+        );
+        assert_eq!(
+            invalid("4k3/8/8/8/8/8/4K3/7R w K - 0 1"),
+            Some(state_error::Error::CastlingRights(castling::Castling::WK))
+        );
+    }
+
+    #[test]
+    fn en_passant() {
+        assert_eq!(
+            invalid("4k3/8/8/8/8/8/8/4K3 w - e6 0 1"),
+            Some(state_error::Error::EnPassant(position::Position::E6))
+        );
+        assert_eq!(
+            invalid("4k3/8/8/4pP2/8/8/8/4K3 w - e3 0 1"),
+            Some(state_error::Error::EnPassant(position::Position::E3))
+        );
+    }
 }
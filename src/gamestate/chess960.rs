@@ -0,0 +1,114 @@
+use crate::board::{castling, colour, pieces, position};
+
+use super::{boardstate, zobrist};
+
+// === Chess960 starting positions ===
+// Numbered 0..960 following Scharnagl's scheme, 518 being the standard setup.
+pub const POSITIONS: u16 = 960;
+pub const STANDARD: u16 = 518;
+
+// Knight placements among the five squares left after the bishops and queen
+const KNIGHTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+// The back rank, a file to h file, for a position index
+pub fn back_rank(index: u16) -> Option<[pieces::Kind; 8]> {
+    if index >= POSITIONS {
+        return None;
+    }
+
+    let mut rank: [Option<pieces::Kind>; 8] = [None; 8];
+    let mut n = index as usize;
+
+    // Light squared bishop on b, d, f or h, then the dark one on a, c, e or g
+    rank[2 * (n % 4) + 1] = Some(pieces::Kind::Bishop);
+    n /= 4;
+    rank[2 * (n % 4)] = Some(pieces::Kind::Bishop);
+    n /= 4;
+
+    place_on_empty(&mut rank, n % 6, pieces::Kind::Queen);
+    n /= 6;
+
+    // Place the second knight first so the first one's index is unaffected
+    let (first, second) = KNIGHTS[n];
+    place_on_empty(&mut rank, second, pieces::Kind::Knight);
+    place_on_empty(&mut rank, first, pieces::Kind::Knight);
+
+    // The king always ends up between the rooks
+    for kind in [pieces::Kind::Rook, pieces::Kind::King, pieces::Kind::Rook] {
+        place_on_empty(&mut rank, 0, kind);
+    }
+
+    let mut kinds = [pieces::Kind::Pawn; 8];
+    for (kind, placed) in kinds.iter_mut().zip(rank) {
+        *kind = placed?;
+    }
+
+    Some(kinds)
+}
+
+fn place_on_empty(rank: &mut [Option<pieces::Kind>; 8], nth: usize, kind: pieces::Kind) {
+    if let Some(square) = rank.iter_mut().filter(|sq| sq.is_none()).nth(nth) {
+        *square = Some(kind);
+    }
+}
+
+// The full starting State for a position index, with all four castling rights
+pub fn start_position(index: u16) -> Option<boardstate::State> {
+    let kinds = back_rank(index)?;
+    let mut state = boardstate::State::default();
+
+    for side in [colour::Colour::White(()), colour::Colour::Black(())] {
+        let back = position::Rank::R1.relative(side);
+        let pawns = position::Rank::R2.relative(side);
+
+        for (file, kind) in position::File::ALL.into_iter().zip(kinds) {
+            state.toggle_piece(
+                pieces::from_colour_kind(&side, kind),
+                position::Position::new(file, back),
+            );
+            state.toggle_piece(
+                pieces::from_colour_kind(&side, pieces::Kind::Pawn),
+                position::Position::new(file, pawns),
+            );
+        }
+    }
+
+    let mut rooks = position::File::ALL
+        .into_iter()
+        .zip(kinds)
+        .filter(|(_, kind)| *kind == pieces::Kind::Rook)
+        .map(|(file, _)| file);
+    let queenside = rooks.next()?;
+    let kingside = rooks.next()?;
+
+    state.castling_rooks = castling::CastlingRooks([kingside, queenside, kingside, queenside]);
+    state.castling = castling::CastlingRights(0b1111);
+    state.full_moves = 1;
+    state.key = zobrist::hash(&state);
+
+    Some(state)
+}
+
+// The index of a starting State's back rank, if it is a Chess960 setup
+pub fn index_of(state: &boardstate::State) -> Option<u16> {
+    let rank: Vec<pieces::Kind> = position::File::ALL
+        .into_iter()
+        .map(|file| {
+            state.mailbox_layer[position::Position::new(file, position::Rank::R1)]
+                .map(|p| pieces::get_kind(&p))
+        })
+        .collect::<Option<_>>()?;
+
+    (0..POSITIONS).find(|i| back_rank(*i).is_some_and(|kinds| kinds[..] == rank[..]))
+}
@@ -6,17 +6,29 @@ use crate::{board::{castling, pieces, position}, engine::movement};
 
 // | Bit Index | Field Name    | Size (bits) | Description                             |
 // |-----------|---------------|-------------|-----------------------------------------|
-// | 31–26     | `extra`       | 6           | Reserved for overlays, flags, extensions|
+// | 127–96    | `half_moves`  | 32          | Half move clock before the move         |
+// | 95–64     | `exploded`    | 32          | Atomic, piece IDs blown up around the   |
+// |           |               |             | target, 4 bits per king-move neighbour  |
+// | 63–47     | `extra`       | 17          | Reserved                                |
+// | 46        | `explosion`   | 1           | Atomic capture, the capturer explodes   |
+// | 45        | `cap_promoted`| 1           | Captured piece had been promoted        |
+// | 44        | `inverted`    | 1           | Delta retracts rather than applies      |
+// | 43–40     | `promoted`    | 4           | Piece ID promoted to (or `0` if none)   |
+// | 39–32     | `extra`       | 8           | Reserved                                |
+// | 31        | `drop`        | 1           | Piece dropped from the pocket           |
+// | 30        | `castle`      | 1           | King castled, target is the rook square |
+// | 29        | `prev_ep`     | 1           | En passant was available before the move|
+// | 28–26     | `prev_ep_file`| 3           | File of that en passant square          |
 // | 25        | `en_passant`  | 1           | En passant flag (`0 = no`, `1 = valid`) |
 // | 24        | `promotion`   | 1           | Promotion flag (`0 = no`, `1 = yes`)    |
-// | 23–20     | `castling`    | 4           | Castling info (side, rook pos, etc.)    |
+// | 23–20     | `castling`    | 4           | Castling rights lost by the move        |
 // | 19–16     | `moved_piece` | 4           | Piece type that was moved               |
-// | 15–10     | `source`      | 6           | Destination square index                |
-// | 9–4       | `target`      | 6           | Source square index                     |
+// | 15–10     | `source`      | 6           | Source square index                     |
+// | 9–4       | `target`      | 6           | Destination square index                |
 // | 3–0       | `captured`    | 4           | Captured piece ID (or `0` if none)      |
 
 #[derive(Clone, Copy, PartialEq, Eq)]
-pub struct Delta(pub u32);
+pub struct Delta(pub u128);
 
 // Iterator for lazy delta chains
 impl IntoIterator for Delta {
@@ -42,7 +54,7 @@ impl fmt::Display for Delta {
 
         writeln!(
             f,
-            "Delta: {:08X} | Castling rights lost: [{}] | Piece: {} | Movement: {} -> {} | Captures: {}",
+            "Delta: {:032X} | Castling rights lost: [{}] | Piece: {} | Movement: {} -> {} | Captures: {}",
             self.0,
             castling_str,
             moved_piece(*self).expect("Pieces always move"),
@@ -58,16 +70,49 @@ impl fmt::Display for Delta {
 
 
 //=== Delta Data access funcs ===
+pub fn is_inverted(delta: Delta) -> bool {
+    (delta.0 >> 44) & 1 != 0
+}
+
 pub fn is_en_passant(delta: Delta) -> bool {
-    delta.0 >> 25 != 0
+    (delta.0 >> 25) & 1 != 0
+}
+
+pub fn is_castle(delta: Delta) -> bool {
+    (delta.0 >> 30) & 1 != 0
+}
+
+pub fn is_drop(delta: Delta) -> bool {
+    (delta.0 >> 31) & 1 != 0
+}
+
+pub fn captured_promoted(delta: Delta) -> bool {
+    (delta.0 >> 45) & 1 != 0
+}
+
+pub fn is_explosion(delta: Delta) -> bool {
+    (delta.0 >> 46) & 1 != 0
+}
+
+// Pieces removed around an Atomic capture, one slot per square of the king
+// attack table from the target, in the order the table iterates them
+pub fn exploded(delta: Delta) -> [Option<pieces::Piece>; 8] {
+    std::array::from_fn(|slot| {
+        pieces::try_from_u8(((delta.0 >> (64 + 4 * slot)) & 0xF) as u8)
+            .expect("Invalid Piece encoding in State Delta")
+    })
 }
 
 pub fn is_promotion(delta: Delta) -> bool {
-    delta.0 >> 24 != 0
+    (delta.0 >> 24) & 1 != 0
 }
 
 pub fn castling(delta: Delta) -> impl Iterator<Item = castling::Castling> {
-    castling::castling_rights_from_bits(castling::CastlingRights(((delta.0 >> 20) & 0xF) as u8))
+    castling::castling_rights_from_bits(castling_lost(delta))
+}
+
+pub fn castling_lost(delta: Delta) -> castling::CastlingRights {
+    castling::CastlingRights(((delta.0 >> 20) & 0xF) as u8)
 }
 
 pub fn moved_piece(delta: Delta) -> Option<pieces::Piece> {
@@ -90,11 +135,28 @@ pub fn captured(delta: Delta) -> Option<pieces::Piece> {
         .expect("Invalid Piece encoding in State Delta")
 }
 
-// === Invert Delta ===
-// This function takes delta D, and produces a new delta d
-// which whem appiled, has the opposite eff of D
+pub fn promoted(delta: Delta) -> Option<pieces::Piece> {
+    pieces::try_from_u8(((delta.0 >> 40) & 0xF) as u8)
+        .expect("Invalid Piece encoding in State Delta")
+}
+
+pub fn prev_en_passant_file(delta: Delta) -> Option<position::File> {
+    if (delta.0 >> 29) & 1 != 0 {
+        position::File::from_index(((delta.0 >> 26) & 0x7) as u8)
+    } else {
+        None
+    }
+}
+
+pub fn prev_half_moves(delta: Delta) -> u32 {
+    (delta.0 >> 96) as u32
+}
+
+// === Delta construction ===
+// Inverting delta D produces a new delta d
+// which when appiled, has the opposite effect of D
 #[derive(Clone, Copy)]
-pub struct DeltaBuilder(pub u32);
+pub struct DeltaBuilder(pub u128);
 
 impl DeltaBuilder {
     pub fn new() -> Self {
@@ -104,7 +166,52 @@ impl DeltaBuilder {
     pub fn en_passant(self, enpassant: bool) -> Self {
         let mut res = self;
 
-        res.0 &= (enpassant as u32) << 25;
+        res.0 &= !(1 << 25);
+        res.0 |= (enpassant as u128) << 25;
+
+        res
+    }
+
+    pub fn castle(self, castle: bool) -> Self {
+        let mut res = self;
+
+        res.0 &= !(1 << 30);
+        res.0 |= (castle as u128) << 30;
+
+        res
+    }
+
+    pub fn drops(self, drop: bool) -> Self {
+        let mut res = self;
+
+        res.0 &= !(1 << 31);
+        res.0 |= (drop as u128) << 31;
+
+        res
+    }
+
+    pub fn captured_promoted(self, promoted: bool) -> Self {
+        let mut res = self;
+
+        res.0 &= !(1 << 45);
+        res.0 |= (promoted as u128) << 45;
+
+        res
+    }
+
+    pub fn explodes(self, exploded: Option<[Option<pieces::Piece>; 8]>) -> Self {
+        let mut res = self;
+
+        res.0 &= !((1 << 46) | (0xFFFF_FFFF << 64));
+        if let Some(pieces) = exploded {
+            res.0 |= 1 << 46;
+            for (slot, piece) in pieces.into_iter().enumerate() {
+                if let Some(p) = piece {
+                    let bits: u8 = p.into();
+                    res.0 |= (bits as u128) << (64 + 4 * slot);
+                }
+            }
+        }
 
         res
     }
@@ -112,7 +219,8 @@ impl DeltaBuilder {
     pub fn promotion(self, promotion: bool) -> Self {
         let mut res = self;
 
-        res.0 &= (promotion as u32) << 24;
+        res.0 &= !(1 << 24);
+        res.0 |= (promotion as u128) << 24;
 
         res
     }
@@ -122,37 +230,58 @@ impl DeltaBuilder {
 
         let castling_bits = castling.0 & 0xF;
 
-        res.0 &= (castling_bits as u32) << 20;
+        res.0 &= !(0xF << 20);
+        res.0 |= (castling_bits as u128) << 20;
 
         res
     }
 
-    // === Piece Movement ===
-    pub fn set_movement_from_move(self, mv: movement::Move) -> Self {
-        // Extract raw values
-        let piece    = (mv.0 >> 16) & 0xF;
-        let source   = (mv.0 >> 10) & 0x3F;
-        let target   = (mv.0 >> 4)  & 0x3F;
-        let captured = mv.0 & 0xF;
+    // === Irreversible state needed to unmake ===
+    pub fn prev_en_passant(self, en_passant: Option<position::Position>) -> Self {
+        let mut res = self;
 
-        // Reposition into Delta layout
-        let piece_bits    = piece << 16;
-        let source_bits   = source << 10;
-        let target_bits   = target << 4;
-        let captured_bits = captured;
+        res.0 &= !(0xF << 26);
+        if let Some(pos) = en_passant {
+            res.0 |= (1 << 29) | ((pos.file() as u128) << 26);
+        }
 
-        let movement_bits = piece_bits | source_bits | target_bits | captured_bits;
+        res
+    }
+
+    pub fn prev_half_moves(self, half_moves: u32) -> Self {
+        let mut res = self;
+
+        res.0 &= !(0xFFFF_FFFF << 96);
+        res.0 |= (half_moves as u128) << 96;
+
+        res
+    }
+
+    pub fn promoted(self, promoted: Option<pieces::Piece>) -> Self {
+        let mut res = self;
+
+        res.0 &= !(0xF << 40);
+        if let Some(piece) = promoted {
+            let piece_bits: u8 = piece.into();
+            res.0 |= (piece_bits as u128) << 40;
+        }
+
+        res
+    }
 
-        // Clear and insert
-        const MOVEMENT_MASK: u32 = 0x000F_FFFF; // bits 19–0
-        Self((self.0 & !MOVEMENT_MASK) | movement_bits)
+    // === Piece Movement ===
+    pub fn set_movement_from_move(self, mv: movement::Move) -> Self {
+        // The movement fields share a layout with Move
+        const MOVEMENT_MASK: u128 = 0x000F_FFFF; // bits 19–0
+        Self((self.0 & !MOVEMENT_MASK) | (mv.0 as u128 & MOVEMENT_MASK))
     }
 
     pub fn set_piece(self, piece: pieces::Piece) -> Self {
         let mut result = self;
 
         let piece_bit: u8 = piece.into();
-        result.0 |= (piece_bit as u32) << 16;
+        result.0 &= !(0xF << 16);
+        result.0 |= (piece_bit as u128) << 16;
 
         result
     }
@@ -161,7 +290,8 @@ impl DeltaBuilder {
         let mut result = self;
 
         let pos_bits: u8 = src as u8;
-        result.0 |= (pos_bits as u32) << 10;
+        result.0 &= !(0x3F << 10);
+        result.0 |= (pos_bits as u128) << 10;
 
         result
     }
@@ -170,16 +300,20 @@ impl DeltaBuilder {
         let mut result = self;
 
         let trgt_bits: u8 = trgt as u8;
-        result.0 |= (trgt_bits as u32) << 4;
+        result.0 &= !(0x3F << 4);
+        result.0 |= (trgt_bits as u128) << 4;
 
         result
     }
 
-    pub fn captures(self, captures: pieces::Piece) -> Self {
+    pub fn captures(self, captures: Option<pieces::Piece>) -> Self {
         let mut result = self;
 
-        let cap: u8 = captures.into();
-        result.0 |= cap as u32;
+        result.0 &= !0xF;
+        if let Some(piece) = captures {
+            let cap: u8 = piece.into();
+            result.0 |= cap as u128;
+        }
 
         result
     }
@@ -187,7 +321,11 @@ impl DeltaBuilder {
     pub fn build(self) -> Delta {
         Delta(self.0)
     }
+}
 
+// Flips the direction a delta is applied in
+pub fn invert(delta: Delta) -> Delta {
+    Delta(delta.0 ^ (1 << 44))
 }
 
 impl Default for DeltaBuilder {
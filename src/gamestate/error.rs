@@ -0,0 +1,54 @@
+use std::fmt::Display;
+
+use crate::board::{castling, colour, position};
+
+// Reasons a State cannot arise in a legal game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    KingCount { side: colour::Colour<()>, count: u32 },
+    TooManyPieces { side: colour::Colour<()>, count: u32 },
+    TooManyPawns { side: colour::Colour<()>, count: u32 },
+    PawnOnBackRank(position::Position),
+    OpponentInCheck,
+    CastlingRights(castling::Castling),
+    EnPassant(position::Position),
+}
+
+impl std::error::Error for Error {}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::KingCount { side, count } => {
+                writeln!(f, "{} has {} kings, expected exactly one", side_name(*side), count)?
+            }
+            Error::TooManyPieces { side, count } => {
+                writeln!(f, "{} has {} pieces, at most 16 are allowed", side_name(*side), count)?
+            }
+            Error::TooManyPawns { side, count } => {
+                writeln!(f, "{} has {} pawns, at most 8 are allowed", side_name(*side), count)?
+            }
+            Error::PawnOnBackRank(pos) => writeln!(f, "Pawn on the back rank at {}", pos)?,
+            Error::OpponentInCheck => writeln!(f, "The side not to move is in check")?,
+            Error::CastlingRights(right) => writeln!(
+                f,
+                "{} castling right without the king and rook on their home squares",
+                right
+            )?,
+            Error::EnPassant(pos) => writeln!(
+                f,
+                "En passant square {} does not follow a double pawn push",
+                pos
+            )?,
+        }
+
+        Ok(())
+    }
+}
+
+fn side_name(side: colour::Colour<()>) -> &'static str {
+    match side {
+        colour::Colour::White(()) => "White",
+        colour::Colour::Black(()) => "Black",
+    }
+}
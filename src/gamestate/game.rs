@@ -0,0 +1,293 @@
+use std::fmt;
+
+use crate::{
+    board::colour,
+    effects::static_attack_provider::StaticAttackProvider,
+    engine::{movement, san, uci},
+    traits::static_lookup as PRECOMP,
+};
+
+use super::{boardstate, delta, move_lens, status};
+
+// === A game record ===
+// Owns the starting position and everything played from it.
+// The cursor (ply) can be moved back and forth through the record,
+// the current State always being the position after `ply` moves.
+#[derive(Clone)]
+pub struct Game {
+    initial: boardstate::State,
+    current: boardstate::State,
+    moves: Vec<movement::Move>,
+    deltas: Vec<delta::Delta>,
+    // keys[i] is the key of the position after i moves, keys[0] the initial position
+    keys: Vec<u64>,
+    ply: usize,
+}
+
+impl Game {
+    pub fn new(initial: boardstate::State) -> Self {
+        Self {
+            initial,
+            current: initial,
+            moves: Vec::new(),
+            deltas: Vec::new(),
+            keys: vec![initial.key],
+            ply: 0,
+        }
+    }
+
+    // Each move must be legal where it is played, see push
+    pub fn from_moves(
+        initial: boardstate::State,
+        moves: impl IntoIterator<Item = movement::Move>,
+    ) -> Self {
+        let mut game = Self::new(initial);
+        for mv in moves {
+            game.push(mv);
+        }
+
+        game
+    }
+
+    // === Accessors ===
+    pub fn initial(&self) -> &boardstate::State {
+        &self.initial
+    }
+
+    pub fn state(&self) -> &boardstate::State {
+        &self.current
+    }
+
+    pub fn moves(&self) -> &[movement::Move] {
+        &self.moves
+    }
+
+    pub fn deltas(&self) -> &[delta::Delta] {
+        &self.deltas
+    }
+
+    pub fn keys(&self) -> &[u64] {
+        &self.keys
+    }
+
+    pub fn ply(&self) -> usize {
+        self.ply
+    }
+
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+}
+
+// === Editing the record ===
+impl Game {
+    // Plays a move from the current position.
+    // If the cursor is not at the end, the moves after it are discarded.
+    // The move is not checked, callers must pass one that is legal here,
+    // such as from move_gen::generate_legal_moves or uci::decode.
+    pub fn push(&mut self, mv: movement::Move) -> delta::Delta {
+        self.truncate(self.ply);
+
+        let delta = move_lens::History.make_move(&mut self.current, mv);
+        self.moves.push(mv);
+        self.deltas.push(delta);
+        self.keys.push(self.current.key);
+        self.ply += 1;
+
+        delta
+    }
+
+    // Removes the last move of the record, stepping back first if the cursor sits on it
+    pub fn pop(&mut self) -> Option<movement::Move> {
+        if self.ply == self.moves.len() {
+            self.back();
+        }
+
+        let mv = self.moves.pop();
+        self.truncate(self.moves.len());
+
+        mv
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.moves.truncate(len);
+        self.deltas.truncate(len);
+        self.keys.truncate(len + 1);
+    }
+}
+
+// === Navigation ===
+impl Game {
+    pub fn back(&mut self) -> bool {
+        if self.ply == 0 {
+            return false;
+        }
+
+        self.ply -= 1;
+        move_lens::History.unmake_move(&mut self.current, &self.deltas[self.ply]);
+        true
+    }
+
+    pub fn forward(&mut self) -> bool {
+        if self.ply == self.moves.len() {
+            return false;
+        }
+
+        move_lens::History.apply_delta_in_place(&mut self.current, &self.deltas[self.ply]);
+        self.ply += 1;
+        true
+    }
+
+    // Moves the cursor to the given ply, clamped to the end of the record
+    pub fn goto(&mut self, ply: usize) {
+        let ply = ply.min(self.moves.len());
+
+        while self.ply > ply {
+            self.back();
+        }
+        while self.ply < ply {
+            self.forward();
+        }
+    }
+
+    pub fn goto_start(&mut self) {
+        self.goto(0);
+    }
+
+    pub fn goto_end(&mut self) {
+        self.goto(self.moves.len());
+    }
+}
+
+// === Game state queries ===
+impl Game {
+    // How many times the current position has occurred up to the cursor, counting itself
+    pub fn repetitions(&self) -> u32 {
+        status::repetitions(&self.current, &self.deltas[..self.ply])
+    }
+
+    pub fn status<A>(&self, lookup: A) -> status::GameStatus
+    where
+        A: PRECOMP::StaticAttack + Copy + 'static,
+    {
+        status::game_status(&self.current, &self.deltas[..self.ply], lookup)
+    }
+
+    // Space separated UCI moves, as sent after `position ... moves`.
+    // With chess960 set castling is written as the king taking its rook.
+    pub fn to_uci(&self, chess960: bool) -> String {
+        self.moves
+            .iter()
+            .map(|mv| uci::encode(*mv, chess960))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+// === Display the numbered move list in SAN ===
+// Replayed from the initial position, SAN depending on the position each move is played in
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut state = self.initial;
+        let mut side = self.initial.side_to_move;
+        let mut number = self.initial.full_moves.max(1);
+
+        for (i, mv) in self.moves.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            match side {
+                colour::Colour::White(()) => write!(f, "{}. ", number)?,
+                colour::Colour::Black(()) if i == 0 => write!(f, "{}... ", number)?,
+                colour::Colour::Black(()) => {}
+            }
+            write!(f, "{}", san::encode(&state, *mv, StaticAttackProvider))?;
+            move_lens::History.make_move(&mut state, *mv);
+
+            if side == colour::Colour::Black(()) {
+                number += 1;
+            }
+            side = side.opp();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts;
+
+    fn play(fen: &str, moves: &[&str]) -> Game {
+        let mut game = Game::new(boardstate::try_from_fen(fen).unwrap());
+        for text in moves {
+            let mv = uci::decode(game.state(), text, false, StaticAttackProvider).unwrap();
+            game.push(mv);
+        }
+
+        game
+    }
+
+    #[test]
+    fn displays_numbered_san() {
+        let game = play(consts::START_POSITION, &["e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "g8f6", "e1g1"]);
+        assert_eq!(game.to_string(), "1. e4 e5 2. Nf3 Nc6 3. Bb5 Nf6 4. O-O");
+
+        let game = play("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2", &["d8h4"]);
+        assert_eq!(game.to_string(), "2... Qh4#");
+    }
+
+    #[test]
+    fn navigation_returns_to_the_same_keys() {
+        // Castling, a capture, en passant and a promotion along the way
+        let moves = ["e2e4", "d7d5", "e4d5", "c7c5", "d5c6", "g8f6", "c6b7", "e7e6", "b7a8q", "f8e7", "g1f3", "e8g8"];
+        let mut game = play(consts::START_POSITION, &moves);
+        let keys = game.keys().to_vec();
+        let fens: Vec<String> = (0..=moves.len())
+            .map(|ply| boardstate::to_fen(*play(consts::START_POSITION, &moves[..ply]).state()).unwrap())
+            .collect();
+
+        let at = |game: &Game| {
+            assert_eq!(game.state().key, keys[game.ply()], "ply {}", game.ply());
+            assert_eq!(boardstate::to_fen(*game.state()).unwrap(), fens[game.ply()], "ply {}", game.ply());
+        };
+
+        while game.back() {
+            at(&game);
+        }
+        assert!(!game.back());
+        while game.forward() {
+            at(&game);
+        }
+        assert!(!game.forward());
+
+        for ply in [3, 9, 0, 12, 5, 100] {
+            game.goto(ply);
+            assert_eq!(game.ply(), ply.min(moves.len()));
+            at(&game);
+        }
+
+        // Popping from the end, then playing the move again
+        game.goto_end();
+        let last = game.pop().unwrap();
+        assert_eq!(game.len(), moves.len() - 1);
+        at(&game);
+        game.push(last);
+        at(&game);
+        assert_eq!(game.keys(), keys);
+
+        // Pushing in the middle drops the rest of the record
+        game.goto(4);
+        let mv = uci::decode(game.state(), "d1g4", false, StaticAttackProvider).unwrap();
+        game.push(mv);
+        assert_eq!(game.len(), 5);
+        assert!(game.pop().is_some());
+        at(&game);
+        assert_eq!(game.keys(), &keys[..=4]);
+    }
+}
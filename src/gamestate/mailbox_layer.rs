@@ -0,0 +1,62 @@
+use crate::board::{bitboard, pieces, position};
+use std::ops::{Index, IndexMut};
+
+use super::material_layer;
+
+// A square centric view of the board,
+// gives O(1) lookup of the piece on a given position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MailboxLayer(pub [Option<pieces::Piece>; 64]);
+
+// === Traits for easy indexing ===
+impl Index<position::Position> for MailboxLayer {
+    type Output = Option<pieces::Piece>;
+
+    fn index(&self, pos: position::Position) -> &Self::Output {
+        &self.0[pos]
+    }
+}
+
+impl IndexMut<position::Position> for MailboxLayer {
+    fn index_mut(&mut self, pos: position::Position) -> &mut Self::Output {
+        &mut self.0[pos]
+    }
+}
+
+impl MailboxLayer {
+    pub fn new() -> Self {
+        generate_mailbox(material_layer::MaterialLayer::new())
+    }
+
+    pub fn empty() -> Self {
+        Self([None; 64])
+    }
+
+    // Bitboard of every square holding a piece
+    pub fn occupied(&self) -> bitboard::Bitboard {
+        position::Position::ALL
+            .iter()
+            .filter(|pos| self[**pos].is_some())
+            .copied()
+            .collect()
+    }
+}
+
+impl Default for MailboxLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn generate_mailbox(mat_layer: material_layer::MaterialLayer) -> MailboxLayer {
+    let mut mailbox = MailboxLayer::empty();
+
+    for (i, bb) in mat_layer.iter().enumerate() {
+        let piece = pieces::Piece::try_from(i).ok();
+        for pos in bb.iter() {
+            mailbox[pos] = piece;
+        }
+    }
+
+    mailbox
+}
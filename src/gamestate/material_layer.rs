@@ -8,7 +8,6 @@ use std::{
     slice::Iter,
 };
 use colored::*;
-use array_init::array_init;
 
 // This struct contains the bitboards for all
 // of the pieces
@@ -87,16 +86,16 @@ impl fmt::Display for MaterialLayer {
         for (i, b) in self.0.iter().enumerate() {
             let piece = pieces::Piece::try_from(i).expect("Invalid board index");
 
-            (0..8).for_each(|x| {
-                for y in 0..8 {
+            for (x, rank) in board.iter_mut().enumerate() {
+                for (y, tile) in rank.iter_mut().enumerate() {
                     let idx = x * 8 + y;
                     if let Some(pos) = position::Position::from_u64(idx as u64) {
                         if b.is_occupied(pos) {
-                            board[x][y] = Some(piece);
+                            *tile = Some(piece);
                         }
                     }
                 }
-            });
+            }
         }
 
         // Render board
@@ -180,6 +179,11 @@ impl MaterialLayer {
     pub fn iter(&self) -> std::slice::Iter<'_, bitboard::Bitboard> {
         self.0.iter()
     }
+
+    // Flip a single piece on or off in place
+    pub fn toggle(&mut self, piece: pieces::Piece, pos: position::Position) {
+        self[piece] ^= 1u64 << pos as u64;
+    }
 }
 
 impl Default for MaterialLayer {
@@ -194,15 +198,10 @@ pub fn move_piece(
     source: position::Position,
     target: position::Position,
 ) -> [bitboard::Bitboard; 12] {
-    array_init(|i| {
-        if i == piece.index() {
-            mat_layer[i]
-                .pop_bit(source)
-                .set_bit(target)
-        } else {
-            mat_layer[i]
-        }
-    })
+    let mut layer = mat_layer;
+    layer.toggle(piece, source);
+    layer.toggle(piece, target);
+    layer.0
 }
 
 pub fn capture_piece(
@@ -210,12 +209,7 @@ pub fn capture_piece(
     capture: pieces::Piece,
     target: position::Position,
 ) -> [bitboard::Bitboard; 12] {
-    array_init(|i| {
-        if i == capture.index() {
-            mat_layer[i]
-                .pop_bit(target)
-        } else {
-            mat_layer[i]
-        }
-    })
+    let mut layer = mat_layer;
+    layer[capture] = layer[capture].pop_bit(target);
+    layer.0
 }
@@ -1,13 +1,11 @@
-use itertools::Itertools;
-
 use crate::{
     board::{castling, colour, pieces, position},
     engine::movement,
-    gamestate::{material_layer, occupancy_layer},
-    traits::lens,
+    effects::static_attack_provider::StaticAttackProvider,
+    traits::{lens, static_lookup::StaticAttack},
 };
 
-use super::{boardstate, delta};
+use super::{boardstate, delta, variant, zobrist};
 
 pub struct History;
 
@@ -18,106 +16,370 @@ impl lens::MoveLens<boardstate::State, movement::Move, delta::Delta> for History
         state: boardstate::State,
         mv: movement::Move,
     ) -> (boardstate::State, super::delta::Delta) {
-        // 0. Check peice type
-        // - If king moves remove CR rights
-        // - If Rook moves remove CR rights for that side
-        // 1.Pop bit at source on side occ and piece material_layer
-        // 2.If capture pop bit at target on opp side occ and capture piece material_layer
-        // 3.Set bit at target on side occ and piece material_layer
+        let mut new_state = state;
+        let delta = self.make_move(&mut new_state, mv);
+
+        (new_state, delta)
+    }
+
+    fn apply_delta(
+        &self,
+        state: boardstate::State,
+        delta: &super::delta::Delta,
+    ) -> boardstate::State {
+        let mut new_state = state;
+        self.apply_delta_in_place(&mut new_state, delta);
+
+        new_state
+    }
+
+    fn invert(&self, delta: &super::delta::Delta) -> super::delta::Delta {
+        delta::invert(*delta)
+    }
+}
+
+// === In place make/unmake ===
+// Only the bitboards touched by the move are XORed,
+// rather than rebuilding every layer from the material layer.
+impl History {
+    pub fn make_move(&self, state: &mut boardstate::State, mv: movement::Move) -> delta::Delta {
+        let delta = self.delta_from_move(state, mv);
+        self.apply_delta_in_place(state, &delta);
+
+        delta
+    }
+
+    pub fn unmake_move(&self, state: &mut boardstate::State, delta: &delta::Delta) {
+        self.apply_delta_in_place(state, &delta::invert(*delta));
+    }
 
+    pub fn apply_delta_in_place(&self, state: &mut boardstate::State, delta: &delta::Delta) {
+        if delta::is_inverted(*delta) {
+            retract(state, *delta);
+        } else {
+            advance(state, *delta);
+        }
+
+        debug_assert_eq!(boardstate::check_layers(state), Ok(()));
+    }
+
+    // Records everything needed to both replay and retract the move
+    fn delta_from_move(&self, state: &boardstate::State, mv: movement::Move) -> delta::Delta {
+        // 0. Check peice type
+        // - If king moves remove CR rights for that side
+        // - If a castling rook moves, or is captured, remove that right
+        // 1. Castling is encoded as the king taking its own rook
+        // 2. Work out what is captured, en passant takes from behind the target
+        // 3. Store the irreversible state so we can step back
         let piece = movement::piece(mv).expect("There will always be a piece moving");
+        let side = pieces::get_colour(&piece);
         let source = movement::source(mv);
         let target = movement::target(mv);
-        let capture = movement::capture(mv);
-
-        // === New State ===
-        let new_cr: Option<Vec<castling::Castling>> = match pieces::get_kind(&piece) {
-            pieces::Kind::King => {
-                Some(castling::castling_rights_from_bits(state.castling).collect())
-            }
-            pieces::Kind::Rook => match source {
-                position::Position::H8 | position::Position::H1 => {
-                    let rights = castling::castling_rights_from_bits(state.castling)
-                        .filter_map(|c| castling::Castling::is_kingside(&c).map(|b| (c, b)))
-                        .filter(|(_, kingside)| !*kingside)
-                        .map(|(c, _)| c)
-                        .collect();
-                    Some(rights)
-                }
-                position::Position::A8 | position::Position::A1 => {
-                    let rights = castling::castling_rights_from_bits(state.castling)
-                        .filter_map(|c| castling::Castling::is_kingside(&c).map(|b| (c, b)))
-                        .filter(|(_, kingside)| *kingside)
-                        .map(|(c, _)| c)
-                        .collect();
-                    Some(rights)
-                }
-                _ => None,
-            },
-            _ => None,
+
+        // Drops come from the pocket, nothing is captured and no rights change
+        if movement::is_drop(mv) {
+            return delta::DeltaBuilder::new()
+                .set_movement_from_move(mv)
+                .captures(None)
+                .drops(true)
+                .prev_en_passant(state.en_passant)
+                .prev_half_moves(state.half_moves)
+                .build();
+        }
+
+        let is_king = pieces::get_kind(&piece) == pieces::Kind::King;
+        let is_castle = is_king
+            && state.mailbox_layer[target]
+                == Some(pieces::from_colour_kind(&side, pieces::Kind::Rook));
+
+        let is_en_passant = pieces::get_kind(&piece) == pieces::Kind::Pawn
+            && state.en_passant == Some(target)
+            && source.file() != target.file();
+
+        let captured = if is_en_passant {
+            Some(pieces::from_colour_kind(&side.opp(), pieces::Kind::Pawn))
+        } else if is_castle {
+            None
+        } else {
+            state.mailbox_layer[target]
         };
 
-        let new_castling_rights = match new_cr{
-            Some(cr) => castling::CastlingRights::from_rights(&cr),
-            None => castling::CastlingRights(0),
+        // An Atomic capture also removes the capturer and the pieces around the target
+        let exploded = (state.variant == variant::Variant::Atomic && captured.is_some())
+            .then(|| variant::blast(state, target, source, StaticAttackProvider));
+        let blast_rights = exploded
+            .into_iter()
+            .flat_map(|blast| StaticAttackProvider.king(target).into_iter().zip(blast))
+            .filter_map(|(pos, piece)| piece.map(|p| (pos, p)))
+            .fold(0, |rights, (pos, piece)| {
+                let king_rights = if pieces::get_kind(&piece) == pieces::Kind::King {
+                    castling::side_rights(pieces::get_colour(&piece)).0
+                } else {
+                    0
+                };
+                rights | king_rights | state.castling_rooks.rights_touching(pos).0
+            });
+
+        let king_rights = if is_king {
+            castling::side_rights(side).0
+        } else {
+            0
         };
+        let lost = castling::CastlingRights(
+            state.castling.0
+                & (king_rights
+                    | state.castling_rooks.rights_touching(source).0
+                    | state.castling_rooks.rights_touching(target).0
+                    | blast_rights),
+        );
 
-        let new_mat_layer = material_layer::MaterialLayer(match capture {
-            Some(cap) => {
-                let new_mat = material_layer::MaterialLayer(material_layer::move_piece(
-                    state.material_layer,
-                    piece,
-                    source,
-                    target,
-                ));
-                material_layer::capture_piece(new_mat, cap, target)
-            }
-            None => material_layer::move_piece(state.material_layer, piece, source, target),
-        });
-
-        let new_half_move = if (pieces::get_kind(&piece) == pieces::Kind::Pawn)
-            || movement::traits(mv).contains(&movement::MoveTrait::Capture)
-        {
-            state.half_moves
+        let promoted = movement::promotion(mv);
+
+        delta::DeltaBuilder::new()
+            .set_movement_from_move(mv)
+            .captures(captured)
+            .castling(lost)
+            .castle(is_castle)
+            .en_passant(is_en_passant)
+            .promotion(promoted.is_some())
+            .promoted(promoted)
+            .captured_promoted(captured.is_some() && state.promoted.is_occupied(target))
+            .explodes(exploded)
+            .prev_en_passant(state.en_passant)
+            .prev_half_moves(state.half_moves)
+            .build()
+    }
+}
+
+// The fields of a delta, decoded once per make/unmake
+struct Unpacked {
+    piece: pieces::Piece,
+    side: colour::Colour<()>,
+    source: position::Position,
+    target: position::Position,
+    captured: Option<pieces::Piece>,
+    placed: pieces::Piece,
+    capture_square: position::Position,
+    // Rook from and to squares when castling, the king then lands on `target`
+    castle_rook: Option<(position::Position, position::Position)>,
+    drop: bool,
+    // Atomic captures leave nothing on the target, `blast` pairs squares with what they held
+    explosion: bool,
+    blast: [(position::Position, Option<pieces::Piece>); 8],
+    // The kind the capture adds to the mover's pocket, promoted pieces revert to pawns
+    pocketed: Option<pieces::Kind>,
+}
+
+impl Unpacked {
+    fn new(delta: delta::Delta) -> Self {
+        let piece = delta::moved_piece(delta).expect("There will always be a piece moving");
+        let source = delta::source(delta);
+        let mut target = delta::target(delta);
+
+        // En passant takes the pawn behind the target
+        let capture_square = if delta::is_en_passant(delta) {
+            position::Position::new(target.file(), source.rank())
         } else {
-            state.half_moves + 1
+            target
         };
 
-        let new_state = boardstate::State {
-            material_layer: new_mat_layer,
-            occupancy_layer: occupancy_layer::generate_occ(new_mat_layer),
-            en_passant: state.en_passant, // Need to check how to set this
-            side_to_move: state.side_to_move.opp(),
-            castling: new_castling_rights,
-            half_moves: new_half_move,
-            full_moves: match state.side_to_move {
-                colour::Colour::White(()) => state.full_moves,
-                colour::Colour::Black(()) => state.full_moves + 1,
-            },
+        // Castling moves the king onto its own rook,
+        // both then jump to the same squares as in standard chess
+        let castle_rook = if delta::is_castle(delta) {
+            let kingside = target.file() > source.file();
+            let right = castling::Castling::for_side(pieces::get_colour(&piece), kingside);
+            let (king_to, rook_to) =
+                castling::destinations(right).expect("A side always has castling squares");
+            let rook_from = target;
+            target = king_to;
+            Some((rook_from, rook_to))
+        } else {
+            None
         };
 
-        // === Delta from new State === 
-        let delta = delta::DeltaBuilder::new()
-            .castling(new_castling_rights)
-            .set_movement_from_move(mv)
-            .build();
+        Self {
+            piece,
+            side: pieces::get_colour(&piece),
+            source,
+            target,
+            captured: delta::captured(delta),
+            placed: delta::promoted(delta).unwrap_or(piece),
+            capture_square,
+            castle_rook,
+            drop: delta::is_drop(delta),
+            explosion: delta::is_explosion(delta),
+            blast: blast_squares(delta, target),
+            pocketed: delta::captured(delta).map(|cap| {
+                if delta::captured_promoted(delta) {
+                    pieces::Kind::Pawn
+                } else {
+                    pieces::get_kind(&cap)
+                }
+            }),
+        }
+    }
 
-        (new_state, delta)
+    // Pieces to lift or restore around an explosion
+    fn exploded(&self) -> impl Iterator<Item = (pieces::Piece, position::Position)> + '_ {
+        self.blast
+            .iter()
+            .take_while(|_| self.explosion)
+            .filter_map(|(pos, piece)| piece.map(|p| (p, *pos)))
     }
 
-    fn apply_delta(
-        &self,
-        state: boardstate::State,
-        delta: &super::delta::Delta,
-    ) -> boardstate::State {
-        unimplemented!("If a move is forward, then delta is inhertly backwards")
+    fn rook(&self) -> pieces::Piece {
+        pieces::from_colour_kind(&self.side, pieces::Kind::Rook)
     }
+}
 
-    fn invert(&self, delta: &super::delta::Delta) -> super::delta::Delta {
-        unimplemented!("This might not need to exist")
+fn advance(state: &mut boardstate::State, delta: delta::Delta) {
+    let mv = Unpacked::new(delta);
+    state.key ^= meta_key(state);
+
+    let was_promoted = state.promoted.is_occupied(mv.source);
+
+    if let Some(cap) = mv.captured {
+        state.toggle_piece(cap, mv.capture_square);
+        state.promoted.mutate_pop_bit(mv.capture_square);
+        if state.variant == variant::Variant::Crazyhouse {
+            state.pockets.add(mv.side, mv.pocketed.expect("Captures are pocketed"));
+        }
+    }
+    if mv.drop {
+        state.pockets.remove(mv.side, pieces::get_kind(&mv.piece));
+    } else {
+        // Lift everything before placing, in Chess960 the squares can overlap
+        state.toggle_piece(mv.piece, mv.source);
+        state.promoted.mutate_pop_bit(mv.source);
+    }
+    if let Some((rook_from, _)) = mv.castle_rook {
+        state.toggle_piece(mv.rook(), rook_from);
+    }
+    if mv.explosion {
+        for (piece, pos) in mv.exploded() {
+            state.toggle_piece(piece, pos);
+        }
+    } else {
+        state.toggle_piece(mv.placed, mv.target);
+    }
+    if let Some((_, rook_to)) = mv.castle_rook {
+        state.toggle_piece(mv.rook(), rook_to);
+    }
+    // Only Crazyhouse needs to remember which pieces were once pawns
+    if was_promoted
+        || (delta::is_promotion(delta) && state.variant == variant::Variant::Crazyhouse)
+    {
+        state.promoted.mutate_set_bit(mv.target);
     }
 
-    fn undo(&self, state: boardstate::State, delta: &super::delta::Delta) -> boardstate::State {
-        unimplemented!("Undo state using delta")
+    let is_pawn = pieces::get_kind(&mv.piece) == pieces::Kind::Pawn;
+
+    state.castling.0 &= !delta::castling_lost(delta).0;
+    // Only a double push from the second rank can be taken en passant, not a Horde first rank one
+    state.en_passant = if is_pawn
+        && mv.source.relative_rank(mv.side) == position::Rank::R2
+        && mv.source.rank().distance(mv.target.rank()) == 2
+    {
+        position::Position::from_index((mv.source as u8 + mv.target as u8) / 2)
+    } else {
+        None
+    };
+    state.half_moves = if is_pawn || mv.captured.is_some() {
+        0
+    } else {
+        state.half_moves + 1
+    };
+    if mv.side == colour::Colour::Black(()) {
+        state.full_moves += 1;
+    }
+    state.side_to_move = mv.side.opp();
+    variant::after_move(state, mv.side, false, StaticAttackProvider);
+    state.key ^= meta_key(state);
+}
+
+fn retract(state: &mut boardstate::State, delta: delta::Delta) {
+    let mv = Unpacked::new(delta);
+    state.key ^= meta_key(state);
+    variant::after_move(state, mv.side, true, StaticAttackProvider);
+
+    let was_promoted = state.promoted.is_occupied(mv.target) && !delta::is_promotion(delta);
+    state.promoted.mutate_pop_bit(mv.target);
+
+    if let Some((_, rook_to)) = mv.castle_rook {
+        state.toggle_piece(mv.rook(), rook_to);
+    }
+    if mv.explosion {
+        for (piece, pos) in mv.exploded() {
+            state.toggle_piece(piece, pos);
+        }
+    } else {
+        state.toggle_piece(mv.placed, mv.target);
+    }
+    if let Some((rook_from, _)) = mv.castle_rook {
+        state.toggle_piece(mv.rook(), rook_from);
+    }
+    if mv.drop {
+        state.pockets.add(mv.side, pieces::get_kind(&mv.piece));
+    } else {
+        state.toggle_piece(mv.piece, mv.source);
+        if was_promoted {
+            state.promoted.mutate_set_bit(mv.source);
+        }
+    }
+    if let Some(cap) = mv.captured {
+        state.toggle_piece(cap, mv.capture_square);
+        if delta::captured_promoted(delta) {
+            state.promoted.mutate_set_bit(mv.capture_square);
+        }
+        if state.variant == variant::Variant::Crazyhouse {
+            state.pockets.remove(mv.side, mv.pocketed.expect("Captures are pocketed"));
+        }
+    }
+
+    state.castling.0 |= delta::castling_lost(delta).0;
+    state.en_passant = delta::prev_en_passant_file(delta).map(|file| {
+        position::Position::new(file, position::Rank::R6.relative(mv.side))
+    });
+    state.half_moves = delta::prev_half_moves(delta);
+    if mv.side == colour::Colour::Black(()) {
+        state.full_moves = state.full_moves.saturating_sub(1);
+    }
+    state.side_to_move = mv.side;
+    state.key ^= meta_key(state);
+}
+
+// The squares around the target paired with the pieces an explosion removed from them
+fn blast_squares(
+    delta: delta::Delta,
+    target: position::Position,
+) -> [(position::Position, Option<pieces::Piece>); 8] {
+    let mut blast = [(target, None); 8];
+    if delta::is_explosion(delta) {
+        for ((slot, pos), piece) in blast
+            .iter_mut()
+            .zip(StaticAttackProvider.king(target))
+            .zip(delta::exploded(delta))
+        {
+            *slot = (pos, piece);
+        }
+    }
+
+    blast
+}
+
+// The non-material part of the key, XORed out before and back in after a move
+// (toggle_piece already takes care of the pieces).
+// Checks, pockets and promoted squares hash to nothing outside their variants
+fn meta_key(state: &boardstate::State) -> u64 {
+    let key = zobrist::castling_key(state.castling)
+        ^ zobrist::side_key(state.side_to_move)
+        ^ zobrist::en_passant_key(state);
+
+    match state.variant {
+        variant::Variant::ThreeCheck => key ^ zobrist::checks_key(state.checks),
+        variant::Variant::Crazyhouse => {
+            key ^ zobrist::pockets_key(&state.pockets) ^ zobrist::promoted_key(state.promoted)
+        }
+        _ => key,
     }
 }
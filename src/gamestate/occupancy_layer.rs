@@ -1,29 +1,27 @@
-use std::ops::Index;
+use std::ops::{Index, IndexMut};
 
 use crate::board::bitboard;
 use crate::board::colour;
 use crate::board::pieces;
+use crate::board::position;
 
 use super::material_layer;
 
 // New type struct for occupancy
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct OccupancyLayer(pub [bitboard::Bitboard; 2]);
 
 impl OccupancyLayer {
     pub fn new() -> Self {
-        Self([
-            // White (bottom) at index 0
-            bitboard::Bitboard({
-                let rank_mask: u64 = 0xFF;
-                rank_mask | (rank_mask << 8) // ranks 1 and 2
-            }),
-            // Red (top) at index 1
-            bitboard::Bitboard({
-                let rank_mask: u64 = 0xFF;
-                (rank_mask << 48) | (rank_mask << 56) // ranks 7 and 8
-            }),
-        ])
+        // White on ranks 1 and 2 at index 0, black on ranks 7 and 8 at index 1
+        generate_occ(material_layer::MaterialLayer::new())
+    }
+}
+
+impl OccupancyLayer {
+    // Flip a single square on or off in place
+    pub fn toggle(&mut self, side: colour::Colour<()>, pos: position::Position) {
+        self[side] ^= 1u64 << pos as u64;
     }
 }
 
@@ -48,6 +46,15 @@ impl Index<colour::Colour<()>> for OccupancyLayer {
     }
 }
 
+impl IndexMut<colour::Colour<()>> for OccupancyLayer {
+    fn index_mut(&mut self, index: colour::Colour<()>) -> &mut Self::Output {
+        match index {
+            colour::Colour::White(()) => &mut self.0[0],
+            colour::Colour::Black(()) => &mut self.0[1],
+        }
+    }
+}
+
 pub fn generate_occ(mat_layer: material_layer::MaterialLayer) -> OccupancyLayer {
     // White occupancy: combine all white piece bitboards
     OccupancyLayer([
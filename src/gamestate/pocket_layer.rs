@@ -0,0 +1,71 @@
+use std::fmt;
+
+use crate::board::{colour, pieces};
+
+// === Crazyhouse pockets ===
+// Pieces each side has captured and may drop back onto the board,
+// counted per kind in the order pawn, knight, bishop, rook, queen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PocketLayer(pub [[u8; 5]; 2]);
+
+// Kinds that can be held, kings are never captured in Crazyhouse
+pub const KINDS: [pieces::Kind; 5] = [
+    pieces::Kind::Pawn,
+    pieces::Kind::Knight,
+    pieces::Kind::Bishop,
+    pieces::Kind::Rook,
+    pieces::Kind::Queen,
+];
+
+fn slot(kind: pieces::Kind) -> Option<usize> {
+    KINDS.iter().position(|k| *k == kind)
+}
+
+impl PocketLayer {
+    pub fn new() -> Self {
+        Self([[0; 5]; 2])
+    }
+
+    pub fn count(&self, side: colour::Colour<()>, kind: pieces::Kind) -> u8 {
+        slot(kind).map(|i| self.0[side][i]).unwrap_or(0)
+    }
+
+    pub fn add(&mut self, side: colour::Colour<()>, kind: pieces::Kind) {
+        if let Some(i) = slot(kind) {
+            self.0[side][i] = self.0[side][i].saturating_add(1);
+        }
+    }
+
+    pub fn remove(&mut self, side: colour::Colour<()>, kind: pieces::Kind) {
+        if let Some(i) = slot(kind) {
+            self.0[side][i] = self.0[side][i].saturating_sub(1);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().flatten().all(|count| *count == 0)
+    }
+
+    // Kinds the side holds at least one of
+    pub fn held(&self, side: colour::Colour<()>) -> impl Iterator<Item = pieces::Kind> + '_ {
+        KINDS
+            .into_iter()
+            .filter(move |kind| self.count(side, *kind) > 0)
+    }
+}
+
+// === Display as FEN pocket letters, white then black, queens first ===
+impl fmt::Display for PocketLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for side in [colour::Colour::White(()), colour::Colour::Black(())] {
+            for kind in KINDS.iter().rev() {
+                let piece = pieces::from_colour_kind(&side, *kind);
+                for _ in 0..self.count(side, *kind) {
+                    write!(f, "{}", char::from(piece))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
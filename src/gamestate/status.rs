@@ -0,0 +1,311 @@
+use std::fmt;
+
+use crate::{
+    board::{bitboard, colour, pieces},
+    consts,
+    engine::move_gen,
+    traits::static_lookup as PRECOMP,
+};
+
+use super::{boardstate, delta, move_lens, occupancy_layer, variant};
+
+// === Game termination ===
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameStatus {
+    Ongoing,
+    // Holds the winning side
+    Checkmate(colour::Colour<()>),
+    // Won through the selected variant's own rules, e.g. a third check
+    VariantWin(colour::Colour<()>),
+    // Drawn through the variant's own rules, e.g. both kings reaching the goal
+    VariantDraw,
+    Stalemate,
+    InsufficientMaterial,
+    SeventyFiveMoveRule,
+    FivefoldRepetition,
+    // Claimable draws, play continues unless a player claims
+    FiftyMoveRule,
+    ThreefoldRepetition,
+}
+
+impl GameStatus {
+    // The game has ended without either player needing to claim anything
+    pub fn is_over(&self) -> bool {
+        !matches!(
+            self,
+            GameStatus::Ongoing | GameStatus::FiftyMoveRule | GameStatus::ThreefoldRepetition
+        )
+    }
+
+    pub fn is_claimable(&self) -> bool {
+        matches!(self, GameStatus::FiftyMoveRule | GameStatus::ThreefoldRepetition)
+    }
+
+    pub fn winner(&self) -> Option<colour::Colour<()>> {
+        match self {
+            GameStatus::Checkmate(side) | GameStatus::VariantWin(side) => Some(*side),
+            _ => None,
+        }
+    }
+
+    // PGN result tag, claimable draws only count once claimed so stay "*"
+    pub fn result(&self) -> &'static str {
+        match self {
+            GameStatus::Checkmate(colour::Colour::White(()))
+            | GameStatus::VariantWin(colour::Colour::White(())) => "1-0",
+            GameStatus::Checkmate(colour::Colour::Black(()))
+            | GameStatus::VariantWin(colour::Colour::Black(())) => "0-1",
+            GameStatus::Ongoing | GameStatus::FiftyMoveRule | GameStatus::ThreefoldRepetition => {
+                "*"
+            }
+            _ => "1/2-1/2",
+        }
+    }
+}
+
+impl fmt::Display for GameStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameStatus::Ongoing => write!(f, "Ongoing"),
+            GameStatus::Checkmate(colour::Colour::White(())) => write!(f, "Checkmate, White wins"),
+            GameStatus::Checkmate(colour::Colour::Black(())) => write!(f, "Checkmate, Black wins"),
+            GameStatus::VariantWin(colour::Colour::White(())) => write!(f, "Variant win, White wins"),
+            GameStatus::VariantWin(colour::Colour::Black(())) => write!(f, "Variant win, Black wins"),
+            GameStatus::VariantDraw => write!(f, "Variant draw"),
+            GameStatus::Stalemate => write!(f, "Stalemate"),
+            GameStatus::InsufficientMaterial => write!(f, "Insufficient material"),
+            GameStatus::SeventyFiveMoveRule => write!(f, "Seventy-five move rule"),
+            GameStatus::FivefoldRepetition => write!(f, "Fivefold repetition"),
+            GameStatus::FiftyMoveRule => write!(f, "Fifty move rule (claimable)"),
+            GameStatus::ThreefoldRepetition => write!(f, "Threefold repetition (claimable)"),
+        }
+    }
+}
+
+// Status of a State given the deltas of the moves that reached it, oldest first.
+// A variant's own win comes first, then checkmate over the move count rules,
+// the automatic draws over the claimable ones.
+pub fn game_status<A>(
+    state: &boardstate::State,
+    history: &[delta::Delta],
+    lookup: A,
+) -> GameStatus
+where
+    A: PRECOMP::StaticAttack + Copy + 'static,
+{
+    if let Some(winner) = variant::winner(state, lookup) {
+        return GameStatus::VariantWin(winner);
+    }
+    if variant::is_drawn(state, lookup) {
+        return GameStatus::VariantDraw;
+    }
+
+    if !move_gen::has_legal_moves(state, lookup) {
+        return if state.variant == variant::Variant::Antichess {
+            // Having no move to make is a win at Antichess
+            GameStatus::VariantWin(state.side_to_move)
+        } else if boardstate::in_check(state, lookup) {
+            GameStatus::Checkmate(state.side_to_move.opp())
+        } else {
+            GameStatus::Stalemate
+        };
+    }
+
+    if is_insufficient_material(state) {
+        return GameStatus::InsufficientMaterial;
+    }
+
+    let repeats = repetitions(state, history);
+    match (state.half_moves, repeats) {
+        (150.., _) => GameStatus::SeventyFiveMoveRule,
+        (_, 5..) => GameStatus::FivefoldRepetition,
+        (100.., _) => GameStatus::FiftyMoveRule,
+        (_, 3..) => GameStatus::ThreefoldRepetition,
+        _ => GameStatus::Ongoing,
+    }
+}
+
+// How many times the current position has occurred, counting itself.
+// Walks the history backwards until a pawn move or capture,
+// as no earlier position can repeat past one.
+pub fn repetitions(state: &boardstate::State, history: &[delta::Delta]) -> u32 {
+    let mut board = *state;
+    let mut count = 1;
+
+    for step in history.iter().rev() {
+        let irreversible = delta::captured(*step).is_some()
+            || delta::moved_piece(*step).map(|p| pieces::get_kind(&p)) == Some(pieces::Kind::Pawn);
+
+        move_lens::History.unmake_move(&mut board, step);
+        if irreversible {
+            break;
+        }
+        if board.key == state.key {
+            count += 1;
+        }
+    }
+
+    count
+}
+
+// Neither side can ever win under the selected variant
+pub fn is_insufficient_material(state: &boardstate::State) -> bool {
+    match state.variant {
+        variant::Variant::Standard => is_insufficient_mating_material(state),
+        // Any piece can still give checks, only bare kings are a draw.
+        // Atomic kings cannot capture, so neither can they blow each other up.
+        variant::Variant::ThreeCheck | variant::Variant::Atomic => {
+            occupancy_layer::get_both(&state.occupancy_layer).popcount() <= 2
+        }
+        // A king can always walk to the centre
+        variant::Variant::KingOfTheHill => false,
+        // Captures refill the pockets, there is always something to drop
+        variant::Variant::Crazyhouse => false,
+        // Losing everything is the goal, any piece left can still be given away
+        variant::Variant::Antichess => false,
+        // Every white piece can be taken, and a king can always race
+        variant::Variant::Horde | variant::Variant::RacingKings => false,
+    }
+}
+
+// Neither side can ever mate: bare kings, a lone minor piece,
+// or any number of bishops all on the same colour squares
+fn is_insufficient_mating_material(state: &boardstate::State) -> bool {
+    let both = |kind: pieces::Kind| -> bitboard::Bitboard {
+        state.material_layer[pieces::from_colour_kind(&colour::Colour::White(()), kind)]
+            | state.material_layer[pieces::from_colour_kind(&colour::Colour::Black(()), kind)]
+    };
+
+    let heavy = both(pieces::Kind::Pawn) | both(pieces::Kind::Rook) | both(pieces::Kind::Queen);
+    if !heavy.is_empty() {
+        return false;
+    }
+
+    let knights = both(pieces::Kind::Knight);
+    let bishops = both(pieces::Kind::Bishop);
+    if (knights | bishops).popcount() <= 1 {
+        return true;
+    }
+
+    knights.is_empty()
+        && (bishops.is_subset(bitboard::Bitboard(consts::LIGHT_SQUARES))
+            || bishops.is_subset(bitboard::Bitboard(consts::DARK_SQUARES)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{effects::static_attack_provider::StaticAttackProvider, engine::uci};
+
+    fn status(fen: &str) -> GameStatus {
+        game_status(&boardstate::try_from_fen(fen).unwrap(), &[], StaticAttackProvider)
+    }
+
+    // Plays the moves from the start, returning the position and the deltas that reached it
+    fn play(moves: &[&str]) -> (boardstate::State, Vec<delta::Delta>) {
+        let mut state = boardstate::try_from_fen(consts::START_POSITION).unwrap();
+        let history = moves
+            .iter()
+            .map(|text| {
+                let mv = uci::decode(&state, text, false, StaticAttackProvider).unwrap();
+                move_lens::History.make_move(&mut state, mv)
+            })
+            .collect();
+
+        (state, history)
+    }
+
+    #[test]
+    fn checkmate_and_stalemate() {
+        let (state, history) = play(&["f2f3", "e7e5", "g2g4", "d8h4"]);
+        assert_eq!(
+            game_status(&state, &history, StaticAttackProvider),
+            GameStatus::Checkmate(colour::Colour::Black(()))
+        );
+
+        assert_eq!(status("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"), GameStatus::Stalemate);
+    }
+
+    #[test]
+    fn move_count_rules() {
+        assert_eq!(status("4k3/8/8/8/8/8/4P3/4K3 w - - 99 80"), GameStatus::Ongoing);
+        assert_eq!(status("4k3/8/8/8/8/8/4P3/4K3 w - - 100 80"), GameStatus::FiftyMoveRule);
+        assert_eq!(status("4k3/8/8/8/8/8/4P3/4K3 w - - 150 80"), GameStatus::SeventyFiveMoveRule);
+
+        // Mate takes precedence over both
+        assert_eq!(
+            status("7k/6Q1/6K1/8/8/8/8/8 b - - 150 80"),
+            GameStatus::Checkmate(colour::Colour::White(()))
+        );
+    }
+
+    #[test]
+    fn repetition_rules() {
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+
+        let (state, history) = play(&shuffle);
+        assert_eq!(repetitions(&state, &history), 2);
+        assert_eq!(game_status(&state, &history, StaticAttackProvider), GameStatus::Ongoing);
+
+        let (state, history) = play(&shuffle.repeat(2));
+        assert_eq!(repetitions(&state, &history), 3);
+        assert_eq!(game_status(&state, &history, StaticAttackProvider), GameStatus::ThreefoldRepetition);
+
+        let (state, history) = play(&shuffle.repeat(4));
+        assert_eq!(repetitions(&state, &history), 5);
+        assert_eq!(game_status(&state, &history, StaticAttackProvider), GameStatus::FivefoldRepetition);
+
+        // A pawn move in between starts the count again
+        let mut moves = shuffle.repeat(2);
+        moves.extend(["e2e4", "e7e5"]);
+        moves.extend(shuffle);
+        let (state, history) = play(&moves);
+        assert_eq!(repetitions(&state, &history), 2);
+    }
+
+    #[test]
+    fn insufficient_material_in_standard_chess() {
+        let cases = [
+            ("8/8/4k3/8/8/4K3/8/8 w - - 0 1", true),
+            ("8/8/4k3/8/8/4KB2/8/8 w - - 0 1", true),
+            ("8/8/4k3/8/8/4KN2/8/8 w - - 0 1", true),
+            // Bishops all on light squares, then on both colours
+            ("8/8/4k1b1/8/8/4KB2/8/8 w - - 0 1", true),
+            ("8/8/4kb2/8/8/4KB2/8/8 w - - 0 1", false),
+            ("8/8/4k3/8/8/4KNN1/8/8 w - - 0 1", false),
+            ("8/8/4k3/8/8/4K3/4P3/8 w - - 0 1", false),
+            ("8/8/4k3/8/8/4KR2/8/8 w - - 0 1", false),
+        ];
+
+        for (fen, expected) in cases {
+            let state = boardstate::try_from_fen(fen).unwrap();
+            assert_eq!(is_insufficient_material(&state), expected, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn insufficient_material_in_variants() {
+        let bare = "8/8/4k3/8/8/4K3/8/8 w - - 0 1";
+        let knight = "8/8/4k3/8/8/4KN2/8/8 w - - 0 1";
+
+        for rules in variant::Variant::ALL {
+            let drawn = |fen| is_insufficient_material(&boardstate::try_from_fen_variant(fen, rules).unwrap());
+
+            match rules {
+                // A lone knight can still check, or blow something up
+                variant::Variant::ThreeCheck | variant::Variant::Atomic => {
+                    assert!(drawn(bare), "{}", rules);
+                    assert!(!drawn(knight), "{}", rules);
+                }
+                variant::Variant::Standard => {
+                    assert!(drawn(bare) && drawn(knight));
+                }
+                // Bare kings can still reach the centre or the goal, and the rest never run dry
+                _ => {
+                    assert!(!drawn(bare), "{}", rules);
+                    assert!(!drawn(knight), "{}", rules);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::board::{castling, pieces, position};
+
+use super::{boardstate, pocket_layer, zobrist};
+
+// === Board transforms ===
+// Each returns a new State, the original is untouched.
+// Positions that transform into each other should search the same, which makes
+// them handy for symmetry checks and for augmenting training data. Only the
+// colour flip evaluates the same, the piece-square tables and the trapped rook
+// check are not mirror symmetric.
+
+// Flip the board top to bottom and swap the colours of every piece,
+// the result is the same position seen from the other side
+pub fn flip_colours(state: &boardstate::State) -> boardstate::State {
+    let mut flipped = rebuild(state, |pos| pos.flip_vertical(), swap_colour);
+
+    flipped.side_to_move = state.side_to_move.opp();
+    flipped.castling = swap_castling(state.castling);
+    flipped.castling_rooks = castling::CastlingRooks([
+        state.castling_rooks.0[2],
+        state.castling_rooks.0[3],
+        state.castling_rooks.0[0],
+        state.castling_rooks.0[1],
+    ]);
+    flipped.en_passant = state.en_passant.map(|pos| pos.flip_vertical());
+    flipped.checks = [state.checks[1], state.checks[0]];
+    flipped.pockets = pocket_layer::PocketLayer([state.pockets.0[1], state.pockets.0[0]]);
+    flipped.promoted = state.promoted.flip_vertical();
+    flipped.key = zobrist::hash(&flipped);
+
+    flipped
+}
+
+// Mirror the board a file <-> h file.
+// Kings leave their castling squares, so the rights are dropped.
+pub fn mirror(state: &boardstate::State) -> boardstate::State {
+    let mut mirrored = rebuild(state, |pos| pos.flip_horizontal(), |piece| piece);
+
+    mirrored.side_to_move = state.side_to_move;
+    mirrored.castling = castling::CastlingRights::new();
+    mirrored.en_passant = state.en_passant.map(|pos| pos.flip_horizontal());
+    mirrored.checks = state.checks;
+    mirrored.pockets = state.pockets;
+    mirrored.promoted = state.promoted.mirror_horizontal();
+    mirrored.key = zobrist::hash(&mirrored);
+
+    mirrored
+}
+
+// Turn the board half way round and swap colours,
+// the colour flip and the mirror combined
+pub fn rotate(state: &boardstate::State) -> boardstate::State {
+    mirror(&flip_colours(state))
+}
+
+// Places every piece of the state on a fresh board through the given maps,
+// the counters and variant are carried over, everything else is left for the caller
+fn rebuild(
+    state: &boardstate::State,
+    square: impl Fn(position::Position) -> position::Position,
+    piece: impl Fn(pieces::Piece) -> pieces::Piece,
+) -> boardstate::State {
+    let mut board = boardstate::State::default();
+
+    for pos in position::Position::ALL {
+        if let Some(p) = state.mailbox_layer[pos] {
+            board.toggle_piece(piece(p), square(pos));
+        }
+    }
+    board.half_moves = state.half_moves;
+    board.full_moves = state.full_moves;
+    board.variant = state.variant;
+
+    board
+}
+
+fn swap_colour(piece: pieces::Piece) -> pieces::Piece {
+    pieces::from_colour_kind(&pieces::get_colour(&piece).opp(), pieces::get_kind(&piece))
+}
+
+// White rights sit in the low two bits, black in the next two
+fn swap_castling(rights: castling::CastlingRights) -> castling::CastlingRights {
+    castling::CastlingRights(((rights.0 & 0b0011) << 2) | ((rights.0 >> 2) & 0b0011))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{consts, eval::evaluation};
+
+    const POSITIONS: [&str; 4] = [
+        consts::START_POSITION,
+        consts::TRICKY_POSITION,
+        consts::KILLER_POSITION,
+        consts::CMK_POSITION,
+    ];
+
+    #[test]
+    fn evaluation_is_colour_symmetric() {
+        for fen in POSITIONS {
+            let state = boardstate::try_from_fen(fen).expect("Test positions are valid FEN");
+
+            assert_eq!(evaluation::evaluate(&state), evaluation::evaluate(&flip_colours(&state)), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn transforms_undo_themselves() {
+        for fen in POSITIONS {
+            let state = boardstate::try_from_fen(fen).expect("Test positions are valid FEN");
+
+            assert_eq!(flip_colours(&flip_colours(&state)), state, "{}", fen);
+            // Mirroring drops the castling rights, the pieces come back
+            assert_eq!(mirror(&mirror(&state)).mailbox_layer, state.mailbox_layer, "{}", fen);
+            assert_eq!(rotate(&rotate(&state)).mailbox_layer, state.mailbox_layer, "{}", fen);
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use std::fmt;
+
+use crate::{
+    board::{bitboard, colour, pieces, position},
+    engine::{move_gen, movement as MOVE},
+    traits::static_lookup as PRECOMP,
+};
+
+use super::boardstate;
+
+// === Rule sets ===
+// Selected per State, move generation, termination and FEN I/O all consult it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Variant {
+    #[default]
+    Standard,
+    // Giving a third check wins
+    ThreeCheck,
+    // Moving your king onto d4, d5, e4 or e5 wins
+    KingOfTheHill,
+    // Captured pieces join the capturer's pocket and may be dropped back in
+    Crazyhouse,
+    // Captures explode, blowing up the enemy king wins
+    Atomic,
+    // Captures are compulsory, losing every piece or being stalemated wins
+    Antichess,
+    // White's 36 pawns against a normal black army, taking every white piece wins
+    Horde,
+    // No checks, the first king to reach the eighth rank wins
+    RacingKings,
+}
+
+impl Variant {
+    pub const ALL: [Variant; 8] = [
+        Variant::Standard,
+        Variant::ThreeCheck,
+        Variant::KingOfTheHill,
+        Variant::Crazyhouse,
+        Variant::Atomic,
+        Variant::Antichess,
+        Variant::Horde,
+        Variant::RacingKings,
+    ];
+
+    // The name used by the UCI_Variant option
+    pub fn uci_name(&self) -> &'static str {
+        match self {
+            Variant::Standard => "chess",
+            Variant::ThreeCheck => "3check",
+            Variant::KingOfTheHill => "kingofthehill",
+            Variant::Crazyhouse => "crazyhouse",
+            Variant::Atomic => "atomic",
+            Variant::Antichess => "antichess",
+            Variant::Horde => "horde",
+            Variant::RacingKings => "racingkings",
+        }
+    }
+
+    pub fn from_uci_name(name: &str) -> Option<Variant> {
+        Variant::ALL
+            .into_iter()
+            .find(|variant| variant.uci_name().eq_ignore_ascii_case(name))
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Variant::Standard => write!(f, "Standard"),
+            Variant::ThreeCheck => write!(f, "Three-check"),
+            Variant::KingOfTheHill => write!(f, "King of the Hill"),
+            Variant::Crazyhouse => write!(f, "Crazyhouse"),
+            Variant::Atomic => write!(f, "Atomic"),
+            Variant::Antichess => write!(f, "Antichess"),
+            Variant::Horde => write!(f, "Horde"),
+            Variant::RacingKings => write!(f, "Racing Kings"),
+        }
+    }
+}
+
+// Checks needed to win at Three-check
+pub const CHECKS_TO_WIN: u8 = 3;
+
+pub(crate) const CENTRE: u64 = (1u64 << position::Position::D4 as u64)
+    | (1u64 << position::Position::E4 as u64)
+    | (1u64 << position::Position::D5 as u64)
+    | (1u64 << position::Position::E5 as u64);
+
+// === Variant specific endings ===
+// The winner if the variant's own rules have ended the game,
+// checked before checkmate and the draw rules
+pub fn winner<A: PRECOMP::StaticAttack + Copy + 'static>(
+    state: &boardstate::State,
+    lookup: A,
+) -> Option<colour::Colour<()>> {
+    match state.variant {
+        Variant::Standard | Variant::Crazyhouse => None,
+        Variant::ThreeCheck => [colour::Colour::White(()), colour::Colour::Black(())]
+            .into_iter()
+            .find(|side| state.checks[*side] >= CHECKS_TO_WIN),
+        Variant::KingOfTheHill => [colour::Colour::White(()), colour::Colour::Black(())]
+            .into_iter()
+            .find(|side| {
+                state.material_layer[pieces::from_colour_kind(side, pieces::Kind::King)]
+                    .intersects(bitboard::Bitboard(CENTRE))
+            }),
+        // The side whose king is still standing
+        Variant::Atomic => [colour::Colour::White(()), colour::Colour::Black(())]
+            .into_iter()
+            .find(|side| {
+                boardstate::king_position(state, side.opp()).is_none()
+                    && boardstate::king_position(state, *side).is_some()
+            }),
+        // The side that has run out of pieces, being stalemated is left to game_status
+        Variant::Antichess => [colour::Colour::White(()), colour::Colour::Black(())]
+            .into_iter()
+            .find(|side| state.occupancy_layer[*side].is_empty()),
+        // Black wins once the horde is gone
+        Variant::Horde => state.occupancy_layer[colour::Colour::White(())]
+            .is_empty()
+            .then_some(colour::Colour::Black(())),
+        Variant::RacingKings => {
+            let white = in_goal(state, colour::Colour::White(()));
+            let black = in_goal(state, colour::Colour::Black(()));
+            match (white, black) {
+                (true, false) if !can_catch_up(state, lookup) => Some(colour::Colour::White(())),
+                (false, true) => Some(colour::Colour::Black(())),
+                _ => None,
+            }
+        }
+    }
+}
+
+// Ended without a winner under the variant's own rules,
+// in Racing Kings by both kings reaching the eighth rank
+pub fn is_drawn<A: PRECOMP::StaticAttack>(state: &boardstate::State, _lookup: A) -> bool {
+    state.variant == Variant::RacingKings
+        && in_goal(state, colour::Colour::White(()))
+        && in_goal(state, colour::Colour::Black(()))
+}
+
+fn in_goal(state: &boardstate::State, side: colour::Colour<()>) -> bool {
+    boardstate::king_position(state, side).is_some_and(|king| king.rank() == position::Rank::R8)
+}
+
+// White got there first, Black still draws with a legal king move onto the eighth rank.
+// The legal move list is not used as it asks who has won, so the king moves are
+// checked one by one, which also rules out a move that gives check.
+fn can_catch_up<A: PRECOMP::StaticAttack + Copy + 'static>(state: &boardstate::State, lookup: A) -> bool {
+    let black = colour::Colour::Black(());
+    if state.side_to_move != black {
+        return false;
+    }
+
+    let mut scratch = *state;
+    move_gen::generate_moves(state, lookup)
+        .filter(|mv| {
+            MOVE::piece(*mv).map(|p| pieces::get_kind(&p)) == Some(pieces::Kind::King)
+                && MOVE::target(*mv).rank() == position::Rank::R8
+        })
+        .any(|mv| move_gen::is_legal(&mut scratch, mv, lookup))
+}
+
+// === Atomic explosions ===
+// Non-pawn pieces on the squares around a capture, blown up along with the capturer.
+// Slots follow the king table's iteration order from the target.
+pub(crate) fn blast<A: PRECOMP::StaticAttack>(
+    state: &boardstate::State,
+    target: position::Position,
+    capturer: position::Position,
+    lookup: A,
+) -> [Option<pieces::Piece>; 8] {
+    let mut exploded = [None; 8];
+
+    for (slot, pos) in lookup.king(target).into_iter().enumerate() {
+        exploded[slot] = state.mailbox_layer[pos]
+            .filter(|p| pos != capturer && pieces::get_kind(p) != pieces::Kind::Pawn);
+    }
+
+    exploded
+}
+
+// === Bookkeeping after a move ===
+// Called by make/unmake once the pieces are in place.
+// Three-check counts a check whenever the side not to move attacks the enemy king.
+pub(crate) fn after_move<A: PRECOMP::StaticAttack>(
+    state: &mut boardstate::State,
+    mover: colour::Colour<()>,
+    undo: bool,
+    lookup: A,
+) {
+    if state.variant == Variant::ThreeCheck
+        && boardstate::is_king_attacked(state, mover.opp(), lookup)
+    {
+        let checks = &mut state.checks[mover];
+        *checks = if undo {
+            checks.saturating_sub(1)
+        } else {
+            checks.saturating_add(1)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        effects::static_attack_provider::StaticAttackProvider,
+        engine::uci,
+        gamestate::{move_lens, status},
+    };
+
+    fn racing(fen: &str) -> boardstate::State {
+        boardstate::try_from_fen_variant(fen, Variant::RacingKings).unwrap()
+    }
+
+    #[test]
+    fn black_catches_up_onto_a_free_square() {
+        let state = racing("1K6/4k3/8/8/8/8/8/8 b - - 0 1");
+        assert_eq!(winner(&state, StaticAttackProvider), None);
+    }
+
+    #[test]
+    fn the_black_king_does_not_shield_its_own_goal_square() {
+        // f8 is only guarded by the bishop through e7, d8 and e8 by the knights
+        let state = racing("1K6/1N2k3/5N2/8/1B6/8/8/8 b - - 0 1");
+        assert_eq!(winner(&state, StaticAttackProvider), Some(colour::Colour::White(())));
+    }
+
+    #[test]
+    fn catching_up_may_not_give_check() {
+        // Every step onto the eighth rank uncovers the rook on a7
+        let state = racing("8/r3k2K/8/8/8/8/8/8 b - - 0 1");
+        assert!(!can_catch_up(&state, StaticAttackProvider));
+    }
+
+    fn play(state: &mut boardstate::State, text: &str) -> crate::gamestate::delta::Delta {
+        let mv = uci::decode(state, text, false, StaticAttackProvider).unwrap();
+        move_lens::History.make_move(state, mv)
+    }
+
+    #[test]
+    fn three_check_counts_checks_given() {
+        let white = colour::Colour::White(());
+        let mut state = boardstate::try_from_fen_variant(crate::consts::START_POSITION, Variant::ThreeCheck).unwrap();
+
+        play(&mut state, "e2e4");
+        play(&mut state, "d7d5");
+        let check = play(&mut state, "f1b5");
+        assert_eq!(state.checks, [1, 0]);
+        assert_eq!(winner(&state, StaticAttackProvider), None);
+
+        move_lens::History.unmake_move(&mut state, &check);
+        assert_eq!(state.checks, [0, 0]);
+
+        // Lichess counts the checks left, older FENs the checks given
+        let remaining = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1+3 0 2";
+        let given = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2 +2+0";
+        let mut state = boardstate::try_from_fen_variant(remaining, Variant::ThreeCheck).unwrap();
+        assert_eq!(state.checks, [2, 0]);
+        assert_eq!(boardstate::try_from_fen_variant(given, Variant::ThreeCheck).unwrap().checks, [2, 0]);
+
+        play(&mut state, "f1b5");
+        assert_eq!(winner(&state, StaticAttackProvider), Some(white));
+        assert_eq!(status::game_status(&state, &[], StaticAttackProvider), status::GameStatus::VariantWin(white));
+        assert!(!move_gen::has_legal_moves(&state, StaticAttackProvider));
+    }
+
+    #[test]
+    fn king_of_the_hill_is_won_in_the_centre() {
+        let white = colour::Colour::White(());
+        let mut state = boardstate::try_from_fen_variant("k7/8/8/8/8/4K3/8/8 w - - 0 1", Variant::KingOfTheHill).unwrap();
+        assert_eq!(winner(&state, StaticAttackProvider), None);
+
+        play(&mut state, "e3e4");
+        assert_eq!(winner(&state, StaticAttackProvider), Some(white));
+        assert_eq!(status::game_status(&state, &[], StaticAttackProvider), status::GameStatus::VariantWin(white));
+        assert!(!move_gen::has_legal_moves(&state, StaticAttackProvider));
+
+        // Just outside the centre is no win
+        let state = boardstate::try_from_fen_variant("k7/8/8/8/5K2/8/8/8 b - - 0 1", Variant::KingOfTheHill).unwrap();
+        assert_eq!(winner(&state, StaticAttackProvider), None);
+        let state = boardstate::try_from_fen_variant("k7/8/8/3K4/8/8/8/8 b - - 0 1", Variant::KingOfTheHill).unwrap();
+        assert_eq!(winner(&state, StaticAttackProvider), Some(white));
+    }
+}
@@ -0,0 +1,221 @@
+use crate::board::{bitboard, castling, colour, pieces, position};
+
+use super::{boardstate, pocket_layer};
+
+// === Zobrist keys ===
+// Random numbers XORed together to fingerprint a position.
+// Generated at compile time from a fixed seed so keys are stable between runs.
+const SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+// SplitMix64, small and good enough for hashing keys
+const fn split_mix(state: u64) -> (u64, u64) {
+    let next = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = next;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (next, z ^ (z >> 31))
+}
+
+struct Keys {
+    pieces: [[u64; 64]; 12],
+    castling: [u64; 16],
+    en_passant: [u64; 8],
+    side: u64,
+    // Three-check counters, a count of zero hashes to nothing
+    checks: [[u64; 4]; 2],
+    // Crazyhouse pocket counts per side and kind, and promoted piece squares
+    pockets: [[[u64; POCKET_DEPTH]; 5]; 2],
+    promoted: [u64; 64],
+}
+
+// Higher counts share the last key, a pocket never gets near it in practice
+const POCKET_DEPTH: usize = 17;
+
+const fn generate_keys() -> Keys {
+    let mut keys = Keys {
+        pieces: [[0; 64]; 12],
+        castling: [0; 16],
+        en_passant: [0; 8],
+        side: 0,
+        checks: [[0; 4]; 2],
+        pockets: [[[0; POCKET_DEPTH]; 5]; 2],
+        promoted: [0; 64],
+    };
+    let mut state = SEED;
+
+    let mut piece = 0;
+    while piece < 12 {
+        let mut sq = 0;
+        while sq < 64 {
+            let (next, key) = split_mix(state);
+            keys.pieces[piece][sq] = key;
+            state = next;
+            sq += 1;
+        }
+        piece += 1;
+    }
+
+    // One key per right, a set of rights hashes to the XOR of its members
+    let mut single = [0; 4];
+    let mut i = 0;
+    while i < 4 {
+        let (next, key) = split_mix(state);
+        single[i] = key;
+        state = next;
+        i += 1;
+    }
+    let mut rights = 0;
+    while rights < 16 {
+        let mut bit = 0;
+        while bit < 4 {
+            if rights & (1 << bit) != 0 {
+                keys.castling[rights] ^= single[bit];
+            }
+            bit += 1;
+        }
+        rights += 1;
+    }
+
+    let mut file = 0;
+    while file < 8 {
+        let (next, key) = split_mix(state);
+        keys.en_passant[file] = key;
+        state = next;
+        file += 1;
+    }
+
+    let (next, key) = split_mix(state);
+    keys.side = key;
+    state = next;
+
+    let mut side = 0;
+    while side < 2 {
+        let mut count = 1;
+        while count < 4 {
+            let (next, key) = split_mix(state);
+            keys.checks[side][count] = key;
+            state = next;
+            count += 1;
+        }
+        side += 1;
+    }
+
+    let mut side = 0;
+    while side < 2 {
+        let mut kind = 0;
+        while kind < 5 {
+            let mut count = 1;
+            while count < POCKET_DEPTH {
+                let (next, key) = split_mix(state);
+                keys.pockets[side][kind][count] = key;
+                state = next;
+                count += 1;
+            }
+            kind += 1;
+        }
+        side += 1;
+    }
+
+    let mut sq = 0;
+    while sq < 64 {
+        let (next, key) = split_mix(state);
+        keys.promoted[sq] = key;
+        state = next;
+        sq += 1;
+    }
+
+    keys
+}
+
+static KEYS: Keys = generate_keys();
+
+pub fn piece_key(piece: pieces::Piece, pos: position::Position) -> u64 {
+    KEYS.pieces[piece.index()][pos as usize]
+}
+
+pub fn castling_key(rights: castling::CastlingRights) -> u64 {
+    KEYS.castling[(rights.0 & 0xF) as usize]
+}
+
+pub fn side_key(side: colour::Colour<()>) -> u64 {
+    match side {
+        colour::Colour::White(()) => 0,
+        colour::Colour::Black(()) => KEYS.side,
+    }
+}
+
+pub fn checks_key(checks: [u8; 2]) -> u64 {
+    KEYS.checks[0][checks[0].min(3) as usize] ^ KEYS.checks[1][checks[1].min(3) as usize]
+}
+
+// Empty pockets hash to nothing, so other variants are unaffected
+pub fn pockets_key(pockets: &pocket_layer::PocketLayer) -> u64 {
+    let mut key = 0;
+    for (side, counts) in pockets.0.iter().enumerate() {
+        for (kind, count) in counts.iter().enumerate() {
+            key ^= KEYS.pockets[side][kind][(*count as usize).min(POCKET_DEPTH - 1)];
+        }
+    }
+
+    key
+}
+
+pub fn promoted_key(promoted: bitboard::Bitboard) -> u64 {
+    promoted
+        .iter()
+        .fold(0, |acc, pos| acc ^ KEYS.promoted[pos as usize])
+}
+
+// The en passant file only counts when a pawn of the side to move could take,
+// otherwise the same position reached by different move orders would hash apart
+pub fn en_passant_key(state: &boardstate::State) -> u64 {
+    capturable_en_passant(state)
+        .map(|file| KEYS.en_passant[file as usize])
+        .unwrap_or(0)
+}
+
+// File of the en passant square when a pawn is actually beside the pushed pawn
+pub fn capturable_en_passant(state: &boardstate::State) -> Option<position::File> {
+    let target = state.en_passant?;
+
+    let pawn = pieces::from_colour_kind(&state.side_to_move, pieces::Kind::Pawn);
+    // The double pushed pawn sits one rank behind the target, capturers beside it
+    let pushed_rank = position::Rank::R5.relative(state.side_to_move);
+    let can_capture = [-1, 1].into_iter().any(|delta| {
+        target
+            .file()
+            .offset(delta)
+            .map(|file| state.mailbox_layer[position::Position::new(file, pushed_rank)] == Some(pawn))
+            .unwrap_or(false)
+    });
+
+    can_capture.then(|| target.file())
+}
+
+// Hash a position from scratch, make/unmake keeps State::key up to date incrementally
+pub fn hash(state: &boardstate::State) -> u64 {
+    let material = state
+        .mailbox_layer
+        .0
+        .iter()
+        .zip(position::Position::ALL)
+        .filter_map(|(piece, pos)| piece.map(|p| piece_key(p, pos)))
+        .fold(0, |acc, key| acc ^ key);
+
+    material
+        ^ castling_key(state.castling)
+        ^ side_key(state.side_to_move)
+        ^ en_passant_key(state)
+        ^ checks_key(state.checks)
+        ^ pockets_key(&state.pockets)
+        ^ promoted_key(state.promoted)
+}
+
+// Hash of the pawns alone, keys the pawn structure cache in the evaluation
+pub fn pawn_hash(state: &boardstate::State) -> u64 {
+    [colour::Colour::White(()), colour::Colour::Black(())]
+        .into_iter()
+        .map(|side| pieces::from_colour_kind(&side, pieces::Kind::Pawn))
+        .flat_map(|pawn| state.material_layer[pawn].iter().map(move |pos| piece_key(pawn, pos)))
+        .fold(0, |acc, key| acc ^ key)
+}
@@ -9,6 +9,7 @@ pub mod board {
 
 pub mod parsers {
     pub mod fen;
+    pub mod pgn;
     pub mod error;
 }
 
@@ -19,6 +20,9 @@ pub mod engine {
     pub mod attack_masks;
     pub mod move_gen;
     pub mod movement;
+    pub mod perft;
+    pub mod uci;
+    pub mod san;
     pub mod error;
 }
 
@@ -26,8 +30,45 @@ pub mod gamestate {
     pub mod boardstate;
     pub mod occupancy_layer;
     pub mod material_layer;
+    pub mod mailbox_layer;
+    pub mod pocket_layer;
     pub mod delta;
     pub mod move_lens;
+    pub mod zobrist;
+    pub mod status;
+    pub mod game;
+    pub mod error;
+    pub mod transform;
+    pub mod chess960;
+    pub mod variant;
+}
+
+pub mod book {
+    pub mod polyglot;
+    pub mod builder;
+    pub(crate) mod polyglot_keys;
+    pub mod error;
+}
+
+pub mod tablebase {
+    pub mod syzygy;
+    pub mod dtm;
+    pub mod retrograde;
+    pub mod error;
+}
+
+pub mod eval {
+    pub mod score;
+    pub mod params;
+    pub mod evaluation;
+    pub mod endgame;
+    pub mod kpk;
+    pub mod pawns;
+    pub mod king_safety;
+    pub mod mobility;
+    pub mod trace;
+    pub mod tuner;
+    pub mod error;
 }
 
 pub mod traits {
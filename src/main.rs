@@ -1,9 +1,7 @@
 use chess::{
-    engine::
-        movement::Move, gamestate::{
-        boardstate::State,
-        move_lens::History, occupancy_layer::{self, OccupancyLayer},
-    }, parsers::error::Error, traits::lens::MoveLens
+    engine::movement::Move,
+    gamestate::{boardstate::State, game::Game},
+    parsers::error::Error,
 };
 
 fn main() -> Result<(), Error> {
@@ -28,39 +26,14 @@ fn main() -> Result<(), Error> {
     ];
 
     let board = State::new();
-    let lens = History;
-    let (res, deltas) = apply_and_collect_deltas(&lens, board, vienna);
+    let game = Game::from_moves(board, vienna);
 
     println!("{}", board);
-    for d in deltas {
+    for d in game.deltas() {
         print!("{}", d);
     }
-    println!("{}", res);
+    println!("{}", game.state());
+    println!("{}", game);
 
     Ok(())
 }
-
-fn apply_all_moves<S: Copy, M, D, L: MoveLens<S, M, D>>(
-    lens: &L,
-    initial: S,
-    moves: impl IntoIterator<Item = M>,
-) -> S {
-    moves.into_iter().fold(initial, |state, mv| {
-        let (new_state, _) = lens.apply_move(state, mv);
-        new_state
-    })
-}
-
-fn apply_and_collect_deltas<S: Copy, M, D, L: MoveLens<S, M, D>>(
-    lens: &L,
-    initial: S,
-    moves: impl IntoIterator<Item = M>,
-) -> (S, Vec<D>) {
-    moves
-        .into_iter()
-        .fold((initial, Vec::new()), |(state, mut deltas), mv| {
-            let (new_state, delta) = lens.apply_move(state, mv);
-            deltas.push(delta);
-            (new_state, deltas)
-        })
-}
@@ -1,9 +1,12 @@
 use colored::*;
 use std::fmt::{self, Debug, Display};
 
+use crate::gamestate::error as state_error;
+
 pub enum Error {
     Deserialization {input: String, invalid_char: char, pos: usize},
-    Serialization(String)
+    Serialization(String),
+    Invalid {input: String, reason: state_error::Error},
 }
 
 impl std::error::Error for Error {}
@@ -54,6 +57,11 @@ impl Display for Error {
                 Ok(())
             },
             Self::Serialization(msg) => writeln!(f, "{}", msg),
+            Self::Invalid{input, reason} => {
+                writeln!(f, "{}:\n", "Invalid position in FEN string".red())?;
+                writeln!(f, "\t{}", input)?;
+                write!(f, "\t{}", reason)
+            },
         }
     }
 }
@@ -3,11 +3,9 @@ use std::{
     str::Chars,
 };
 
-use itertools::Itertools;
-
 use crate::{
     board::castling, board::colour, board::pieces,
-    board::position, gamestate::boardstate,
+    board::position, gamestate::{boardstate, pocket_layer, variant, zobrist},
 };
 
 #[derive(Debug)]
@@ -16,9 +14,18 @@ enum Token {
     EmptySquares(u32),
     ActiveColour(colour::Colour<()>),
     Castling(castling::Castling),
+    // Shredder-FEN and X-FEN name the castling rook by its file
+    CastlingFile(colour::Colour<()>, position::File),
     Enpassant(Option<position::Position>),
     HalfMove(u32),
     FullMove(u32),
+    // Crazyhouse pieces in hand written as [..] after the board, with where the [ is,
+    // and `~` following a piece that was promoted
+    Pocket(usize, pocket_layer::PocketLayer),
+    Promoted,
+    // Three-check counters, lichess style remaining checks or trailing checks given
+    RemainingChecks(u32, u32),
+    ChecksGiven(u32, u32),
     NextRank,
     NextRegion,
     Err(usize, char),
@@ -50,8 +57,18 @@ impl Region {
 
 // This will need to be a result
 pub(crate) fn parse(input: &str) -> Result<boardstate::State, crate::parsers::error::Error> {
+    parse_variant(input, variant::Variant::Standard)
+}
+
+pub(crate) fn parse_variant(
+    input: &str,
+    rules: variant::Variant,
+) -> Result<boardstate::State, crate::parsers::error::Error> {
     // Init chessboard
-    let mut board: boardstate::State = boardstate::State::default();
+    let mut board = boardstate::State {
+        variant: rules,
+        ..Default::default()
+    };
 
     // Init x & y postions
     // The file counter gets reset each encountered
@@ -65,16 +82,64 @@ pub(crate) fn parse(input: &str) -> Result<boardstate::State, crate::parsers::er
     // === Loop over tokens and configure chessboard ===
     for token in tokens {
         match token {
+            // A ninth rank holds the pockets in the older Crazyhouse format
+            Token::Material(p) if rank >= 8 => {
+                board.pockets.add(pieces::get_colour(&p), pieces::get_kind(&p))
+            }
             Token::Material(p) => {
                 board.material_layer[p].mutate_set_bit(rank * 8 + file);
+                if let Some(pos) = position::Position::from_u32(rank * 8 + file) {
+                    board.mailbox_layer[pos] = Some(p);
+                }
                 file += 1;
             }
             Token::EmptySquares(sq) => file += sq,
+            Token::Pocket(i, pocket) => {
+                for (side, counts) in pocket.0.iter().enumerate() {
+                    for (slot, count) in counts.iter().enumerate() {
+                        let held = &mut board.pockets.0[side][slot];
+                        *held = held.checked_add(*count).ok_or_else(|| {
+                            crate::parsers::error::Error::Deserialization {
+                                input: input.to_owned(),
+                                invalid_char: '[',
+                                pos: i,
+                            }
+                        })?;
+                    }
+                }
+            }
+            Token::Promoted => {
+                if let Some(pos) = (rank * 8 + file)
+                    .checked_sub(1)
+                    .and_then(position::Position::from_u32)
+                {
+                    board.promoted.mutate_set_bit(pos);
+                }
+            }
             Token::ActiveColour(colour) => board.side_to_move = colour,
-            Token::Castling(cr) => board.castling.add_castling_right(cr),
+            Token::Castling(cr) => {
+                if let Some(file) = outermost_rook(&board, cr) {
+                    board.castling_rooks.set_file(cr, file);
+                }
+                board.castling.add_castling_right(cr)
+            }
+            Token::CastlingFile(side, file) => {
+                let cr = castling_from_file(&board, side, file);
+                board.castling_rooks.set_file(cr, file);
+                board.castling.add_castling_right(cr)
+            }
             Token::Enpassant(en) => board.en_passant = en,
             Token::HalfMove(count) => board.half_moves = count,
             Token::FullMove(count) => board.full_moves = count,
+            Token::RemainingChecks(white, black) => {
+                board.checks = [
+                    variant::CHECKS_TO_WIN.saturating_sub(white.min(255) as u8),
+                    variant::CHECKS_TO_WIN.saturating_sub(black.min(255) as u8),
+                ]
+            }
+            Token::ChecksGiven(white, black) => {
+                board.checks = [white.min(255) as u8, black.min(255) as u8]
+            }
             Token::NextRank => {
                 rank += 1;
                 file = 0;
@@ -99,6 +164,85 @@ pub(crate) fn parse(input: &str) -> Result<boardstate::State, crate::parsers::er
         }
     }
 
+    board.key = zobrist::hash(&board);
+
+    debug_assert_eq!(boardstate::check_layers(&board), Ok(()));
+
+    Ok(board)
+}
+
+// === Castling fields ===
+// K, Q, k and q mean the outermost rook on that side of the king (X-FEN),
+// falling back to the standard h and a files when there is no such rook
+fn outermost_rook(board: &boardstate::State, cr: castling::Castling) -> Option<position::File> {
+    let side = cr.side()?;
+    let rank = cr.back_rank()?;
+    let king = boardstate::king_position(board, side).filter(|k| k.rank() == rank)?;
+    let rook = pieces::from_colour_kind(&side, pieces::Kind::Rook);
+
+    let is_rook = |file: &position::File| {
+        board.mailbox_layer[position::Position::new(*file, rank)] == Some(rook)
+    };
+
+    if cr.is_kingside()? {
+        position::File::ALL
+            .into_iter()
+            .rev()
+            .take_while(|file| *file > king.file())
+            .find(is_rook)
+    } else {
+        position::File::ALL
+            .into_iter()
+            .take_while(|file| *file < king.file())
+            .find(is_rook)
+    }
+}
+
+// A rook file names the right on that side of the king
+fn castling_from_file(
+    board: &boardstate::State,
+    side: colour::Colour<()>,
+    file: position::File,
+) -> castling::Castling {
+    let king_file = boardstate::king_position(board, side)
+        .map(|king| king.file())
+        .unwrap_or(position::File::E);
+
+    castling::Castling::for_side(side, file > king_file)
+}
+
+fn castling_char(state: &boardstate::State, cr: castling::Castling, format: CastlingFormat) -> char {
+    let file = state.castling_rooks.file(cr);
+    let letter = match (format, file) {
+        (CastlingFormat::XFen, Some(f)) if outermost_rook(state, cr).is_none_or(|o| o == f) => {
+            match cr {
+                castling::Castling::WK | castling::Castling::RK => 'k',
+                _ => 'q',
+            }
+        }
+        (_, Some(f)) => f.to_char(),
+        (_, None) => '-',
+    };
+
+    match cr.side() {
+        Some(colour::Colour::White(())) => letter.to_ascii_uppercase(),
+        _ => letter,
+    }
+}
+
+// Parses as above, then rejects boards that could not arise in a legal game of the variant
+pub(crate) fn parse_strict(
+    input: &str,
+    rules: variant::Variant,
+) -> Result<boardstate::State, crate::parsers::error::Error> {
+    let board = parse_variant(input, rules)?;
+    board
+        .validate()
+        .map_err(|err| crate::parsers::error::Error::Invalid {
+            input: input.to_owned(),
+            reason: err,
+        })?;
+
     Ok(board)
 }
 
@@ -112,6 +256,11 @@ fn tokenize(input: &str) -> Vec<Token> {
             Region::Boardstate => match character {
                 '0'..='8' => Token::EmptySquares(character.to_digit(10).unwrap_or_default()),
                 '/' => Token::NextRank,
+                '~' => Token::Promoted,
+                '[' => match parse_pocket(&mut chars) {
+                    Some(pocket) => Token::Pocket(i, pocket),
+                    None => Token::Err(i, character),
+                },
                 ' ' => {
                     region.advance();
                     Token::NextRegion
@@ -138,6 +287,14 @@ fn tokenize(input: &str) -> Vec<Token> {
                 'k' => Token::Castling(castling::Castling::RK),
                 'q' => Token::Castling(castling::Castling::RQ),
                 '-' => Token::Castling(castling::Castling::None),
+                'A'..='H' => match position::File::from_char(character.to_ascii_lowercase()) {
+                    Some(file) => Token::CastlingFile(colour::Colour::White(()), file),
+                    None => Token::Err(i, character),
+                },
+                'a'..='h' => match position::File::from_char(character) {
+                    Some(file) => Token::CastlingFile(colour::Colour::Black(()), file),
+                    None => Token::Err(i, character),
+                },
                 ' ' => {
                     region.advance();
                     Token::NextRegion
@@ -173,7 +330,24 @@ fn tokenize(input: &str) -> Vec<Token> {
             Region::HalfMove => match character {
                 '0'..='9' => {
                     let value = parse_number(character, &mut chars);
-                    Token::HalfMove(value)
+                    // A lichess Three-check field sits between en passant and the clocks
+                    if let Some((_, '+')) = chars.peek() {
+                        chars.next();
+                        match chars.next() {
+                            Some((_, c @ '0'..='9')) => {
+                                let black = parse_number(c, &mut chars);
+                                // Stay in this region, the half move clock is next
+                                if let Some((_, ' ')) = chars.peek() {
+                                    chars.next();
+                                }
+                                Token::RemainingChecks(value, black)
+                            }
+                            Some((j, c)) => Token::Err(j, c),
+                            None => Token::Err(i, character),
+                        }
+                    } else {
+                        Token::HalfMove(value)
+                    }
                 }
                 ' ' => {
                     region.advance();
@@ -187,6 +361,11 @@ fn tokenize(input: &str) -> Vec<Token> {
                     let value = parse_number(character, &mut chars);
                     Token::FullMove(value)
                 }
+                // Older Three-check FENs append the checks given, e.g. +1+0
+                '+' => match parse_checks_given(&mut chars) {
+                    Some((white, black)) => Token::ChecksGiven(white, black),
+                    None => Token::Err(i, character),
+                },
                 ' ' => {
                     region.advance();
                     Token::NextRegion
@@ -201,6 +380,41 @@ fn tokenize(input: &str) -> Vec<Token> {
     tokens
 }
 
+// Reads pocket letters up to the closing ']', `-` marks empty pockets
+fn parse_pocket(chars: &mut Peekable<Enumerate<Chars>>) -> Option<pocket_layer::PocketLayer> {
+    let mut pocket = pocket_layer::PocketLayer::new();
+
+    loop {
+        match chars.next()? {
+            (_, ']') => return Some(pocket),
+            (_, '-') => {}
+            (_, c) => {
+                let piece = pieces::Piece::try_from(&c).ok()?;
+                let kind = pieces::get_kind(&piece);
+                // Kings are never held, and no more than a u8 of any kind
+                if kind == pieces::Kind::King || pocket.count(pieces::get_colour(&piece), kind) == u8::MAX {
+                    return None;
+                }
+                pocket.add(pieces::get_colour(&piece), kind);
+            }
+        }
+    }
+}
+
+// Reads `w+b` following a leading '+'
+fn parse_checks_given(chars: &mut Peekable<Enumerate<Chars>>) -> Option<(u32, u32)> {
+    let white = match chars.next() {
+        Some((_, c @ '0'..='9')) => parse_number(c, chars),
+        _ => return None,
+    };
+    let black = match (chars.next(), chars.next()) {
+        (Some((_, '+')), Some((_, c @ '0'..='9'))) => parse_number(c, chars),
+        _ => return None,
+    };
+
+    Some((white, black))
+}
+
 fn parse_number(start: char, chars: &mut Peekable<Enumerate<Chars>>) -> u32 {
     let mut num = String::new();
     num.push(start);
@@ -215,33 +429,56 @@ fn parse_number(start: char, chars: &mut Peekable<Enumerate<Chars>>) -> u32 {
     num.parse().unwrap_or(0)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CastlingFormat {
+    // KQkq unless the rook is not the outermost one, then its file
+    XFen,
+    // Always the rook's file
+    Shredder,
+}
+
 // serialize state to fen
 pub fn serialize(state: boardstate::State) -> Result<String, crate::parsers::error::Error> {
+    serialize_with(state, CastlingFormat::XFen)
+}
+
+// serialize state to Shredder-FEN, castling rights written as rook files
+pub fn serialize_shredder(state: boardstate::State) -> Result<String, crate::parsers::error::Error> {
+    serialize_with(state, CastlingFormat::Shredder)
+}
+
+fn serialize_with(
+    state: boardstate::State,
+    format: CastlingFormat,
+) -> Result<String, crate::parsers::error::Error> {
     let rows = [0..8, 8..16, 16..24, 24..32, 32..40, 40..48, 48..56, 56..64];
 
     let fen_rows = rows
         .into_iter()
         .map(|row| {
             // Get piece at pos
-            row.map(|sq| -> Result<Option<pieces::Piece>, super::error::Error> {
+            row.map(|sq| -> Result<(Option<pieces::Piece>, bool), super::error::Error> {
                 let pos = position::Position::from_u32(sq as u32).ok_or_else(|| {
                     super::error::Error::Serialization(
                         "Invalid boardstate for serialization".to_owned(),
                     )
                 })?;
-                Ok(boardstate::get_piece_at_pos(&state, pos))
+                Ok((
+                    boardstate::get_piece_at_pos(&state, pos),
+                    state.promoted.is_occupied(pos),
+                ))
             })
             .collect::<Result<Vec<_>, _>>()?
             .into_iter()
             // Convert peice to char and space to placeholder '1'
-            .map(|piece| match piece {
-                Some(p) => char::from(p),
-                None => '1',
+            .map(|(piece, promoted)| match piece {
+                Some(p) => (char::from(p), promoted),
+                None => ('1', false),
             })
             // Fold our row chars into fen row string
             .try_fold(
                 (String::new(), 0),
-                |(mut fen, space), c| -> Result<(String, u32), super::error::Error> {
+                |(mut fen, space), (c, promoted)| -> Result<(String, u32), super::error::Error> {
                     match c {
                         '1' => Ok((fen, space + 1)),
                         _p => {
@@ -254,6 +491,9 @@ pub fn serialize(state: boardstate::State) -> Result<String, crate::parsers::err
                                 fen.push(spc);
                             }
                             fen.push(_p);
+                            if promoted {
+                                fen.push('~');
+                            }
                             Ok((fen, 0))
                         }
                     }
@@ -282,27 +522,81 @@ pub fn serialize(state: boardstate::State) -> Result<String, crate::parsers::err
     .to_owned();
 
     let castling = castling::castling_rights_from_bits(state.castling)
-        .map(|cr| match cr {
-            castling::Castling::None => "-",
-            castling::Castling::WK => "K",
-            castling::Castling::WQ => "Q",
-            castling::Castling::RK => "k",
-            castling::Castling::RQ => "q",
-        })
-        .join("");
+        .map(|cr| castling_char(&state, cr, format))
+        .collect::<String>();
+    let castling = if castling.is_empty() {
+        "-".to_owned()
+    } else {
+        castling
+    };
 
     let enpassant = match state.en_passant {
         Some(e) => position::to_string(e),
         None => "-".to_owned(),
     };
 
-    Ok([
-        fen_rows.join("/"),
-        side_to_move,
-        castling,
-        enpassant,
-        format!("{}", state.half_moves),
-        format!("{}", state.full_moves),
-    ]
-    .join(" "))
+    let mut placement = fen_rows.join("/");
+    if state.variant == variant::Variant::Crazyhouse {
+        placement.push_str(&format!("[{}]", state.pockets));
+    }
+
+    let mut fields = vec![placement, side_to_move, castling, enpassant];
+    if state.variant == variant::Variant::ThreeCheck {
+        fields.push(format!(
+            "{}+{}",
+            variant::CHECKS_TO_WIN.saturating_sub(state.checks[0]),
+            variant::CHECKS_TO_WIN.saturating_sub(state.checks[1])
+        ));
+    }
+    fields.push(format!("{}", state.half_moves));
+    fields.push(format!("{}", state.full_moves));
+
+    Ok(fields.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts;
+
+    #[test]
+    fn strict_parsing_follows_the_variant() {
+        assert!(parse_strict(consts::HORDE_POSITION, variant::Variant::Horde).is_ok());
+        assert!(parse_strict(consts::HORDE_POSITION, variant::Variant::Standard).is_err());
+
+        // A dropped pawn makes nine, which only Crazyhouse allows
+        let crowded = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1";
+        assert!(parse_strict(crowded, variant::Variant::Crazyhouse).is_ok());
+        assert!(parse_strict(crowded, variant::Variant::Standard).is_err());
+    }
+
+    #[test]
+    fn pockets_that_overflow_are_errors() {
+        let board = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR";
+        let fen = |pocket: &str| format!("{}{} w KQkq - 0 1", board, pocket);
+        let queens = |count| format!("[{}]", "Q".repeat(count));
+
+        let full = parse_variant(&fen(&queens(255)), variant::Variant::Crazyhouse).unwrap();
+        assert_eq!(full.pockets.count(colour::Colour::White(()), pieces::Kind::Queen), 255);
+
+        for pocket in [queens(256), format!("{}[Q]", queens(255))] {
+            assert!(matches!(
+                parse_variant(&fen(&pocket), variant::Variant::Crazyhouse),
+                Err(crate::parsers::error::Error::Deserialization { invalid_char: '[', .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn crazyhouse_fens_round_trip() {
+        for fen in [
+            "r1bq1r1k/ppppNppp/8/4Q~3/8/8/PPPP1PPP/R1B1K2R[BNbn] b KQ - 0 1",
+            "2k5/8/8/8/8/8/8/4K3[QRBNPqrbnp] w - - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1",
+            "2kr1b~r1/ppp2ppp/8/8/8/8/PPP2PPP/2KR1B~R1[QQPPnn] w - - 12 30",
+        ] {
+            let state = parse_variant(fen, variant::Variant::Crazyhouse).unwrap();
+            assert_eq!(serialize(state).unwrap(), fen);
+        }
+    }
 }
@@ -0,0 +1,191 @@
+use std::{iter::Peekable, str::Chars};
+
+use crate::board::colour;
+
+// === PGN game collections ===
+// Reads tag pairs and the main line of each game. Comments, variations,
+// NAGs and move numbers are skipped, moves are kept as written so they can
+// be decoded against the position they are played in (see engine::san).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<String>,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Outcome {
+    Win(colour::Colour<()>),
+    Draw,
+    #[default]
+    Unknown,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.moves.is_empty()
+    }
+}
+
+// Splits a collection into games, anything unreadable is skipped rather than fatal
+pub fn parse_games(input: &str) -> Vec<PgnGame> {
+    let mut games = Vec::new();
+    let mut game = PgnGame::default();
+    let mut chars = input.chars().peekable();
+    let mut line_start = true;
+
+    while let Some(c) = chars.next() {
+        let escape = line_start;
+        line_start = c == '\n';
+
+        match c {
+            '[' => {
+                // A tag after movetext means the last game never gave its result
+                if !game.moves.is_empty() {
+                    games.push(std::mem::take(&mut game));
+                }
+                if let Some(tag) = parse_tag(&mut chars) {
+                    game.tags.push(tag);
+                }
+            }
+            '{' => skip_comment(&mut chars),
+            // % only escapes a line from the first column
+            ';' => skip_line(&mut chars),
+            '%' if escape => skip_line(&mut chars),
+            '(' => skip_variation(&mut chars),
+            '$' => {
+                while chars.next_if(|c| c.is_ascii_digit()).is_some() {}
+            }
+            c if c.is_whitespace() || matches!(c, ']' | '}' | ')' | '%') => {}
+            c => {
+                let word = read_word(c, &mut chars);
+                if let Some(outcome) = parse_outcome(&word) {
+                    game.outcome = outcome;
+                    games.push(std::mem::take(&mut game));
+                } else if let Some(mv) = strip_move_number(&word) {
+                    game.moves.push(mv.to_string());
+                }
+            }
+        }
+    }
+
+    if !game.is_empty() {
+        games.push(game);
+    }
+
+    games
+}
+
+// [Name "Value"], with \" and \\ escaped inside the value
+fn parse_tag(chars: &mut Peekable<Chars>) -> Option<(String, String)> {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+    let mut name = String::new();
+    while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != '"' && *c != ']') {
+        name.push(c);
+    }
+
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    chars.next_if_eq(&'"')?;
+
+    let mut value = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => value.extend(chars.next()),
+            '"' => break,
+            c => value.push(c),
+        }
+    }
+
+    for c in chars.by_ref() {
+        if c == ']' {
+            break;
+        }
+    }
+
+    Some((name, value))
+}
+
+fn skip_comment(chars: &mut Peekable<Chars>) {
+    for c in chars.by_ref() {
+        if c == '}' {
+            break;
+        }
+    }
+}
+
+// Up to the end of the line, the newline itself is left to start the next one
+fn skip_line(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| *c != '\n').is_some() {}
+}
+
+// Variations nest, and may hold comments with brackets of their own
+fn skip_variation(chars: &mut Peekable<Chars>) {
+    let mut depth = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '{' => skip_comment(chars),
+            ';' => skip_line(chars),
+            _ => {}
+        }
+        if depth == 0 {
+            break;
+        }
+    }
+}
+
+fn read_word(first: char, chars: &mut Peekable<Chars>) -> String {
+    let mut word = first.to_string();
+    while let Some(c) = chars.next_if(|c| !c.is_whitespace() && !"[]{}();$".contains(*c)) {
+        word.push(c);
+    }
+
+    word
+}
+
+fn parse_outcome(word: &str) -> Option<Outcome> {
+    match word {
+        "1-0" => Some(Outcome::Win(colour::Colour::White(()))),
+        "0-1" => Some(Outcome::Win(colour::Colour::Black(()))),
+        "1/2-1/2" => Some(Outcome::Draw),
+        "*" => Some(Outcome::Unknown),
+        _ => None,
+    }
+}
+
+// "12.", "12..." and "12.e4" all number a move, "0-0" is castling
+fn strip_move_number(word: &str) -> Option<&str> {
+    let digits = word.trim_start_matches(|c: char| c.is_ascii_digit());
+    let mv = if digits.len() < word.len() && digits.starts_with('.') {
+        digits.trim_start_matches('.')
+    } else {
+        word
+    };
+
+    (!mv.is_empty()).then_some(mv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_escapes_only_from_the_first_column() {
+        let pgn = "%escaped 1. d4 d5 *\n[Event \"Test\"]\n\n1. e4 % e5 ; comment\n%skipped 2. f4\n2. Nf3 1-0\n";
+        let games = parse_games(pgn);
+
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].tags, vec![("Event".to_string(), "Test".to_string())]);
+        assert_eq!(games[0].moves, vec!["e4", "e5", "Nf3"]);
+        assert_eq!(games[0].outcome, Outcome::Win(colour::Colour::White(())));
+    }
+}
@@ -0,0 +1,475 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    board::{colour, pieces, position},
+    engine::move_gen,
+    gamestate::{boardstate, move_lens, occupancy_layer, transform, variant, zobrist},
+    traits::static_lookup as PRECOMP,
+};
+
+use super::error;
+
+// === Distance to mate tables ===
+// Exact results for endgames of up to four pieces, made by tablebase::retrograde.
+// A table covers one material set, named with the stronger side first (KQvK, KBNvK),
+// positions with the colours the other way round are looked up with the board flipped.
+//
+// Each entry is a byte: 0 for a draw, 255 for squares that are not a legal position,
+// otherwise the plies to mate plus one. Odd plies are wins for the side to move.
+
+pub const MAX_PIECES: usize = 4;
+
+const MAGIC: [u8; 4] = *b"DTM\x01";
+
+pub(crate) const DRAW: u8 = 0;
+pub(crate) const ILLEGAL: u8 = 255;
+
+// Longest mate an entry can hold
+pub(crate) const MAX_PLIES: u32 = 253;
+
+// Name order of the pieces, and the order they are indexed in within a side
+const KINDS: [(pieces::Kind, char); 6] = [
+    (pieces::Kind::King, 'K'),
+    (pieces::Kind::Queen, 'Q'),
+    (pieces::Kind::Rook, 'R'),
+    (pieces::Kind::Bishop, 'B'),
+    (pieces::Kind::Knight, 'N'),
+    (pieces::Kind::Pawn, 'P'),
+];
+
+// Without pawns the first king is kept to a8-d8-d5, one of the eight
+// symmetries of the board takes it there from any square
+const TRIANGLE: [usize; 10] = [0, 1, 2, 3, 9, 10, 11, 18, 19, 27];
+
+// === Results ===
+// Plies until mate with best play from both sides
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dtm {
+    Win(u32),
+    Loss(u32),
+    Draw,
+}
+
+impl Dtm {
+    pub(crate) fn from_entry(entry: u8) -> Option<Self> {
+        match entry {
+            ILLEGAL => None,
+            DRAW => Some(Dtm::Draw),
+            e if e % 2 == 0 => Some(Dtm::Win(e as u32 - 1)),
+            e => Some(Dtm::Loss(e as u32 - 1)),
+        }
+    }
+
+    pub(crate) fn to_entry(self) -> u8 {
+        match self {
+            Dtm::Win(plies) | Dtm::Loss(plies) => plies.min(MAX_PLIES) as u8 + 1,
+            Dtm::Draw => DRAW,
+        }
+    }
+
+    // The same result one ply earlier, from the other side
+    pub fn parent(self) -> Dtm {
+        match self {
+            Dtm::Win(plies) => Dtm::Loss(plies + 1),
+            Dtm::Loss(plies) => Dtm::Win(plies + 1),
+            Dtm::Draw => Dtm::Draw,
+        }
+    }
+
+    // Orders results for the side to move, quicker wins and slower losses first
+    pub fn better_than(self, other: Dtm) -> bool {
+        let score = |dtm: Dtm| match dtm {
+            Dtm::Win(plies) => 1000 - plies as i32,
+            Dtm::Draw => 0,
+            Dtm::Loss(plies) => plies as i32 - 1000,
+        };
+
+        score(self) > score(other)
+    }
+}
+
+// === Material sets ===
+// White's pieces then black's, each side in KQRBNP order
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Material {
+    pieces: Vec<pieces::Piece>,
+}
+
+impl Material {
+    // Reads KQvK, or KQK with the second king starting black's pieces
+    pub fn parse(name: &str) -> Option<Self> {
+        let (white, black) = match name.split_once('v') {
+            Some(sides) => sides,
+            None => name.split_at(name.get(1..)?.find('K')? + 1),
+        };
+
+        let mut list = Vec::new();
+        for (side, text) in [(colour::Colour::White(()), white), (colour::Colour::Black(()), black)] {
+            if text.chars().filter(|c| *c == 'K').count() != 1 {
+                return None;
+            }
+            for (kind, letter) in KINDS {
+                let count = text.chars().filter(|c| *c == letter).count();
+                list.extend(std::iter::repeat_n(pieces::from_colour_kind(&side, kind), count));
+            }
+            if text.chars().any(|c| !KINDS.iter().any(|(_, letter)| *letter == c)) {
+                return None;
+            }
+        }
+
+        Some(Material { pieces: list })
+    }
+
+    // Any list of pieces, put into material order
+    pub fn from_pieces(list: &[pieces::Piece]) -> Self {
+        let order = |p: &pieces::Piece| {
+            let kind = KINDS.iter().position(|(kind, _)| *kind == pieces::get_kind(p));
+            (pieces::get_colour(p) != colour::Colour::White(()), kind)
+        };
+        let mut list = list.to_vec();
+        list.sort_by_key(order);
+
+        Material { pieces: list }
+    }
+
+    pub fn from_state(state: &boardstate::State) -> Self {
+        let mut list = Vec::new();
+        for side in [colour::Colour::White(()), colour::Colour::Black(())] {
+            for (kind, _) in KINDS {
+                let piece = pieces::from_colour_kind(&side, kind);
+                list.extend(std::iter::repeat_n(piece, state.material_layer[piece].count_bits()));
+            }
+        }
+
+        Material { pieces: list }
+    }
+
+    pub fn name(&self) -> String {
+        format!(
+            "{}v{}",
+            self.side(colour::Colour::White(())),
+            self.side(colour::Colour::Black(()))
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.pieces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pieces.is_empty()
+    }
+
+    pub fn has_pawns(&self) -> bool {
+        self.pieces.iter().any(|p| pieces::get_kind(p) == pieces::Kind::Pawn)
+    }
+
+    // The same pieces with the colours swapped
+    pub fn flipped(&self) -> Self {
+        let swap = |p: &pieces::Piece| pieces::from_colour_kind(&pieces::get_colour(p).opp(), pieces::get_kind(p));
+        let (white, black): (Vec<_>, Vec<_>) = self
+            .pieces
+            .iter()
+            .partition(|p| pieces::get_colour(p) == colour::Colour::White(()));
+
+        Material {
+            pieces: black.iter().chain(white.iter()).map(swap).collect(),
+        }
+    }
+
+    // Tables hold the stronger side as white: more pieces, then the heavier pieces
+    pub fn is_canonical(&self) -> bool {
+        let strength = |side: colour::Colour<()>| {
+            let kinds: Vec<usize> = self
+                .pieces
+                .iter()
+                .filter(|p| pieces::get_colour(p) == side)
+                .map(|p| KINDS.iter().position(|(kind, _)| *kind == pieces::get_kind(p)).unwrap_or(0))
+                .collect();
+            (std::cmp::Reverse(kinds.len()), kinds)
+        };
+
+        strength(colour::Colour::White(())) <= strength(colour::Colour::Black(()))
+    }
+
+    pub fn canonical(&self) -> Self {
+        if self.is_canonical() {
+            self.clone()
+        } else {
+            self.flipped()
+        }
+    }
+
+    pub(crate) fn pieces(&self) -> &[pieces::Piece] {
+        &self.pieces
+    }
+
+    fn side(&self, side: colour::Colour<()>) -> String {
+        self.pieces
+            .iter()
+            .filter(|p| pieces::get_colour(p) == side)
+            .map(|p| char::from(*p).to_ascii_uppercase())
+            .collect()
+    }
+
+    // === Indexing ===
+    // Squares are board positions, a8 = 0 to h1 = 63, one per piece in material order.
+    // The first piece, white's king, carries the symmetry: kept to a8-d8-d5
+    // without pawns, to the a-d files with them.
+    fn king_squares(&self) -> usize {
+        if self.has_pawns() {
+            32
+        } else {
+            TRIANGLE.len()
+        }
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        2 * self.king_squares() * 64usize.pow(self.len() as u32 - 1)
+    }
+
+    pub(crate) fn index(&self, squares: &[usize], black_to_move: bool) -> usize {
+        let mut squares = squares.to_vec();
+        let king = squares[0];
+
+        if king & 7 > 3 {
+            squares.iter_mut().for_each(|sq| *sq ^= 7);
+        }
+        if !self.has_pawns() {
+            if squares[0] >> 3 > 3 {
+                squares.iter_mut().for_each(|sq| *sq ^= 56);
+            }
+            if squares[0] >> 3 > squares[0] & 7 {
+                squares.iter_mut().for_each(|sq| *sq = ((*sq >> 3) | (*sq << 3)) & 63);
+            }
+        }
+
+        let king = if self.has_pawns() {
+            (squares[0] >> 3) * 4 + (squares[0] & 7)
+        } else {
+            TRIANGLE.iter().position(|sq| *sq == squares[0]).unwrap_or(0)
+        };
+
+        squares[1..].iter().fold(black_to_move as usize * self.king_squares() + king, |idx, sq| idx * 64 + sq)
+    }
+
+    // The squares and side to move an index stands for
+    pub(crate) fn squares(&self, mut idx: usize) -> (Vec<usize>, bool) {
+        let mut squares = vec![0; self.len()];
+        for sq in squares[1..].iter_mut().rev() {
+            *sq = idx % 64;
+            idx /= 64;
+        }
+
+        let king = idx % self.king_squares();
+        squares[0] = if self.has_pawns() {
+            (king / 4) * 8 + king % 4
+        } else {
+            TRIANGLE[king]
+        };
+
+        (squares, idx >= self.king_squares())
+    }
+
+    // Where each piece stands, None when the state holds other material
+    pub(crate) fn squares_of(&self, state: &boardstate::State) -> Option<Vec<usize>> {
+        let mut layer = state.material_layer;
+        let squares: Option<Vec<usize>> = self
+            .pieces
+            .iter()
+            .map(|piece| {
+                let pos = layer[*piece].get_ls1b()?;
+                layer[*piece].mutate_pop_bit(pos);
+                Some(pos as usize)
+            })
+            .collect();
+
+        layer.iter().all(|bb| bb.is_empty()).then_some(squares?)
+    }
+
+    // A fresh board with these pieces on the given squares
+    pub(crate) fn state(&self, squares: &[usize], black_to_move: bool) -> boardstate::State {
+        let mut state = boardstate::State::default();
+        for (piece, sq) in self.pieces.iter().zip(squares) {
+            state.toggle_piece(*piece, position::Position::ALL[*sq]);
+        }
+        if black_to_move {
+            state.side_to_move = colour::Colour::Black(());
+        }
+        state.key = zobrist::hash(&state);
+
+        state
+    }
+}
+
+// === A single table ===
+pub struct Table {
+    material: Material,
+    entries: Vec<u8>,
+}
+
+impl Table {
+    pub(crate) fn new(material: Material, entries: Vec<u8>) -> Self {
+        Table { material, entries }
+    }
+
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+
+    pub fn name(&self) -> String {
+        self.material.name()
+    }
+
+    // Magic, the length of the name and the name, then one byte per index
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, error::Error> {
+        let corrupt = || error::Error::Corrupt("DTM table".to_string());
+        if bytes.get(0..4) != Some(&MAGIC) {
+            return Err(corrupt());
+        }
+
+        let len = *bytes.get(4).ok_or_else(corrupt)? as usize;
+        let name = bytes
+            .get(5..5 + len)
+            .and_then(|name| std::str::from_utf8(name).ok())
+            .ok_or_else(corrupt)?;
+        let material = Material::parse(name)
+            .filter(|m| m.len() <= MAX_PIECES)
+            .ok_or_else(|| error::Error::Corrupt(name.to_string()))?;
+
+        let entries = &bytes[5 + len..];
+        if entries.len() != material.size() {
+            return Err(error::Error::Corrupt(name.to_string()));
+        }
+
+        Ok(Table::new(material, entries.to_vec()))
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, error::Error> {
+        Table::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let name = self.name();
+        let mut bytes = Vec::with_capacity(5 + name.len() + self.entries.len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(name.len() as u8);
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.extend_from_slice(&self.entries);
+
+        bytes
+    }
+
+    // Written as <name>.dtm inside the directory
+    pub fn save(&self, dir: impl AsRef<Path>) -> Result<(), error::Error> {
+        fs::write(dir.as_ref().join(format!("{}.dtm", self.name())), self.to_bytes())?;
+        Ok(())
+    }
+
+    // None for other material, or a position that could not arise
+    pub fn probe(&self, state: &boardstate::State) -> Option<Dtm> {
+        let black_to_move = state.side_to_move == colour::Colour::Black(());
+
+        if let Some(squares) = self.material.squares_of(state) {
+            return Dtm::from_entry(self.entries[self.material.index(&squares, black_to_move)]);
+        }
+
+        let flipped = transform::flip_colours(state);
+        let squares = self.material.squares_of(&flipped)?;
+        Dtm::from_entry(self.entries[self.material.index(&squares, !black_to_move)])
+    }
+
+    // The longest win in the table
+    pub fn longest_mate(&self) -> Option<u32> {
+        self.entries
+            .iter()
+            .filter_map(|e| match Dtm::from_entry(*e) {
+                Some(Dtm::Win(plies)) => Some(plies),
+                _ => None,
+            })
+            .max()
+    }
+}
+
+// === Tables on disk ===
+pub struct Tablebase {
+    dir: PathBuf,
+    tables: Mutex<HashMap<String, Arc<Table>>>,
+}
+
+impl Tablebase {
+    // Tables are read from the directory the first time they are needed
+    pub fn open(dir: impl AsRef<Path>) -> Self {
+        Tablebase {
+            dir: dir.as_ref().to_path_buf(),
+            tables: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Makes a table available without it being on disk, e.g. straight from the generator
+    pub fn insert(&self, table: Table) {
+        let mut tables = self.tables.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        tables.insert(table.name(), Arc::new(table));
+    }
+
+    // Distance to mate for the side to move. An en passant capture is not part of
+    // the tables, so positions offering one are answered by looking a move ahead.
+    pub fn probe<A>(&self, state: &boardstate::State, lookup: A) -> Result<Dtm, error::Error>
+    where
+        A: PRECOMP::StaticAttack + Copy + 'static,
+    {
+        let pieces = occupancy_layer::get_both(&state.occupancy_layer).count_bits();
+        if state.variant != variant::Variant::Standard {
+            return Err(error::Error::Unsupported(format!("{} positions", state.variant)));
+        } else if state.castling.0 != 0 {
+            return Err(error::Error::Unsupported("castling rights".to_string()));
+        } else if pieces > MAX_PIECES {
+            return Err(error::Error::Unsupported(format!("{} pieces", pieces)));
+        } else if pieces == 2 {
+            return Ok(Dtm::Draw);
+        }
+
+        if zobrist::capturable_en_passant(state).is_some() {
+            let mut best = None;
+            for mv in move_gen::generate_legal_moves(state, lookup) {
+                let mut next = *state;
+                move_lens::History.make_move(&mut next, mv);
+                next.en_passant = None;
+
+                let dtm = self.probe(&next, lookup)?.parent();
+                if best.is_none_or(|best| dtm.better_than(best)) {
+                    best = Some(dtm);
+                }
+            }
+            return Ok(best.unwrap_or(Dtm::Loss(0)));
+        }
+
+        let material = Material::from_state(state).canonical();
+        self.table(&material)?
+            .probe(state)
+            .ok_or_else(|| error::Error::Unsupported("not a legal position".to_string()))
+    }
+
+    fn table(&self, material: &Material) -> Result<Arc<Table>, error::Error> {
+        let name = material.name();
+        let mut tables = self.tables.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(table) = tables.get(&name) {
+            return Ok(table.clone());
+        }
+
+        let path = self.dir.join(format!("{}.dtm", name));
+        if !path.is_file() {
+            return Err(error::Error::MissingTable(format!("{}.dtm", name)));
+        }
+
+        let table = Arc::new(Table::open(path)?);
+        tables.insert(name, table.clone());
+        Ok(table)
+    }
+}
+
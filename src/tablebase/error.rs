@@ -0,0 +1,33 @@
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    // A table file that does not have the expected layout, holds its name
+    Corrupt(String),
+    // No table on disk covers this material, holds the table name
+    MissingTable(String),
+    // Positions the tables cannot answer, e.g. with castling rights or too many pieces
+    Unsupported(String),
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(err) => writeln!(f, "Could not read the tablebase: {}", err)?,
+            Error::Corrupt(name) => writeln!(f, "Table {} is corrupt", name)?,
+            Error::MissingTable(name) => writeln!(f, "No table found for {}", name)?,
+            Error::Unsupported(reason) => writeln!(f, "Position cannot be probed: {}", reason)?,
+        }
+
+        Ok(())
+    }
+}
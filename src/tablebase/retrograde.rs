@@ -0,0 +1,399 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    board::{bitboard, colour, pieces, position},
+    engine::{move_gen, movement as MOVE},
+    gamestate::{boardstate, move_lens},
+    traits::static_lookup as PRECOMP,
+};
+
+use super::{dtm, error};
+
+// A conversion draws or wins, so however the other moves turn out the position is not lost
+const NO_LOSS: u8 = u8::MAX;
+
+const PROMOTIONS: [pieces::Kind; 4] = [
+    pieces::Kind::Queen,
+    pieces::Kind::Rook,
+    pieces::Kind::Bishop,
+    pieces::Kind::Knight,
+];
+
+// === Retrograde generation ===
+// Solves a material set backwards from its mates. Every legal position is visited
+// once with engine::move_gen: mates start the search, and captures and promotions
+// are looked up in the smaller tables they lead to, which are made first.
+// Results then spread one ply at a time by taking moves back, a position is won
+// once any move reaches a lost one, and lost once every move reaches a won one.
+//
+// En passant is left out, a double pawn push is solved as if it could not be taken.
+pub struct Generator<A> {
+    lookup: A,
+    tables: HashMap<String, dtm::Table>,
+}
+
+impl<A> Generator<A>
+where
+    A: PRECOMP::StaticAttack + Copy + 'static,
+{
+    pub fn new(lookup: A) -> Self {
+        Generator {
+            lookup,
+            tables: HashMap::new(),
+        }
+    }
+
+    // Makes the table for a material set such as KQK or KBNvK,
+    // along with every table it converts into
+    pub fn generate(&mut self, name: &str) -> Result<&dtm::Table, error::Error> {
+        let material = dtm::Material::parse(name)
+            .ok_or_else(|| error::Error::Unsupported(format!("material {}", name)))?
+            .canonical();
+        if material.len() > dtm::MAX_PIECES {
+            return Err(error::Error::Unsupported(format!("{} pieces", material.len())));
+        }
+
+        self.solve(&material)?;
+        Ok(&self.tables[&material.name()])
+    }
+
+    pub fn tables(&self) -> impl Iterator<Item = &dtm::Table> {
+        self.tables.values()
+    }
+
+    pub fn into_tables(self) -> Vec<dtm::Table> {
+        self.tables.into_values().collect()
+    }
+
+    // Writes every table made so far into the directory
+    pub fn save(&self, dir: impl AsRef<Path>) -> Result<(), error::Error> {
+        for table in self.tables.values() {
+            table.save(dir.as_ref())?;
+        }
+
+        Ok(())
+    }
+
+    fn solve(&mut self, material: &dtm::Material) -> Result<(), error::Error> {
+        // Bare kings are drawn without a table
+        if material.len() < 3 || self.tables.contains_key(&material.name()) {
+            return Ok(());
+        }
+
+        for next in conversions(material) {
+            self.solve(&next)?;
+        }
+
+        let table = Solver::new(material, self.lookup).run(&self.tables)?;
+        self.tables.insert(material.name(), table);
+
+        Ok(())
+    }
+}
+
+// Material sets one capture or promotion away, with the stronger side as white
+fn conversions(material: &dtm::Material) -> Vec<dtm::Material> {
+    let list = material.pieces();
+    let without = |skip: Option<usize>| -> Vec<pieces::Piece> {
+        list.iter()
+            .enumerate()
+            .filter(|(i, _)| Some(*i) != skip)
+            .map(|(_, p)| *p)
+            .collect()
+    };
+    let is_king = |p: &pieces::Piece| pieces::get_kind(p) == pieces::Kind::King;
+
+    let mut found = Vec::new();
+    for (i, piece) in list.iter().enumerate() {
+        if !is_king(piece) {
+            found.push(without(Some(i)));
+        }
+
+        if pieces::get_kind(piece) != pieces::Kind::Pawn {
+            continue;
+        }
+
+        // Promotions, quietly or taking any enemy piece but the king
+        let side = pieces::get_colour(piece);
+        let captures = list
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| pieces::get_colour(p) != side && !is_king(p))
+            .map(|(j, _)| Some(j));
+        for capture in std::iter::once(None).chain(captures) {
+            for kind in PROMOTIONS {
+                let mut promoted = without(capture);
+                let at = promoted.iter().position(|p| p == piece).unwrap_or(0);
+                promoted[at] = pieces::from_colour_kind(&side, kind);
+                found.push(promoted);
+            }
+        }
+    }
+
+    found
+        .into_iter()
+        .map(|list| dtm::Material::from_pieces(&list).canonical())
+        .collect()
+}
+
+// === Solving one material set ===
+// Works over every placement of the pieces, a symmetric position is solved
+// under each of its squares, and the table keeps one of each at the end
+struct Solver<'a, A> {
+    material: &'a dtm::Material,
+    lookup: A,
+    // Entries as in dtm, with 0 standing for unsolved until the end
+    value: Vec<u8>,
+    // Moves that stay within the material and are not known to lose
+    count: Vec<u8>,
+    // The slowest loss through a conversion, or NO_LOSS
+    floor: Vec<u8>,
+    // Positions waiting to pass their result back, by plies to mate
+    queue: Vec<Vec<u32>>,
+}
+
+impl<'a, A> Solver<'a, A>
+where
+    A: PRECOMP::StaticAttack + Copy + 'static,
+{
+    fn new(material: &'a dtm::Material, lookup: A) -> Self {
+        let size = 2 * 64usize.pow(material.len() as u32);
+        Solver {
+            material,
+            lookup,
+            value: vec![dtm::DRAW; size],
+            count: vec![0; size],
+            floor: vec![0; size],
+            queue: vec![Vec::new(); dtm::MAX_PLIES as usize + 1],
+        }
+    }
+
+    fn run(mut self, tables: &HashMap<String, dtm::Table>) -> Result<dtm::Table, error::Error> {
+        for idx in 0..self.value.len() {
+            self.visit(idx, tables)?;
+        }
+
+        for plies in 0..=dtm::MAX_PLIES {
+            for idx in std::mem::take(&mut self.queue[plies as usize]) {
+                // Overtaken by a quicker win
+                if self.value[idx as usize] == plies as u8 + 1 {
+                    self.retract(idx as usize, plies)?;
+                }
+            }
+        }
+
+        let entries = (0..self.material.size())
+            .map(|idx| {
+                let (squares, black_to_move) = self.material.squares(idx);
+                self.value[self.full_index(&squares, black_to_move)]
+            })
+            .collect();
+
+        Ok(dtm::Table::new(self.material.clone(), entries))
+    }
+
+    // Every square the pieces could stand on, not just the symmetric ones
+    fn full_index(&self, squares: &[usize], black_to_move: bool) -> usize {
+        squares.iter().fold(black_to_move as usize, |idx, sq| idx * 64 + sq)
+    }
+
+    fn full_squares(&self, mut idx: usize) -> (Vec<usize>, bool) {
+        let mut squares = vec![0; self.material.len()];
+        for sq in squares.iter_mut().rev() {
+            *sq = idx % 64;
+            idx /= 64;
+        }
+
+        (squares, idx == 1)
+    }
+
+    // Sets up one position: mates and conversions are known straight away,
+    // the other moves are counted to be resolved later
+    fn visit(&mut self, idx: usize, tables: &HashMap<String, dtm::Table>) -> Result<(), error::Error> {
+        let (squares, black_to_move) = self.full_squares(idx);
+
+        let clash = squares.iter().enumerate().any(|(i, a)| squares[i + 1..].contains(a));
+        let pawn_on_edge = self.material.pieces().iter().zip(&squares).any(|(p, sq)| {
+            pieces::get_kind(p) == pieces::Kind::Pawn && (*sq < 8 || *sq >= 56)
+        });
+        if clash || pawn_on_edge {
+            self.value[idx] = dtm::ILLEGAL;
+            return Ok(());
+        }
+
+        let state = self.material.state(&squares, black_to_move);
+        if boardstate::is_king_attacked(&state, state.side_to_move.opp(), self.lookup) {
+            self.value[idx] = dtm::ILLEGAL;
+            return Ok(());
+        }
+
+        let mut moves = 0;
+        let mut best: Option<dtm::Dtm> = None;
+        for mv in move_gen::generate_legal_moves(&state, self.lookup) {
+            moves += 1;
+            if MOVE::capture(mv).is_none() && MOVE::promotion(mv).is_none() {
+                self.count[idx] += 1;
+                continue;
+            }
+
+            let mut next = state;
+            move_lens::History.make_move(&mut next, mv);
+            let dtm = probe(tables, &next)?.parent();
+            if best.is_none_or(|best| dtm.better_than(best)) {
+                best = Some(dtm);
+            }
+        }
+
+        match best {
+            None if moves == 0 => {
+                if boardstate::in_check(&state, self.lookup) {
+                    self.resolve(idx, 0)?;
+                } else {
+                    self.floor[idx] = NO_LOSS;
+                }
+            }
+            None => {}
+            Some(dtm::Dtm::Win(plies)) => {
+                self.floor[idx] = NO_LOSS;
+                self.resolve(idx, plies)?;
+            }
+            Some(dtm::Dtm::Draw) => self.floor[idx] = NO_LOSS,
+            Some(dtm::Dtm::Loss(plies)) => {
+                self.floor[idx] = plies as u8;
+                if self.count[idx] == 0 {
+                    self.resolve(idx, plies)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resolve(&mut self, idx: usize, plies: u32) -> Result<(), error::Error> {
+        if plies > dtm::MAX_PLIES {
+            return Err(error::Error::Unsupported(format!("mates longer than {} plies", dtm::MAX_PLIES)));
+        }
+
+        // Odd plies end with the side to move giving mate
+        self.value[idx] = if plies % 2 == 1 {
+            dtm::Dtm::Win(plies).to_entry()
+        } else {
+            dtm::Dtm::Loss(plies).to_entry()
+        };
+        self.queue[plies as usize].push(idx as u32);
+        Ok(())
+    }
+
+    // Passes a result back to every position one move earlier
+    fn retract(&mut self, idx: usize, plies: u32) -> Result<(), error::Error> {
+        let (squares, black_to_move) = self.full_squares(idx);
+        let won = plies % 2 == 1;
+
+        for before in self.unmoves(&squares, black_to_move) {
+            let entry = self.value[before];
+            if entry == dtm::ILLEGAL {
+                continue;
+            }
+
+            if !won {
+                // Unsolved, or a slower win through a conversion
+                if entry == dtm::DRAW || (entry.is_multiple_of(2) && entry > plies as u8 + 2) {
+                    self.resolve(before, plies + 1)?;
+                }
+            } else {
+                self.count[before] = self.count[before].saturating_sub(1);
+                if self.count[before] == 0 && self.floor[before] != NO_LOSS && entry == dtm::DRAW {
+                    self.resolve(before, (plies + 1).max(self.floor[before] as u32))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Positions the side that just moved could have come from without a capture or promotion
+    fn unmoves(&self, squares: &[usize], black_to_move: bool) -> Vec<usize> {
+        let mover = if black_to_move {
+            colour::Colour::White(())
+        } else {
+            colour::Colour::Black(())
+        };
+        let occupied = bitboard::Bitboard(squares.iter().fold(0, |bb, sq| bb | 1u64 << sq));
+        let empty = |sq: usize| occupied.0 & (1u64 << sq) == 0;
+
+        let mut found = Vec::new();
+        for (i, piece) in self.material.pieces().iter().enumerate() {
+            if pieces::get_colour(piece) != mover {
+                continue;
+            }
+
+            let pos = position::Position::ALL[squares[i]];
+            let origins = match pieces::get_kind(piece) {
+                pieces::Kind::King => self.lookup.king(pos),
+                pieces::Kind::Knight => self.lookup.knight(pos),
+                pieces::Kind::Bishop => self.lookup.bishop(pos, occupied),
+                pieces::Kind::Rook => self.lookup.rook(pos, occupied),
+                pieces::Kind::Queen => self.lookup.queen(pos, occupied),
+                pieces::Kind::Pawn => {
+                    // White pawns head for a8 = 0, so they came from higher squares
+                    let (back, start_row, double_row) = match mover {
+                        colour::Colour::White(()) => (8i32, 6, 4),
+                        colour::Colour::Black(()) => (-8i32, 1, 3),
+                    };
+                    let one = (squares[i] as i32 + back) as usize;
+                    let mut origins = 0u64;
+                    if (8..56).contains(&one) && empty(one) {
+                        origins |= 1 << one;
+                        let two = (one as i32 + back) as usize;
+                        if squares[i] / 8 == double_row && two / 8 == start_row && empty(two) {
+                            origins |= 1 << two;
+                        }
+                    }
+                    bitboard::Bitboard(origins)
+                }
+            };
+
+            for origin in bitboard::Bitboard(origins.0 & !occupied.0).iter() {
+                let mut before = squares.to_vec();
+                before[i] = origin as usize;
+                found.push(self.full_index(&before, !black_to_move));
+            }
+        }
+
+        found
+    }
+}
+
+// Result of a position in a smaller, finished table
+fn probe(tables: &HashMap<String, dtm::Table>, state: &boardstate::State) -> Result<dtm::Dtm, error::Error> {
+    let material = dtm::Material::from_state(state);
+    if material.len() < 3 {
+        return Ok(dtm::Dtm::Draw);
+    }
+
+    let name = material.canonical().name();
+    tables
+        .get(&name)
+        .ok_or_else(|| error::Error::MissingTable(name.clone()))?
+        .probe(state)
+        .ok_or(error::Error::Corrupt(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effects::static_attack_provider::StaticAttackProvider;
+
+    // Minutes without optimisations, a few seconds with them
+    #[test]
+    #[cfg_attr(debug_assertions, ignore = "slow without --release")]
+    fn longest_mates_match_the_reference() {
+        let mut generator = Generator::new(StaticAttackProvider);
+
+        // In plies, as KBNvK's 65 would be
+        for (name, plies) in [("KQvK", 19), ("KRvK", 31)] {
+            let table = generator.generate(name).unwrap();
+            assert_eq!(table.longest_mate(), Some(plies), "{}", name);
+        }
+    }
+}
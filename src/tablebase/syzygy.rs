@@ -0,0 +1,1304 @@
+use std::{
+    collections::HashMap,
+    fs,
+    ops::Neg,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use once_cell::sync::Lazy;
+
+use crate::{
+    board::{colour, pieces, position},
+    engine::{move_gen, movement as MOVE},
+    gamestate::{boardstate, material_layer, move_lens, occupancy_layer, variant},
+    traits::static_lookup as PRECOMP,
+};
+
+use super::error;
+
+// === Syzygy tablebases ===
+// Probes the WDL (.rtbw) and DTZ (.rtbz) files found in a local directory.
+// A table is read whole the first time it is needed and kept for later probes.
+// The index scheme and decompression follow the reference prober, tbprobe.cpp.
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+// Per table flags
+const STM: u8 = 1;
+const MAPPED: u8 = 2;
+const WIN_PLIES: u8 = 4;
+const LOSS_PLIES: u8 = 8;
+const WIDE: u8 = 16;
+const SINGLE_VALUE: u8 = 128;
+
+// Root moves are ranked on this scale, certain wins at the top and certain losses at the bottom
+const MAX_DTZ: i32 = 1 << 18;
+
+// Piece letters in table name order
+const NAME_ORDER: [(pieces::Kind, char); 6] = [
+    (pieces::Kind::King, 'K'),
+    (pieces::Kind::Queen, 'Q'),
+    (pieces::Kind::Rook, 'R'),
+    (pieces::Kind::Bishop, 'B'),
+    (pieces::Kind::Knight, 'N'),
+    (pieces::Kind::Pawn, 'P'),
+];
+
+// === Results ===
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Wdl {
+    Loss = -2,
+    // Lost, but saved by the fifty-move rule
+    BlessedLoss = -1,
+    Draw = 0,
+    // Won, but not before the fifty-move rule draws it
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Self {
+        match value {
+            i32::MIN..=-2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+
+    fn signum(self) -> i32 {
+        (self as i32).signum()
+    }
+}
+
+impl Neg for Wdl {
+    type Output = Wdl;
+
+    fn neg(self) -> Self::Output {
+        Wdl::from_value(-(self as i32))
+    }
+}
+
+// A legal move at the root with what the tables say about it
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct RootMove {
+    pub mv: MOVE::Move,
+    // Outcome once the fifty-move counter of the root is taken into account
+    pub wdl: Wdl,
+    // Plies to the next capture or pawn move, None when only WDL tables were found
+    pub dtz: Option<i32>,
+    // Higher is better, moves sharing the top rank are all optimal
+    pub rank: i32,
+}
+
+// === Index tables ===
+// Squares here are numbered a1 = 0 to h8 = 63, as in the files.
+struct Encoding {
+    // Squares below the a1-h8 diagonal to 0..28
+    map_b1h1h7: [u64; 64],
+    // The a1-d1-d4 triangle to 0..10, diagonal squares last
+    map_a1d1d4: [u64; 64],
+    // Two kings with the first in the triangle, 462 placements
+    map_kk: [[u64; 64]; 10],
+    binomial: [[u64; 64]; 7],
+    // Pawn squares a2-h7 to 0..48, higher for the edge files and lower ranks
+    map_pawns: [u64; 64],
+    lead_pawn_idx: [[u64; 64]; 6],
+    lead_pawns_size: [[u64; 4]; 6],
+}
+
+const fn rank_of(sq: usize) -> usize {
+    sq >> 3
+}
+
+const fn file_of(sq: usize) -> usize {
+    sq & 7
+}
+
+const fn off_a1h8(sq: usize) -> i32 {
+    rank_of(sq) as i32 - file_of(sq) as i32
+}
+
+const fn edge_distance(file: usize) -> usize {
+    if file < 4 {
+        file
+    } else {
+        7 - file
+    }
+}
+
+static ENCODING: Lazy<Encoding> = Lazy::new(|| {
+    let mut enc = Encoding {
+        map_b1h1h7: [0; 64],
+        map_a1d1d4: [0; 64],
+        map_kk: [[0; 64]; 10],
+        binomial: [[0; 64]; 7],
+        map_pawns: [0; 64],
+        lead_pawn_idx: [[0; 64]; 6],
+        lead_pawns_size: [[0; 4]; 6],
+    };
+
+    let mut code = 0;
+    for sq in 0..64 {
+        if off_a1h8(sq) < 0 {
+            enc.map_b1h1h7[sq] = code;
+            code += 1;
+        }
+    }
+
+    let mut diagonal = Vec::new();
+    code = 0;
+    for sq in 0..28 {
+        if off_a1h8(sq) < 0 && file_of(sq) <= 3 {
+            enc.map_a1d1d4[sq] = code;
+            code += 1;
+        } else if off_a1h8(sq) == 0 && file_of(sq) <= 3 {
+            diagonal.push(sq);
+        }
+    }
+    for sq in diagonal {
+        enc.map_a1d1d4[sq] = code;
+        code += 1;
+    }
+
+    // With the first king on the diagonal the second stays on or below it,
+    // and placements with both on the diagonal are numbered last
+    let mut both_on_diagonal = Vec::new();
+    code = 0;
+    for idx in 0..10 {
+        // b1 is the only square of the triangle mapped to 0, every other square defaults to it
+        for s1 in (0..28).filter(|s1| enc.map_a1d1d4[*s1] == idx && (idx != 0 || *s1 == 1)) {
+            for s2 in 0..64 {
+                let touching = rank_of(s1).abs_diff(rank_of(s2)) <= 1 && file_of(s1).abs_diff(file_of(s2)) <= 1;
+                if touching || (off_a1h8(s1) == 0 && off_a1h8(s2) > 0) {
+                    continue;
+                }
+
+                if off_a1h8(s1) == 0 && off_a1h8(s2) == 0 {
+                    both_on_diagonal.push((idx as usize, s2));
+                } else {
+                    enc.map_kk[idx as usize][s2] = code;
+                    code += 1;
+                }
+            }
+        }
+    }
+    for (idx, s2) in both_on_diagonal {
+        enc.map_kk[idx][s2] = code;
+        code += 1;
+    }
+
+    enc.binomial[0][0] = 1;
+    for n in 1..64 {
+        for k in 0..7.min(n + 1) {
+            enc.binomial[k][n] = if k > 0 { enc.binomial[k - 1][n - 1] } else { 0 }
+                + if k < n { enc.binomial[k][n - 1] } else { 0 };
+        }
+    }
+
+    // The leading pawn is the one with the highest map_pawns, every other pawn
+    // of its group then has fewer squares to choose from
+    let mut available = 48;
+    for lead in 1..6 {
+        for file in 0..4 {
+            let mut idx = 0;
+            for rank in 1..7 {
+                let sq = rank * 8 + file;
+                if lead == 1 {
+                    enc.map_pawns[sq] = available - 1;
+                    enc.map_pawns[sq ^ 7] = available - 2;
+                    available -= 2;
+                }
+                enc.lead_pawn_idx[lead][sq] = idx;
+                idx += enc.binomial[lead - 1][enc.map_pawns[sq] as usize];
+            }
+            enc.lead_pawns_size[lead][file] = idx;
+        }
+    }
+
+    enc
+});
+
+// === Reading the files ===
+fn byte(data: &[u8], at: usize) -> Option<u8> {
+    data.get(at).copied()
+}
+
+fn u16_le(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_le(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+impl TableKind {
+    fn extension(self) -> &'static str {
+        match self {
+            TableKind::Wdl => "rtbw",
+            TableKind::Dtz => "rtbz",
+        }
+    }
+
+    fn magic(self) -> [u8; 4] {
+        match self {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC,
+        }
+    }
+}
+
+// One compressed sub-table, per side to move and leading pawn file.
+// Offsets point into the table's data.
+#[derive(Debug, Clone, Default)]
+struct PairsData {
+    flags: u8,
+    // Piece codes in encoding order, 1-6 white pawn to king and 9-14 black
+    pieces: [u8; 7],
+    // Zero terminated group sizes, and the multiplier of each group in the index
+    group_len: [usize; 8],
+    group_idx: [u64; 8],
+    size_of_block: usize,
+    span: u64,
+    sparse_index_size: usize,
+    blocks_num: usize,
+    block_length_size: usize,
+    max_sym_len: u8,
+    // Holds the value itself for single value tables
+    min_sym_len: u8,
+    lowest_sym: usize,
+    base64: Vec<u64>,
+    symlen: Vec<u8>,
+    btree: usize,
+    sparse_index: usize,
+    block_length: usize,
+    blocks: usize,
+    // DTZ value maps per result, relative to the table's map
+    map_idx: [usize; 4],
+}
+
+impl PairsData {
+    fn set_groups(&mut self, table: &Table, order: [usize; 2], file: usize) {
+        let enc = &*ENCODING;
+        let mut n = 0;
+        let mut first_len: i32 = if table.has_pawns {
+            0
+        } else if table.has_unique_pieces {
+            3
+        } else {
+            2
+        };
+
+        self.group_len[0] = 1;
+        for i in 1..table.piece_count {
+            first_len -= 1;
+            if first_len > 0 || self.pieces[i] == self.pieces[i - 1] {
+                self.group_len[n] += 1;
+            } else {
+                n += 1;
+                self.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        self.group_len[n] = 0;
+
+        // Groups are multiplied in the order the file gives, not the order they appear
+        let pp = table.has_pawns && table.pawn_count[1] > 0;
+        let mut next = if pp { 2 } else { 1 };
+        let mut free_squares = 64 - self.group_len[0] - if pp { self.group_len[1] } else { 0 };
+        let mut idx = 1;
+
+        let mut k = 0;
+        while next < n || k == order[0] || k == order[1] {
+            if k == order[0] {
+                self.group_idx[0] = idx;
+                idx *= if table.has_pawns {
+                    enc.lead_pawns_size[self.group_len[0]][file]
+                } else if table.has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] {
+                self.group_idx[1] = idx;
+                idx *= enc.binomial[self.group_len[1]][48 - self.group_len[0]];
+            } else {
+                self.group_idx[next] = idx;
+                idx *= enc.binomial[self.group_len[next]][free_squares];
+                free_squares -= self.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+
+        self.group_idx[n] = idx;
+    }
+
+    // Reads the Huffman header, returns the offset just past it
+    fn set_sizes(&mut self, data: &[u8], mut at: usize) -> Option<usize> {
+        self.flags = byte(data, at)?;
+        at += 1;
+
+        if self.flags & SINGLE_VALUE != 0 {
+            self.min_sym_len = byte(data, at)?;
+            return Some(at + 1);
+        }
+
+        let terminator = self.group_len.iter().position(|len| *len == 0)?;
+        let tb_size = self.group_idx[terminator];
+
+        self.size_of_block = 1 << byte(data, at)?;
+        self.span = 1 << byte(data, at + 1)?;
+        self.sparse_index_size = tb_size.div_ceil(self.span) as usize;
+        let padding = byte(data, at + 2)? as usize;
+        self.blocks_num = u32_le(data, at + 3)? as usize;
+        self.block_length_size = self.blocks_num + padding;
+        self.max_sym_len = byte(data, at + 7)?;
+        self.min_sym_len = byte(data, at + 8)?;
+        at += 9;
+        self.lowest_sym = at;
+
+        // Canonical Huffman, longer codes have lower values. base64[i] is the
+        // lowest code of length min_sym_len + i, left aligned in 64 bits.
+        let lengths = (self.max_sym_len.checked_sub(self.min_sym_len)? as usize) + 1;
+        self.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            let lowest = u16_le(data, self.lowest_sym + 2 * i)? as u64;
+            let next = u16_le(data, self.lowest_sym + 2 * (i + 1))? as u64;
+            self.base64[i] = self.base64[i + 1].wrapping_add(lowest).wrapping_sub(next) / 2;
+        }
+        for (i, base) in self.base64.iter_mut().enumerate() {
+            *base = base.checked_shl((64 - i - self.min_sym_len as usize) as u32).unwrap_or(0);
+        }
+        at += 2 * lengths;
+
+        let symbols = u16_le(data, at)? as usize;
+        at += 2;
+        self.btree = at;
+        data.get(at..at + 3 * symbols)?;
+
+        // Symbols pair up recursively, symlen is how many values one expands to, minus one
+        self.symlen = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for sym in 0..symbols {
+            if !visited[sym] {
+                self.symlen[sym] = self.set_symlen(data, sym, &mut visited)?;
+            }
+        }
+
+        Some(at + 3 * symbols + (symbols & 1))
+    }
+
+    fn set_symlen(&mut self, data: &[u8], sym: usize, visited: &mut [bool]) -> Option<u8> {
+        visited[sym] = true;
+        let right = self.right(data, sym);
+        if right == 0xFFF {
+            return Some(0);
+        }
+
+        let left = self.left(data, sym);
+        for child in [left, right] {
+            if !*visited.get(child)? {
+                self.symlen[child] = self.set_symlen(data, child, visited)?;
+            }
+        }
+
+        Some(self.symlen[left].wrapping_add(self.symlen[right]).wrapping_add(1))
+    }
+
+    // Each btree node packs two 12 bit symbols, a leaf keeps its value on the left
+    fn left(&self, data: &[u8], sym: usize) -> usize {
+        let at = self.btree + 3 * sym;
+        ((data[at + 1] as usize & 0xF) << 8) | data[at] as usize
+    }
+
+    fn right(&self, data: &[u8], sym: usize) -> usize {
+        let at = self.btree + 3 * sym;
+        ((data[at + 2] as usize) << 4) | (data[at + 1] as usize >> 4)
+    }
+
+    fn decompress(&self, data: &[u8], idx: u64) -> i32 {
+        if self.flags & SINGLE_VALUE != 0 {
+            return self.min_sym_len as i32;
+        }
+
+        // The sparse index points near idx, blocks are walked from there
+        let k = (idx / self.span) as usize;
+        let entry = self.sparse_index + 6 * k;
+        let mut block = u32::from_le_bytes(data[entry..entry + 4].try_into().unwrap()) as usize;
+        let mut offset = u16::from_le_bytes(data[entry + 4..entry + 6].try_into().unwrap()) as i64;
+        offset += (idx % self.span) as i64 - (self.span / 2) as i64;
+
+        let block_length = |block: usize| {
+            let at = self.block_length + 2 * block;
+            u16::from_le_bytes(data[at..at + 2].try_into().unwrap()) as i64
+        };
+        while offset < 0 {
+            block -= 1;
+            offset += block_length(block) + 1;
+        }
+        while offset > block_length(block) {
+            offset -= block_length(block) + 1;
+            block += 1;
+        }
+
+        let mut at = self.blocks + block * self.size_of_block;
+        // The decoder reads ahead, past the end of the last block there is nothing left to use
+        let read_u32 = |at: usize| {
+            data.get(at..at + 4)
+                .map_or(0, |bytes| u32::from_be_bytes(bytes.try_into().unwrap()) as u64)
+        };
+        let mut buf = (read_u32(at) << 32) | read_u32(at + 4);
+        let mut buf_size = 64;
+        at += 8;
+
+        let min = self.min_sym_len as usize;
+        let mut sym;
+        loop {
+            let mut len = 0;
+            while buf < self.base64[len] {
+                len += 1;
+            }
+
+            sym = (buf - self.base64[len]).checked_shr((64 - len - min) as u32).unwrap_or(0) as usize;
+            sym += u16::from_le_bytes(
+                data[self.lowest_sym + 2 * len..self.lowest_sym + 2 * len + 2]
+                    .try_into()
+                    .unwrap(),
+            ) as usize;
+
+            if offset < self.symlen[sym] as i64 + 1 {
+                break;
+            }
+
+            offset -= self.symlen[sym] as i64 + 1;
+            len += min;
+            buf <<= len;
+            buf_size -= len;
+
+            if buf_size <= 32 {
+                buf_size += 32;
+                buf |= read_u32(at) << (64 - buf_size);
+                at += 4;
+            }
+        }
+
+        // Expand the symbol down to the single value at our offset
+        while self.symlen[sym] != 0 {
+            let left = self.left(data, sym);
+            if offset < self.symlen[left] as i64 + 1 {
+                sym = left;
+            } else {
+                offset -= self.symlen[left] as i64 + 1;
+                sym = self.right(data, sym);
+            }
+        }
+
+        self.left(data, sym) as i32
+    }
+}
+
+// === A single table file ===
+struct Table {
+    kind: TableKind,
+    data: Vec<u8>,
+    // The material of the side written first in the name, e.g. KRP for KRPvKP
+    white: String,
+    symmetric: bool,
+    piece_count: usize,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    // Pawns of the leading colour, then of the other
+    pawn_count: [usize; 2],
+    pairs: [[PairsData; 4]; 2],
+    map: usize,
+}
+
+impl Table {
+    fn parse(name: &str, kind: TableKind, data: Vec<u8>) -> Result<Self, error::Error> {
+        let corrupt = || error::Error::Corrupt(format!("{}.{}", name, kind.extension()));
+        let (white, black) = name.split_once('v').ok_or_else(corrupt)?;
+        let count = |side: &str, c: char| side.chars().filter(|p| *p == c).count();
+        let (white_pawns, black_pawns) = (count(white, 'P'), count(black, 'P'));
+
+        // The colour with fewer pawns leads, it compresses better
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+
+        let mut table = Table {
+            kind,
+            data: Vec::new(),
+            white: white.to_string(),
+            symmetric: white == black,
+            piece_count: white.len() + black.len(),
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces: "QRBNP".chars().any(|c| count(white, c) == 1 || count(black, c) == 1),
+            pawn_count: if white_leads {
+                [white_pawns, black_pawns]
+            } else {
+                [black_pawns, white_pawns]
+            },
+            pairs: Default::default(),
+            map: 0,
+        };
+
+        if table.piece_count > 7 || data.get(0..4) != Some(&kind.magic()) {
+            return Err(corrupt());
+        }
+        table.read(&data).ok_or_else(corrupt)?;
+        table.data = data;
+
+        Ok(table)
+    }
+
+    fn read(&mut self, data: &[u8]) -> Option<()> {
+        let flags = byte(data, 4)?;
+        if (flags & 2 != 0) != self.has_pawns || (flags & 1 != 0) == self.symmetric {
+            return None;
+        }
+
+        let sides = if self.kind == TableKind::Wdl && !self.symmetric { 2 } else { 1 };
+        let files = if self.has_pawns { 4 } else { 1 };
+        let pp = self.has_pawns && self.pawn_count[1] > 0;
+        let mut at = 5;
+
+        for file in 0..files {
+            let first = byte(data, at)? as usize;
+            let second = if pp { byte(data, at + 1)? as usize } else { 0xFF };
+            let order = [[first & 0xF, second & 0xF], [first >> 4, second >> 4]];
+            at += 1 + pp as usize;
+
+            for k in 0..self.piece_count {
+                let codes = byte(data, at)?;
+                self.pairs[0][file].pieces[k] = codes & 0xF;
+                self.pairs[1][file].pieces[k] = codes >> 4;
+                at += 1;
+            }
+
+            for (side, order) in order.iter().enumerate().take(sides) {
+                let mut pairs = std::mem::take(&mut self.pairs[side][file]);
+                pairs.set_groups(self, *order, file);
+                self.pairs[side][file] = pairs;
+            }
+        }
+        at += at & 1;
+
+        for file in 0..files {
+            for side in 0..sides {
+                at = self.pairs[side][file].set_sizes(data, at)?;
+            }
+        }
+
+        if self.kind == TableKind::Dtz {
+            at = self.set_dtz_map(data, at, files)?;
+        }
+
+        for file in 0..files {
+            for side in 0..sides {
+                let pairs = &mut self.pairs[side][file];
+                pairs.sparse_index = at;
+                at += 6 * pairs.sparse_index_size;
+            }
+        }
+
+        for file in 0..files {
+            for side in 0..sides {
+                let pairs = &mut self.pairs[side][file];
+                pairs.block_length = at;
+                at += 2 * pairs.block_length_size;
+            }
+        }
+
+        for file in 0..files {
+            for side in 0..sides {
+                let pairs = &mut self.pairs[side][file];
+                // Blocks start on 64 byte boundaries, single value tables have none
+                if pairs.blocks_num > 0 {
+                    at = (at + 0x3F) & !0x3F;
+                }
+                pairs.blocks = at;
+                at += pairs.blocks_num * pairs.size_of_block;
+            }
+        }
+
+        (at <= data.len()).then_some(())
+    }
+
+    fn set_dtz_map(&mut self, data: &[u8], mut at: usize, files: usize) -> Option<usize> {
+        self.map = at;
+
+        for file in 0..files {
+            let flags = self.pairs[0][file].flags;
+            if flags & MAPPED == 0 {
+                continue;
+            }
+
+            if flags & WIDE != 0 {
+                at += at & 1;
+                for i in 0..4 {
+                    self.pairs[0][file].map_idx[i] = (at - self.map) / 2 + 1;
+                    at += 2 * u16_le(data, at)? as usize + 2;
+                }
+            } else {
+                for i in 0..4 {
+                    self.pairs[0][file].map_idx[i] = at - self.map + 1;
+                    at += byte(data, at)? as usize + 1;
+                }
+            }
+        }
+
+        Some(at + (at & 1))
+    }
+
+    fn pairs(&self, stm: usize, file: usize) -> &PairsData {
+        let sides = if self.kind == TableKind::Wdl { 2 } else { 1 };
+        &self.pairs[stm % sides][if self.has_pawns { file } else { 0 }]
+    }
+
+    // DTZ tables hold one side to move, None means the other side is needed
+    fn probe(&self, state: &boardstate::State, wdl: Wdl) -> Option<i32> {
+        let (stm, file, idx) = self.index(state)?;
+
+        if self.kind == TableKind::Dtz {
+            let flags = self.pairs(0, file).flags;
+            let either_side = self.symmetric && !self.has_pawns;
+            if (flags & STM) as usize != stm && !either_side {
+                return None;
+            }
+        }
+
+        Some(self.map_score(file, self.pairs(stm, file).decompress(&self.data, idx), wdl))
+    }
+
+    // The side to move and leading pawn file of the sub-table, and the position's index in it
+    fn index(&self, state: &boardstate::State) -> Option<(usize, usize, u64)> {
+        let enc = &*ENCODING;
+        let black_to_move = state.side_to_move == colour::Colour::Black(());
+
+        // Tables are stored with the first named side as white. Positions with
+        // the colours the other way round, or black to move in a symmetric
+        // table, are looked up with the board flipped.
+        let flip = if self.symmetric {
+            black_to_move
+        } else {
+            side_name(&state.material_layer, colour::Colour::White(())) != self.white
+        };
+        let flip_colour = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let stm = (flip ^ black_to_move) as usize;
+
+        let mut squares = [0usize; 7];
+        let mut codes = [0u8; 7];
+        let mut size = 0;
+        let mut lead_count = 0;
+        let mut file = 0;
+        let mut lead_pawns = crate::board::bitboard::Bitboard::new();
+
+        // Pawn tables are split by the file of the leading pawn
+        if self.has_pawns {
+            let lead = self.pairs[0][0].pieces[0] ^ flip_colour;
+            let lead_colour = if lead & 8 != 0 {
+                colour::Colour::Black(())
+            } else {
+                colour::Colour::White(())
+            };
+            lead_pawns = state.material_layer[pieces::from_colour_kind(&lead_colour, pieces::Kind::Pawn)];
+            for pos in lead_pawns.iter() {
+                squares[size] = square(pos) ^ flip_squares;
+                size += 1;
+            }
+            lead_count = size;
+
+            let leader = (0..lead_count).max_by_key(|i| enc.map_pawns[squares[*i]])?;
+            squares.swap(0, leader);
+            file = edge_distance(file_of(squares[0]));
+        }
+
+        let occupied = occupancy_layer::get_both(&state.occupancy_layer);
+        for pos in (occupied ^ lead_pawns).iter() {
+            let piece = state.mailbox_layer[pos]?;
+            squares[size] = square(pos) ^ flip_squares;
+            codes[size] = piece_code(piece) ^ flip_colour;
+            size += 1;
+        }
+
+        let d = self.pairs(stm, file);
+
+        // Put the pieces in the order the table was encoded in
+        for i in lead_count..size.saturating_sub(1) {
+            if let Some(j) = (i + 1..size).find(|j| d.pieces[i] == codes[*j]) {
+                codes.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+
+        // Mirror the leading piece into files a-d
+        if file_of(squares[0]) > 3 {
+            squares[..size].iter_mut().for_each(|sq| *sq ^= 7);
+        }
+
+        let mut idx = if self.has_pawns {
+            let mut idx = enc.lead_pawn_idx[lead_count][squares[0]];
+            squares[1..lead_count].sort_by_key(|sq| enc.map_pawns[*sq]);
+            for (i, sq) in squares.iter().enumerate().take(lead_count).skip(1) {
+                idx += enc.binomial[i][enc.map_pawns[*sq] as usize];
+            }
+
+            idx
+        } else {
+            // Without pawns the board can also be mirrored into ranks 1-4
+            // and across the a1-h8 diagonal
+            if rank_of(squares[0]) > 3 {
+                squares[..size].iter_mut().for_each(|sq| *sq ^= 56);
+            }
+
+            if let Some(i) = (0..d.group_len[0]).find(|i| off_a1h8(squares[*i]) != 0) {
+                if off_a1h8(squares[i]) > 0 {
+                    squares[i..size]
+                        .iter_mut()
+                        .for_each(|sq| *sq = ((*sq >> 3) | (*sq << 3)) & 63);
+                }
+            }
+
+            self.leading_group_index(&squares)
+        };
+
+        idx *= d.group_idx[0];
+
+        // Remaining groups, each square counted among the squares not already taken
+        let mut start = d.group_len[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = 1;
+        while d.group_len[next] != 0 {
+            let len = d.group_len[next];
+            squares[start..start + len].sort_unstable();
+
+            let mut n = 0;
+            for i in 0..len {
+                let sq = squares[start + i];
+                let adjust = squares[..start].iter().filter(|s| sq > **s).count();
+                let rank_offset = if remaining_pawns { 8 } else { 0 };
+                n += enc.binomial[i + 1][sq - adjust - rank_offset];
+            }
+
+            remaining_pawns = false;
+            idx += n * d.group_idx[next];
+            start += len;
+            next += 1;
+        }
+
+        Some((stm, file, idx))
+    }
+
+    // The leading group of a pawnless table, three unique pieces together or the two kings
+    fn leading_group_index(&self, squares: &[usize; 7]) -> u64 {
+        let enc = &*ENCODING;
+        let [s0, s1, s2] = [squares[0], squares[1], squares[2]];
+
+        if !self.has_unique_pieces {
+            return enc.map_kk[enc.map_a1d1d4[s0] as usize][s1];
+        }
+
+        let adjust1 = (s1 > s0) as u64;
+        let adjust2 = (s2 > s0) as u64 + (s2 > s1) as u64;
+        let (s0, s1, s2) = (s0 as u64, s1 as u64, s2 as u64);
+        let rank = |sq: u64| sq >> 3;
+
+        if off_a1h8(s0 as usize) != 0 {
+            (enc.map_a1d1d4[s0 as usize] * 63 + (s1 - adjust1)) * 62 + s2 - adjust2
+        } else if off_a1h8(s1 as usize) != 0 {
+            (6 * 63 + rank(s0) * 28 + enc.map_b1h1h7[s1 as usize]) * 62 + s2 - adjust2
+        } else if off_a1h8(s2 as usize) != 0 {
+            6 * 63 * 62
+                + 4 * 28 * 62
+                + rank(s0) * 7 * 28
+                + (rank(s1) - adjust1) * 28
+                + enc.map_b1h1h7[s2 as usize]
+        } else {
+            6 * 63 * 62
+                + 4 * 28 * 62
+                + 4 * 7 * 28
+                + rank(s0) * 7 * 6
+                + (rank(s1) - adjust1) * 6
+                + (rank(s2) - adjust2)
+        }
+    }
+
+    fn map_score(&self, file: usize, value: i32, wdl: Wdl) -> i32 {
+        if self.kind == TableKind::Wdl {
+            return value - 2;
+        }
+
+        // Which of the four maps holds each result, indexed by wdl + 2
+        const WDL_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+
+        let d = self.pairs(0, file);
+        let mut value = value;
+        if d.flags & MAPPED != 0 {
+            let at = d.map_idx[WDL_MAP[(wdl as i32 + 2) as usize]] + value as usize;
+            value = if d.flags & WIDE != 0 {
+                u16::from_le_bytes(self.data[self.map + 2 * at..self.map + 2 * at + 2].try_into().unwrap())
+                    as i32
+            } else {
+                self.data[self.map + at] as i32
+            };
+        }
+
+        // Stored in moves unless the table says plies
+        if (wdl == Wdl::Win && d.flags & WIN_PLIES == 0)
+            || (wdl == Wdl::Loss && d.flags & LOSS_PLIES == 0)
+            || wdl == Wdl::CursedWin
+            || wdl == Wdl::BlessedLoss
+        {
+            value *= 2;
+        }
+
+        value + 1
+    }
+}
+
+// Table file squares run from a1 rather than a8
+fn square(pos: position::Position) -> usize {
+    pos as usize ^ 56
+}
+
+fn piece_code(piece: pieces::Piece) -> u8 {
+    let kind = match pieces::get_kind(&piece) {
+        pieces::Kind::Pawn => 1,
+        pieces::Kind::Knight => 2,
+        pieces::Kind::Bishop => 3,
+        pieces::Kind::Rook => 4,
+        pieces::Kind::Queen => 5,
+        pieces::Kind::King => 6,
+    };
+
+    match pieces::get_colour(&piece) {
+        colour::Colour::White(()) => kind,
+        colour::Colour::Black(()) => kind | 8,
+    }
+}
+
+// One side's half of a table name, e.g. KRP
+pub fn side_name(material: &material_layer::MaterialLayer, side: colour::Colour<()>) -> String {
+    NAME_ORDER
+        .iter()
+        .map(|(kind, letter)| {
+            let count = material[pieces::from_colour_kind(&side, *kind)].count_bits();
+            letter.to_string().repeat(count)
+        })
+        .collect()
+}
+
+// The table covering this material, named with white first, e.g. KRPvKP
+pub fn table_name(material: &material_layer::MaterialLayer) -> String {
+    format!(
+        "{}v{}",
+        side_name(material, colour::Colour::White(())),
+        side_name(material, colour::Colour::Black(()))
+    )
+}
+
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => 101,
+        Wdl::BlessedLoss => -101,
+        Wdl::Loss => -1,
+        Wdl::Draw => 0,
+    }
+}
+
+fn is_zeroing(mv: MOVE::Move) -> bool {
+    MOVE::capture(mv).is_some() || MOVE::piece(mv).map(|p| pieces::get_kind(&p)) == Some(pieces::Kind::Pawn)
+}
+
+// === The tablebase ===
+pub struct Tablebase {
+    dir: PathBuf,
+    max_pieces: usize,
+    wdl: Mutex<HashMap<String, Arc<Table>>>,
+    dtz: Mutex<HashMap<String, Arc<Table>>>,
+}
+
+impl Tablebase {
+    // Scans the directory for tables, nothing is read until a probe needs it
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, error::Error> {
+        let mut max_pieces = 0;
+        for entry in fs::read_dir(dir.as_ref())? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "rtbw") {
+                if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    max_pieces = max_pieces.max(stem.chars().filter(|c| *c != 'v').count());
+                }
+            }
+        }
+
+        Ok(Tablebase {
+            dir: dir.as_ref().to_path_buf(),
+            max_pieces,
+            wdl: Mutex::new(HashMap::new()),
+            dtz: Mutex::new(HashMap::new()),
+        })
+    }
+
+    // Most pieces of any WDL table found
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    // Win, draw or loss for the side to move with best play, the fifty-move
+    // rule counted from a fresh counter
+    pub fn probe_wdl<A>(&self, state: &boardstate::State, lookup: A) -> Result<Wdl, error::Error>
+    where
+        A: PRECOMP::StaticAttack + Copy + 'static,
+    {
+        self.check(state)?;
+        Ok(self.search(state, false, lookup)?.0)
+    }
+
+    // Plies to the next capture or pawn move on the way to the result,
+    // positive when winning, negative when losing and 0 for draws.
+    // Values beyond 100 are cursed wins and blessed losses.
+    pub fn probe_dtz<A>(&self, state: &boardstate::State, lookup: A) -> Result<i32, error::Error>
+    where
+        A: PRECOMP::StaticAttack + Copy + 'static,
+    {
+        self.check(state)?;
+        self.dtz(state, lookup)
+    }
+
+    // Every legal move ranked by the tables, taking the root's fifty-move counter into
+    // account. Falls back to WDL ranking when DTZ tables are missing.
+    pub fn root_moves<A>(&self, state: &boardstate::State, lookup: A) -> Result<Vec<RootMove>, error::Error>
+    where
+        A: PRECOMP::StaticAttack + Copy + 'static,
+    {
+        self.check(state)?;
+        match self.root_moves_dtz(state, lookup) {
+            Err(error::Error::MissingTable(_)) => self.root_moves_wdl(state, lookup),
+            ranked => ranked,
+        }
+    }
+
+    // The moves search should restrict itself to once the root is in the tables
+    pub fn filter_root_moves<A>(&self, state: &boardstate::State, lookup: A) -> Result<Vec<MOVE::Move>, error::Error>
+    where
+        A: PRECOMP::StaticAttack + Copy + 'static,
+    {
+        let ranked = self.root_moves(state, lookup)?;
+        let best = ranked.iter().map(|root| root.rank).max().unwrap_or(0);
+
+        Ok(ranked.into_iter().filter(|root| root.rank == best).map(|root| root.mv).collect())
+    }
+
+    fn check(&self, state: &boardstate::State) -> Result<(), error::Error> {
+        let pieces = occupancy_layer::get_both(&state.occupancy_layer).count_bits();
+        if state.variant != variant::Variant::Standard {
+            Err(error::Error::Unsupported(format!("{} positions", state.variant)))
+        } else if state.castling.0 != 0 {
+            Err(error::Error::Unsupported("castling rights".to_string()))
+        } else if pieces > self.max_pieces.max(2) {
+            Err(error::Error::Unsupported(format!("{} pieces", pieces)))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn root_moves_dtz<A>(&self, state: &boardstate::State, lookup: A) -> Result<Vec<RootMove>, error::Error>
+    where
+        A: PRECOMP::StaticAttack + Copy + 'static,
+    {
+        let fifty = state.half_moves as i32;
+
+        move_gen::generate_legal_moves(state, lookup)
+            .map(|mv| {
+                let mut next = *state;
+                move_lens::History.make_move(&mut next, mv);
+
+                let mut dtz = if next.half_moves == 0 {
+                    dtz_before_zeroing(-self.search(&next, false, lookup)?.0)
+                } else if next.half_moves >= 100 && !is_mate(&next, lookup) {
+                    0
+                } else {
+                    let dtz = -self.dtz(&next, lookup)?;
+                    dtz + dtz.signum()
+                };
+
+                if dtz == 2 && is_mate(&next, lookup) {
+                    dtz = 1;
+                }
+
+                // Wins the fifty-move rule cannot touch come first, quickest first,
+                // then cursed wins. Losses mirror that, the longest resistance first.
+                let (wdl, rank) = if dtz > 0 {
+                    if dtz + fifty <= 99 {
+                        (Wdl::Win, MAX_DTZ - dtz)
+                    } else {
+                        (Wdl::CursedWin, MAX_DTZ / 2 - (dtz + fifty))
+                    }
+                } else if dtz < 0 {
+                    if -dtz * 2 + fifty < 100 {
+                        (Wdl::Loss, -MAX_DTZ - dtz)
+                    } else {
+                        (Wdl::BlessedLoss, -MAX_DTZ / 2 + (-dtz + fifty))
+                    }
+                } else {
+                    (Wdl::Draw, 0)
+                };
+
+                Ok(RootMove { mv, wdl, dtz: Some(dtz), rank })
+            })
+            .collect()
+    }
+
+    fn root_moves_wdl<A>(&self, state: &boardstate::State, lookup: A) -> Result<Vec<RootMove>, error::Error>
+    where
+        A: PRECOMP::StaticAttack + Copy + 'static,
+    {
+        const WDL_TO_RANK: [i32; 5] = [-MAX_DTZ, -MAX_DTZ + 101, 0, MAX_DTZ - 101, MAX_DTZ];
+
+        move_gen::generate_legal_moves(state, lookup)
+            .map(|mv| {
+                let mut next = *state;
+                move_lens::History.make_move(&mut next, mv);
+
+                let wdl = if next.half_moves >= 100 && !is_mate(&next, lookup) {
+                    Wdl::Draw
+                } else {
+                    -self.search(&next, false, lookup)?.0
+                };
+
+                Ok(RootMove { mv, wdl, dtz: None, rank: WDL_TO_RANK[(wdl as i32 + 2) as usize] })
+            })
+            .collect()
+    }
+
+    // Tables store "don't care" values where a capture (or for DTZ a pawn move)
+    // decides the result, so those moves are searched and the best result kept.
+    // The flag is set when that best result comes from such a zeroing move.
+    fn search<A>(&self, state: &boardstate::State, zeroing: bool, lookup: A) -> Result<(Wdl, bool), error::Error>
+    where
+        A: PRECOMP::StaticAttack + Copy + 'static,
+    {
+        let moves: Vec<_> = move_gen::generate_legal_moves(state, lookup).collect();
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+
+        for mv in &moves {
+            let pawn = MOVE::piece(*mv).map(|p| pieces::get_kind(&p)) == Some(pieces::Kind::Pawn);
+            if MOVE::capture(*mv).is_none() && (!zeroing || !pawn) {
+                continue;
+            }
+            searched += 1;
+
+            let mut next = *state;
+            move_lens::History.make_move(&mut next, *mv);
+            let value = -self.search(&next, false, lookup)?.0;
+
+            if value > best {
+                best = value;
+                if value >= Wdl::Win {
+                    return Ok((value, true));
+                }
+            }
+        }
+
+        // With every move searched the stored value is not needed, and could be wrong,
+        // e.g. tables know nothing of en passant
+        let no_more_moves = searched > 0 && searched == moves.len();
+        let value = if no_more_moves {
+            best
+        } else {
+            Wdl::from_value(self.probe_table(state, TableKind::Wdl, Wdl::Draw)?.unwrap_or(0))
+        };
+
+        if best >= value {
+            Ok((best, best > Wdl::Draw || no_more_moves))
+        } else {
+            Ok((value, false))
+        }
+    }
+
+    fn dtz<A>(&self, state: &boardstate::State, lookup: A) -> Result<i32, error::Error>
+    where
+        A: PRECOMP::StaticAttack + Copy + 'static,
+    {
+        let (wdl, zeroing_best) = self.search(state, true, lookup)?;
+        if wdl == Wdl::Draw {
+            return Ok(0);
+        }
+
+        // The stored value is meaningless when a zeroing move is best
+        if zeroing_best {
+            return Ok(dtz_before_zeroing(wdl));
+        }
+
+        if let Some(dtz) = self.probe_table(state, TableKind::Dtz, wdl)? {
+            let cursed = matches!(wdl, Wdl::BlessedLoss | Wdl::CursedWin);
+            return Ok((dtz + if cursed { 100 } else { 0 }) * wdl.signum());
+        }
+
+        // The table holds the other side to move, so take the best move one ply on
+        let mut min_dtz = i32::MAX;
+        for mv in move_gen::generate_legal_moves(state, lookup) {
+            let zeroing = is_zeroing(mv);
+            let mut next = *state;
+            move_lens::History.make_move(&mut next, mv);
+
+            let mut dtz = if zeroing {
+                -dtz_before_zeroing(self.search(&next, false, lookup)?.0)
+            } else {
+                -self.dtz(&next, lookup)?
+            };
+
+            if dtz == 1 && is_mate(&next, lookup) {
+                min_dtz = 1;
+            }
+
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+
+            if dtz < min_dtz && dtz.signum() == wdl.signum() {
+                min_dtz = dtz;
+            }
+        }
+
+        // No legal moves, the side to move is mated
+        Ok(if min_dtz == i32::MAX { -1 } else { min_dtz })
+    }
+
+    // None when a DTZ table only holds the other side to move
+    fn probe_table(&self, state: &boardstate::State, kind: TableKind, wdl: Wdl) -> Result<Option<i32>, error::Error> {
+        // Bare kings have no table
+        if occupancy_layer::get_both(&state.occupancy_layer).count_bits() == 2 {
+            return Ok(Some(0));
+        }
+
+        Ok(self.table(&state.material_layer, kind)?.probe(state, wdl))
+    }
+
+    // Tables are named with the stronger side first, whichever colour it has here
+    fn table(&self, material: &material_layer::MaterialLayer, kind: TableKind) -> Result<Arc<Table>, error::Error> {
+        let white = side_name(material, colour::Colour::White(()));
+        let black = side_name(material, colour::Colour::Black(()));
+        let cache = match kind {
+            TableKind::Wdl => &self.wdl,
+            TableKind::Dtz => &self.dtz,
+        };
+        let mut cache = cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        for name in [format!("{}v{}", white, black), format!("{}v{}", black, white)] {
+            if let Some(table) = cache.get(&name) {
+                return Ok(table.clone());
+            }
+
+            let path = self.dir.join(format!("{}.{}", name, kind.extension()));
+            if path.is_file() {
+                let table = Arc::new(Table::parse(&name, kind, fs::read(path)?)?);
+                cache.insert(name, table.clone());
+                return Ok(table);
+            }
+        }
+
+        Err(error::Error::MissingTable(format!("{}v{}.{}", white, black, kind.extension())))
+    }
+}
+
+fn is_mate<A>(state: &boardstate::State, lookup: A) -> bool
+where
+    A: PRECOMP::StaticAttack + Copy + 'static,
+{
+    boardstate::in_check(state, lookup) && !move_gen::has_legal_moves(state, lookup)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn squares_map_to_the_triangles() {
+        let enc = &*ENCODING;
+
+        // 28 squares below the diagonal, numbered in order
+        let below: Vec<u64> = (0..64).filter(|sq| off_a1h8(*sq) < 0).map(|sq| enc.map_b1h1h7[sq]).collect();
+        assert_eq!(below, (0..28).collect::<Vec<_>>());
+
+        // b1 c1 d1 c2 d2 d3, then a1 b2 c3 d4
+        let triangle = [1, 2, 3, 10, 11, 19, 0, 9, 18, 27];
+        for (code, sq) in triangle.into_iter().enumerate() {
+            assert_eq!(enc.map_a1d1d4[sq], code as u64, "square {}", sq);
+        }
+    }
+
+    #[test]
+    fn kings_have_462_placements() {
+        let enc = &*ENCODING;
+        let triangle = [1, 2, 3, 10, 11, 19, 0, 9, 18, 27];
+
+        let mut codes: Vec<u64> = triangle
+            .into_iter()
+            .flat_map(|s1: usize| {
+                (0..64)
+                    .filter(move |s2: &usize| {
+                        let touching = rank_of(s1).abs_diff(rank_of(*s2)) <= 1 && file_of(s1).abs_diff(file_of(*s2)) <= 1;
+                        !touching && (off_a1h8(s1) != 0 || off_a1h8(*s2) <= 0)
+                    })
+                    .map(move |s2| enc.map_kk[enc.map_a1d1d4[s1] as usize][s2])
+            })
+            .collect();
+        codes.sort_unstable();
+
+        assert_eq!(codes, (0..462).collect::<Vec<_>>());
+
+        // The 21 placements with both kings on the diagonal come last, a1 c3 first and d4 h8 final
+        assert_eq!(enc.map_kk[enc.map_a1d1d4[0] as usize][18], 441);
+        assert_eq!(enc.map_kk[enc.map_a1d1d4[27] as usize][63], 461);
+    }
+
+    #[test]
+    fn binomial_is_pascals_triangle() {
+        let enc = &*ENCODING;
+
+        for n in 0..64u64 {
+            let mut expected = 1;
+            for k in 0..7u64 {
+                assert_eq!(enc.binomial[k as usize][n as usize], expected, "{} choose {}", n, k);
+                expected = expected * n.saturating_sub(k) / (k + 1);
+            }
+        }
+
+        assert_eq!(enc.binomial[6][63], 67_945_521);
+        assert_eq!(enc.binomial[3][48], 17_296);
+    }
+
+    #[test]
+    fn lead_pawn_sizes_match_the_reference() {
+        let enc = &*ENCODING;
+
+        assert_eq!(enc.map_pawns[8], 47);
+        assert_eq!(enc.map_pawns[15], 46);
+        assert_eq!(enc.map_pawns[51], 1);
+        assert_eq!(enc.map_pawns[52], 0);
+
+        assert_eq!(
+            enc.lead_pawns_size[1..],
+            [
+                [6, 6, 6, 6],
+                [252, 180, 108, 36],
+                [5201, 2645, 953, 125],
+                [70315, 25375, 5491, 295],
+                [700336, 178696, 23176, 496],
+            ]
+        );
+    }
+}